@@ -1,7 +1,7 @@
 use std::f64::consts::PI;
 
 use glam::dvec3;
-use ray_tracer::{Camera, World, Color, transformations, lights::{Light, PointLight}, ObjLoader, shapes::{Shape, Cube}, Object, Material, pattern::{PatternObject, CheckerPattern, PlainPattern}, Pattern};
+use ray_tracer::{Camera, World, Color, transformations, lights::{Light, PointLight}, ObjLoader, shapes::{Shape, Cube}, Object, Material, pattern::{PatternObject, CheckerPattern, PlainPattern}, Pattern, bvh::SahBuilder};
 
 fn main() {
     let camera = Camera::new(1000, 700, PI / 3.0)
@@ -19,7 +19,12 @@ fn main() {
         Color::white()
     ));
 
-    let obj_loader = ObjLoader::load_from_path("examples/obj/teapot.obj");
+    let obj_loader = ObjLoader::load_from_path_with_logger(
+        "examples/obj/teapot.obj",
+        |message| println!("{}", message)
+    )
+    .unwrap();
+    println!("{:?}", obj_loader.summary());
     let mut objects = obj_loader.objects();
     objects[0] = objects[0].clone()
     .with_scale(0.1, 0.1, 0.1)
@@ -41,7 +46,9 @@ fn main() {
                 )
             )
         )
-    );
+    )
+    .flatten(&SahBuilder::new());
+    println!("{:?}", objects[0].bvh_stats());
 
     let room = Object::new(Shape::Cube(Cube::default()))
     .with_scale(10.0, 10.0, 10.0)
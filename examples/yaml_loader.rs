@@ -1,25 +1,68 @@
-use std::{env, path::PathBuf};
+use std::{env, path::PathBuf, time::Duration};
 
-use ray_tracer::{YamlLoader, World};
+use ray_tracer::{YamlLoader, World, Canvas, progress::{ConsoleObserver, CancellationToken, RenderObserver}};
+
+/// preview that gets refined on disk after each pass
+struct PreviewObserver {
+    export_path: PathBuf,
+    passes: usize,
+}
+
+impl RenderObserver for PreviewObserver {
+    fn render_started(&self) {
+        ConsoleObserver.render_started()
+    }
+
+    fn pass_completed(&self, pass: usize, canvas: &Canvas) {
+        println!("Pass {} of {}", pass, self.passes);
+        canvas.export(&self.export_path).unwrap()
+    }
+
+    fn render_finished(&self, elapsed: Duration) {
+        ConsoleObserver.render_finished(elapsed)
+    }
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        println!("No command argument found. Please specify a yaml file path");
+        println!("No command argument found. Please specify a yaml file path, --sample-counts to also export the number of samples per pixel, --cropped to only export the crop window or --progressive to export a preview after each pass");
         return
     }
     let scene_file = &args[1];
     //let scene_file = "examples/yaml_scenes/shadow-glamour-shot.yml";
 
-    let source = std::fs::read_to_string(scene_file).unwrap();
-    let loader = YamlLoader::load_from_str(source.as_str());
+    let loader = match YamlLoader::load_from_path(scene_file) {
+        Ok(loader) => loader,
+        Err(error) => {
+            println!("Unable to load {}: {}", scene_file, error);
+            return
+        }
+    };
 
     let world = World::new()
         .with_objects(loader.objects().to_owned())
         .with_lights(loader.lights().to_owned());
 
-    let canvas = loader.camera().unwrap().render(&world, 5);
+    let camera = loader.camera().unwrap();
     let mut export_path = PathBuf::from(scene_file);
     export_path.set_extension("png");
-    canvas.export(export_path).unwrap()
+
+    if args.iter().any(|arg| arg == "--progressive") {
+        let observer = PreviewObserver { export_path, passes: camera.samples_per_pixel() };
+        camera.render_progressive(&world, 5, observer.passes, None, &observer, &CancellationToken::new());
+        return
+    }
+
+    let rendering = camera.render_tiles(&world, 5, &ConsoleObserver, &CancellationToken::new());
+    let (canvas, sample_counts) = (rendering.canvas, rendering.sample_counts);
+    match camera.crop() {
+        Some(crop) if args.iter().any(|arg| arg == "--cropped") => canvas.crop(&crop).export(&export_path).unwrap(),
+        _ => canvas.export(&export_path).unwrap(),
+    }
+
+    if args.iter().any(|arg| arg == "--sample-counts") {
+        export_path.set_extension("samples.png");
+        sample_counts.export(export_path).unwrap()
+    }
 }
@@ -1,4 +1,4 @@
-use std::{mem::swap, f64::EPSILON};
+use std::mem::swap;
 
 use glam::{DVec3, DMat4, dvec3};
 
@@ -42,12 +42,37 @@ impl BoundingBox {
         )
     }
 
+    pub fn centroid(&self) -> DVec3 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let size = self.size();
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    /// false for empty boxes and boxes of unbounded shapes
+    pub fn is_finite(&self) -> bool {
+        self.min.is_finite() && self.max.is_finite()
+    }
+
     pub fn merge(self, other: &BoundingBox) -> Self {
         self
         .add_point(other.min)
         .add_point(other.max)
     }
 
+    /// returns the box shared by both boxes, empty when they don't overlap
+    pub fn overlap(&self, other: &BoundingBox) -> Self {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        if min.cmpgt(max).any() {
+            Self::default()
+        } else {
+            Self::new(min, max)
+        }
+    }
+
     pub fn split(&self) -> (BoundingBox, BoundingBox) {
         let size = self.size();
         let dx = size.x;
@@ -63,10 +88,10 @@ impl BoundingBox {
         let mut y1 = self.max.y;
         let mut z1 = self.max.z;
 
-        if f64::abs(greatest - dx) < EPSILON {
+        if f64::abs(greatest - dx) < f64::EPSILON {
             x0 += dx / 2.0;
             x1 = x0;
-        } else if f64::abs(greatest - dy) < EPSILON {
+        } else if f64::abs(greatest - dy) < f64::EPSILON {
             y0 += dy / 2.0;
             y1 = y0;
         } else {
@@ -106,28 +131,45 @@ impl BoundingBox {
         self.contains_point(other.min) && self.contains_point(other.max)
     }
 
+    /// the box is transformed one axis at a time, so that the infinite sides
+    /// of unbounded shapes stay infinite instead of becoming NaN
     pub fn transform(self, matrix: &DMat4) -> Self {
-        let p0 = self.min;
-        let p1 = dvec3(self.max.x, self.min.y, self.min.z);
-        let p2 = dvec3(self.min.x, self.max.y, self.min.z);
-        let p3 = dvec3(self.max.x, self.max.y, self.min.z);
-        let p4 = dvec3(self.min.x, self.min.y, self.max.z);
-        let p5 = dvec3(self.max.x, self.min.y, self.max.z);
-        let p6 = dvec3(self.min.x, self.max.y, self.max.z);
-        let p7 = self.max;
-
-        Self::default()
-        .add_point(matrix.transform_point3(p0))
-        .add_point(matrix.transform_point3(p1))
-        .add_point(matrix.transform_point3(p2))
-        .add_point(matrix.transform_point3(p3))
-        .add_point(matrix.transform_point3(p4))
-        .add_point(matrix.transform_point3(p5))
-        .add_point(matrix.transform_point3(p6))
-        .add_point(matrix.transform_point3(p7))
-    }
-
-    pub fn intersects<'a>(&self, ray: &Ray) -> bool {
+        if self.min.cmpgt(self.max).any() {
+            return self;
+        }
+
+        let mut min = matrix.w_axis.truncate();
+        let mut max = min;
+        for (column, (low, high)) in [
+            (matrix.x_axis, (self.min.x, self.max.x)),
+            (matrix.y_axis, (self.min.y, self.max.y)),
+            (matrix.z_axis, (self.min.z, self.max.z)),
+        ] {
+            for row in 0..3 {
+                let factor = column[row];
+                if factor == 0.0 {
+                    continue;
+                }
+                let (a, b) = (factor * low, factor * high);
+                min[row] += f64::min(a, b);
+                max[row] += f64::max(a, b);
+            }
+        }
+
+        Self::new(min, max)
+    }
+
+    /// a ray entering and leaving at the same distance still hits the box,
+    /// it grazes an edge or crosses a flat box such as the one of a triangle
+    /// lying in an axis plane
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let (tmin, tmax) = self.intersection_range(ray);
+        tmin <= tmax
+    }
+
+    /// distances along the ray where it enters and leaves the box,
+    /// the ray misses the box when the entry is after the exit
+    pub fn intersection_range(&self, ray: &Ray) -> (f64, f64) {
         fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
             let tmin_numerator = min - origin;
             let tmax_numerator = max - origin;
@@ -156,14 +198,14 @@ impl BoundingBox {
         let tmin = f64::max(xtmin, f64::max(ytmin, ztmin));
         let tmax = f64::min(xtmax, f64::min(ytmax, ztmax));
 
-        tmin < tmax
+        (tmin, tmax)
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use std::f64::{INFINITY, NEG_INFINITY, consts::PI};
+    use std::f64::consts::PI;
 
     const EPSILON: f64 = 0.0001;
 
@@ -177,8 +219,8 @@ mod tests {
     fn creating_an_empty_bounding_box() {
         let bb = BoundingBox::default();
 
-        assert_eq!(bb.min(), dvec3(INFINITY, INFINITY, INFINITY));
-        assert_eq!(bb.max(), dvec3(NEG_INFINITY, NEG_INFINITY, NEG_INFINITY));
+        assert_eq!(bb.min(), dvec3(f64::INFINITY, f64::INFINITY, f64::INFINITY));
+        assert_eq!(bb.max(), dvec3(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY));
     }
 
     #[test]
@@ -215,21 +257,39 @@ mod tests {
         assert_eq!(bb.max(), dvec3(14.0, 4.0, 8.0));
     }
 
+    #[test]
+    fn overlapping_two_bounding_boxes() {
+        let bb = BoundingBox::new(dvec3(-1.0, -1.0, -1.0), dvec3(2.0, 2.0, 2.0));
+        let datas = vec![
+            (dvec3(0.0, 0.0, 0.0), dvec3(3.0, 3.0, 3.0), dvec3(0.0, 0.0, 0.0), dvec3(2.0, 2.0, 2.0)),
+            (dvec3(-2.0, 0.5, -2.0), dvec3(0.0, 1.0, 3.0), dvec3(-1.0, 0.5, -1.0), dvec3(0.0, 1.0, 2.0)),
+        ];
+
+        for data in datas {
+            let overlap = bb.overlap(&BoundingBox::new(data.0, data.1));
+            assert_eq!(overlap.min(), data.2);
+            assert_eq!(overlap.max(), data.3);
+        }
+
+        let overlap = bb.overlap(&BoundingBox::new(dvec3(3.0, 3.0, 3.0), dvec3(4.0, 4.0, 4.0)));
+        assert_eq!(overlap, BoundingBox::default());
+    }
+
     #[test]
     fn checking_if_a_box_contains_a_given_point() {
         let bb = BoundingBox::default()
             .add_point(dvec3(5.0, -2.0, 0.0))
             .add_point(dvec3(11.0, 4.0, 7.0));
 
-        assert_eq!(bb.contains_point(dvec3(5.0, -2.0, 0.0)), true);
-        assert_eq!(bb.contains_point(dvec3(11.0, 4.0, 0.0)), true);
-        assert_eq!(bb.contains_point(dvec3(8.0, 1.0, 3.0)), true);
-        assert_eq!(bb.contains_point(dvec3(3.0, 0.0, 3.0)), false);
-        assert_eq!(bb.contains_point(dvec3(8.0, -4.0, 3.0)), false);
-        assert_eq!(bb.contains_point(dvec3(8.0, 1.0, -1.0)), false);
-        assert_eq!(bb.contains_point(dvec3(13.0, 1.0, 3.0)), false);
-        assert_eq!(bb.contains_point(dvec3(8.0, 5.0, 3.0)), false);
-        assert_eq!(bb.contains_point(dvec3(8.0, 1.0, 8.0)), false);
+        assert!(bb.contains_point(dvec3(5.0, -2.0, 0.0)));
+        assert!(bb.contains_point(dvec3(11.0, 4.0, 0.0)));
+        assert!(bb.contains_point(dvec3(8.0, 1.0, 3.0)));
+        assert!(!bb.contains_point(dvec3(3.0, 0.0, 3.0)));
+        assert!(!bb.contains_point(dvec3(8.0, -4.0, 3.0)));
+        assert!(!bb.contains_point(dvec3(8.0, 1.0, -1.0)));
+        assert!(!bb.contains_point(dvec3(13.0, 1.0, 3.0)));
+        assert!(!bb.contains_point(dvec3(8.0, 5.0, 3.0)));
+        assert!(!bb.contains_point(dvec3(8.0, 1.0, 8.0)));
     }
 
     #[test]
@@ -272,6 +332,25 @@ mod tests {
         assert!(bb.max.abs_diff_eq(dvec3(1.4142, 1.7071, 1.7071), EPSILON));
     }
 
+    #[test]
+    fn transforming_an_unbounded_bounding_box() {
+        let plane = BoundingBox::new(
+            dvec3(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY),
+            dvec3(f64::INFINITY, 0.0, f64::INFINITY)
+        );
+        let bb = plane.transform(&Transform::new().with_translation(1.0, 2.0, 3.0).matrix);
+        assert_eq!(bb.min, dvec3(f64::NEG_INFINITY, 2.0, f64::NEG_INFINITY));
+        assert_eq!(bb.max, dvec3(f64::INFINITY, 2.0, f64::INFINITY));
+
+        let bb = plane.transform(&Transform::new().with_rotation_z(PI / 2.0).matrix);
+        assert_eq!(bb.min.y, f64::NEG_INFINITY);
+        assert_eq!(bb.max.z, f64::INFINITY);
+        assert!(!bb.min.is_nan() && !bb.max.is_nan());
+
+        let empty = BoundingBox::default();
+        assert_eq!(empty.transform(&Transform::new().with_scale(2.0, 2.0, 2.0).matrix), empty);
+    }
+
     #[test]
     fn querying_a_shape_bounding_box_in_its_parent_space() {
         let s = Object::new(Shape::Sphere(Sphere::default()))
@@ -346,6 +425,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn a_ray_grazing_a_bounding_box_intersects_it() {
+        let bb = BoundingBox::new(
+            dvec3(-1.0, -1.0, -1.0),
+            dvec3(1.0, 1.0, 1.0)
+        );
+
+        // enters and leaves the box on its edge at x = 1, y = 1
+        let r = Ray::new(dvec3(2.0, 0.0, 0.0), dvec3(-1.0, 1.0, 0.0));
+        assert_eq!(bb.intersection_range(&r), (1.0, 1.0));
+        assert!(bb.intersects(&r));
+
+        // slides along the face at y = 1
+        let r = Ray::new(dvec3(-5.0, 1.0, 0.0), dvec3(1.0, 0.0, 0.0));
+        assert!(bb.intersects(&r));
+
+        // passes beside the edge
+        let r = Ray::new(dvec3(2.0, 0.01, 0.0), dvec3(-1.0, 1.0, 0.0));
+        assert!(!bb.intersects(&r));
+    }
+
+    #[test]
+    fn a_ray_crossing_a_flat_bounding_box_intersects_it() {
+        // the box of a triangle lying in the plane z = 0
+        let bb = BoundingBox::new(
+            dvec3(-2.0, -2.0, 0.0),
+            dvec3(2.0, 2.0, 0.0)
+        );
+        let r = Ray::new(dvec3(0.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(bb.intersection_range(&r), (5.0, 5.0));
+        assert!(bb.intersects(&r));
+    }
+
     #[test]
     fn intersecting_a_ray_with_a_non_cubic_bounding_box() {
         let bb = BoundingBox::new(
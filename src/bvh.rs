@@ -0,0 +1,563 @@
+use crate::{Object, shapes::{Shape, Group}, bounds::BoundingBox, ray::Ray};
+
+/// how `Object::divide` builds the hierarchy of a group or a mesh
+#[derive(Debug, Clone, PartialEq)]
+pub enum BvhBuilder {
+    /// splits the bounds at the middle of their largest axis once they hold
+    /// threshold children or more. the children of a group straddling both
+    /// halves stay in the parent, the faces of a mesh go to the side of
+    /// their centroid
+    Midpoint { threshold: usize },
+    /// binned surface area heuristic
+    Sah(SahBuilder),
+}
+
+impl From<usize> for BvhBuilder {
+    fn from(threshold: usize) -> Self {
+        BvhBuilder::Midpoint { threshold }
+    }
+}
+
+impl From<SahBuilder> for BvhBuilder {
+    fn from(builder: SahBuilder) -> Self {
+        BvhBuilder::Sah(builder)
+    }
+}
+
+impl BvhBuilder {
+    /// splits the items in two and returns the split axis, or gives them
+    /// back when they stay together in a leaf
+    pub(crate) fn partition<T>(&self, items: Vec<T>, bounds_of: impl Fn(&T) -> BoundingBox) -> Result<(usize, Vec<T>, Vec<T>), Vec<T>> {
+        match self {
+            BvhBuilder::Midpoint { threshold } => Self::midpoint_partition(*threshold, items, bounds_of),
+            BvhBuilder::Sah(builder) => builder.partition(items, bounds_of),
+        }
+    }
+
+    fn midpoint_partition<T>(threshold: usize, items: Vec<T>, bounds_of: impl Fn(&T) -> BoundingBox) -> Result<(usize, Vec<T>, Vec<T>), Vec<T>> {
+        if items.len() < threshold.max(2) {
+            return Err(items);
+        }
+        let bounds = items
+            .iter()
+            .fold(BoundingBox::default(), |bounds, item| bounds.merge(&bounds_of(item)));
+        let size = bounds.size();
+        let axis = match size.max_element() {
+            e if e == size.x => 0,
+            e if e == size.y => 1,
+            _ => 2,
+        };
+        let middle = bounds.centroid()[axis];
+        let (left, right): (Vec<T>, Vec<T>) = items
+            .into_iter()
+            .partition(|item| bounds_of(item).centroid()[axis] < middle);
+        match (left.is_empty(), right.is_empty()) {
+            (false, false) => Ok((axis, left, right)),
+            _ => Err(left.into_iter().chain(right).collect()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SahBuilder {
+    bins: usize,
+    leaf_size: usize,
+    traversal_cost: f64,
+    intersection_cost: f64,
+}
+
+impl Default for SahBuilder {
+    fn default() -> Self {
+        Self {
+            bins: 12,
+            leaf_size: 4,
+            traversal_cost: 1.0,
+            intersection_cost: 1.0,
+        }
+    }
+}
+
+impl SahBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// number of buckets the centroids are sorted in along the split axis
+    pub fn with_bins(mut self, bins: usize) -> Self {
+        self.bins = bins.max(2);
+        self
+    }
+
+    /// nodes with at most this number of children are never split
+    pub fn with_leaf_size(mut self, leaf_size: usize) -> Self {
+        self.leaf_size = leaf_size.max(1);
+        self
+    }
+
+    /// relative cost of testing the bounding box of a node
+    pub fn with_traversal_cost(mut self, traversal_cost: f64) -> Self {
+        self.traversal_cost = traversal_cost;
+        self
+    }
+
+    /// relative cost of intersecting a child of a leaf
+    pub fn with_intersection_cost(mut self, intersection_cost: f64) -> Self {
+        self.intersection_cost = intersection_cost;
+        self
+    }
+
+    pub fn bins(&self) -> usize {
+        self.bins
+    }
+
+    pub fn leaf_size(&self) -> usize {
+        self.leaf_size
+    }
+
+    pub fn traversal_cost(&self) -> f64 {
+        self.traversal_cost
+    }
+
+    pub fn intersection_cost(&self) -> f64 {
+        self.intersection_cost
+    }
+
+    /// returns the new children of a group holding these objects.
+    /// unbounded objects can't be sorted and stay at the top
+    pub(crate) fn build(&self, objects: Vec<Object>) -> Vec<Object> {
+        let (mut unbounded, bounded): (Vec<Object>, Vec<Object>) = objects
+            .into_iter()
+            .partition(|object| !object.bounding_box().is_finite());
+        unbounded.extend(self.split(bounded));
+        unbounded
+    }
+
+    fn split(&self, objects: Vec<Object>) -> Vec<Object> {
+        match self.partition(objects, |object| *object.bounding_box()) {
+            Ok((_, left, right)) => vec![
+                Self::node(self.split(left)),
+                Self::node(self.split(right)),
+            ],
+            Err(objects) => objects,
+        }
+    }
+
+    /// splits the items along the cheapest binned plane and returns the
+    /// split axis, or gives them back when keeping them in a leaf is cheaper
+    pub(crate) fn partition<T>(&self, items: Vec<T>, bounds_of: impl Fn(&T) -> BoundingBox) -> Result<(usize, Vec<T>, Vec<T>), Vec<T>> {
+        if items.len() <= self.leaf_size {
+            return Err(items);
+        }
+
+        let (bounds, centroid_bounds) = items
+            .iter()
+            .fold((BoundingBox::default(), BoundingBox::default()), |(bounds, centroids), item| {
+                let item_bounds = bounds_of(item);
+                (
+                    bounds.merge(&item_bounds),
+                    centroids.add_point(item_bounds.centroid())
+                )
+            });
+
+        let extent = centroid_bounds.size();
+        let axis = match extent.max_element() {
+            e if e == extent.x => 0,
+            e if e == extent.y => 1,
+            _ => 2,
+        };
+        let area = bounds.surface_area();
+        if extent[axis] <= 0.0 || area <= 0.0 {
+            return Err(items);
+        }
+
+        let min = centroid_bounds.min()[axis];
+        let bin_of = |item: &T| {
+            let offset = (bounds_of(item).centroid()[axis] - min) / extent[axis];
+            ((offset * self.bins as f64) as usize).min(self.bins - 1)
+        };
+
+        let mut bins = vec![(0, BoundingBox::default()); self.bins];
+        for item in &items {
+            let bin = &mut bins[bin_of(item)];
+            bin.0 += 1;
+            bin.1 = bin.1.merge(&bounds_of(item));
+        }
+
+        // sweeps from the right to know what lies after each split,
+        // empty bins are skipped as merging an empty box spans everything
+        let mut right = vec![(0, 0.0); self.bins];
+        let mut count = 0;
+        let mut right_bounds = BoundingBox::default();
+        for i in (1..self.bins).rev() {
+            if bins[i].0 > 0 {
+                count += bins[i].0;
+                right_bounds = right_bounds.merge(&bins[i].1);
+            }
+            right[i] = (count, right_bounds.surface_area());
+        }
+
+        // the split i keeps the bins 0..=i on the left
+        let mut best: Option<(usize, f64)> = None;
+        let mut count = 0;
+        let mut left_bounds = BoundingBox::default();
+        for i in 0..self.bins - 1 {
+            if bins[i].0 > 0 {
+                count += bins[i].0;
+                left_bounds = left_bounds.merge(&bins[i].1);
+            }
+            let (right_count, right_area) = right[i + 1];
+            if count == 0 || right_count == 0 {
+                continue;
+            }
+            let cost = self.traversal_cost + self.intersection_cost *
+                (count as f64 * left_bounds.surface_area() + right_count as f64 * right_area) / area;
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some((i, cost));
+            }
+        }
+
+        let leaf_cost = self.intersection_cost * items.len() as f64;
+        let split = match best {
+            Some((split, cost)) if cost < leaf_cost => split,
+            _ => return Err(items),
+        };
+
+        let (left, right) = items
+            .into_iter()
+            .partition(|item| bin_of(item) <= split);
+        Ok((axis, left, right))
+    }
+
+    fn node(mut children: Vec<Object>) -> Object {
+        match children.len() {
+            1 => children.remove(0),
+            _ => Object::new(Shape::Group(Group::new().with_objects(children))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NodeKind {
+    /// the first child directly follows its parent in the array
+    Interior { second_child: usize, axis: usize },
+    /// a range of the primitive indices
+    Leaf { first: usize, count: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Node {
+    bounds: BoundingBox,
+    kind: NodeKind,
+}
+
+/// nodes stored in depth first order over primitives referenced by index,
+/// the primitives themselves are kept by the owner of the tree
+#[derive(Clone, Debug, PartialEq, Default)]
+pub(crate) struct BvhTree {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    /// unbounded primitives are tested by every ray
+    unbounded: Vec<usize>,
+}
+
+impl BvhTree {
+    pub(crate) fn new(bounds: &[BoundingBox], builder: &BvhBuilder) -> Self {
+        let (unbounded, bounded): (Vec<usize>, Vec<usize>) = (0..bounds.len())
+            .partition(|&index| !bounds[index].is_finite());
+
+        let mut tree = Self {
+            nodes: Vec::default(),
+            indices: Vec::with_capacity(bounded.len()),
+            unbounded,
+        };
+        if !bounded.is_empty() {
+            tree.build_node(bounded, bounds, builder);
+        }
+        tree
+    }
+
+    fn build_node(&mut self, indices: Vec<usize>, bounds: &[BoundingBox], builder: &BvhBuilder) -> usize {
+        let node_bounds = indices
+            .iter()
+            .fold(BoundingBox::default(), |node_bounds, &index| node_bounds.merge(&bounds[index]));
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            kind: NodeKind::Leaf { first: 0, count: 0 },
+        });
+
+        self.nodes[node].kind = match builder.partition(indices, |&index| bounds[index]) {
+            Ok((axis, left, right)) => {
+                self.build_node(left, bounds, builder);
+                let second_child = self.build_node(right, bounds, builder);
+                NodeKind::Interior { second_child, axis }
+            }
+            Err(indices) => {
+                let first = self.indices.len();
+                let count = indices.len();
+                self.indices.extend(indices);
+                NodeKind::Leaf { first, count }
+            }
+        };
+        node
+    }
+
+    pub(crate) fn bounds(&self) -> BoundingBox {
+        match self.nodes.first() {
+            Some(root) => root.bounds,
+            None => BoundingBox::default(),
+        }
+    }
+
+    pub(crate) fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn unbounded(&self) -> &[usize] {
+        &self.unbounded
+    }
+
+    /// visits the unbounded primitives, then the primitives of the leaves hit
+    /// by the ray nearest child first. the visitor returns the new maximum
+    /// distance, nodes entered beyond it are skipped and a negative distance
+    /// stops the traversal
+    pub(crate) fn traverse(&self, ray: &Ray, mut t_max: f64, mut visit: impl FnMut(usize, f64) -> f64) {
+        for &index in &self.unbounded {
+            t_max = visit(index, t_max);
+            if t_max < 0.0 {
+                return;
+            }
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let (t_enter, t_exit) = node.bounds.intersection_range(ray);
+            if t_enter > t_exit || t_enter > t_max {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { first, count } => {
+                    for &primitive in &self.indices[first..first + count] {
+                        t_max = visit(primitive, t_max);
+                        if t_max < 0.0 {
+                            return;
+                        }
+                    }
+                }
+                NodeKind::Interior { second_child, axis } => {
+                    // the near child is pushed last to be visited first
+                    match ray.direction[axis] < 0.0 {
+                        true => stack.extend([index + 1, second_child]),
+                        false => stack.extend([second_child, index + 1]),
+                    }
+                }
+            }
+        }
+    }
+
+    /// true as soon as one of the primitives visited is hit before t_max
+    pub(crate) fn any_hit(&self, ray: &Ray, t_max: f64, mut hit: impl FnMut(usize) -> bool) -> bool {
+        let mut found = false;
+        self.traverse(ray, t_max, |index, t_max| {
+            found = hit(index);
+            match found {
+                true => -1.0,
+                false => t_max,
+            }
+        });
+        found
+    }
+
+    /// recomputes the bounds of the nodes once the primitives moved
+    pub(crate) fn refit(&mut self, bounds_of: impl Fn(usize) -> BoundingBox) {
+        for index in (0..self.nodes.len()).rev() {
+            self.nodes[index].bounds = match self.nodes[index].kind {
+                NodeKind::Leaf { first, count } => self.indices[first..first + count]
+                    .iter()
+                    .fold(BoundingBox::default(), |bounds, &primitive| bounds.merge(&bounds_of(primitive))),
+                NodeKind::Interior { second_child, .. } => self.nodes[index + 1].bounds
+                    .merge(&self.nodes[second_child].bounds),
+            };
+        }
+    }
+
+    pub(crate) fn add_stats(&self, stats: &mut BvhStats, depth: usize) {
+        if !self.unbounded.is_empty() || self.nodes.is_empty() {
+            stats.add_node(depth, self.unbounded.len());
+        }
+        if !self.nodes.is_empty() {
+            self.add_node_stats(0, stats, depth);
+        }
+    }
+
+    fn add_node_stats(&self, index: usize, stats: &mut BvhStats, depth: usize) {
+        match self.nodes[index].kind {
+            NodeKind::Leaf { count, .. } => stats.add_node(depth, count),
+            NodeKind::Interior { second_child, .. } => {
+                stats.add_node(depth, 0);
+                self.add_node_stats(index + 1, stats, depth + 1);
+                self.add_node_stats(second_child, stats, depth + 1);
+            }
+        }
+    }
+}
+
+/// describes the hierarchy of groups below an object.
+/// every group or mesh is a node, the nodes directly holding
+/// other shapes are leaves
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub depth: usize,
+    pub primitives: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+}
+
+impl BvhStats {
+    pub fn new(object: &Object) -> Self {
+        let mut stats = Self::default();
+        stats.visit(object, 0);
+        stats
+    }
+
+    pub fn average_leaf_size(&self) -> f64 {
+        match self.leaves {
+            0 => 0.0,
+            leaves => self.primitives as f64 / leaves as f64,
+        }
+    }
+
+    fn visit(&mut self, object: &Object, depth: usize) {
+        let children = match object.shape() {
+            Shape::Group(g) => g.objects(),
+            Shape::Mesh(m) => return m.add_stats(self, depth),
+            Shape::Bvh(b) => return b.add_stats(self, depth),
+            _ => return,
+        };
+
+        let mut leaf_size = 0;
+        for child in children {
+            match child.shape() {
+                Shape::Group(_) | Shape::Mesh(_) | Shape::Bvh(_) => self.visit(child, depth + 1),
+                _ => leaf_size += 1,
+            }
+        }
+        self.add_node(depth, leaf_size);
+    }
+
+    /// a node directly holding shapes counts as a leaf
+    pub(crate) fn add_node(&mut self, depth: usize, leaf_size: usize) {
+        self.nodes += 1;
+        self.depth = self.depth.max(depth + 1);
+        if leaf_size > 0 {
+            self.min_leaf_size = match self.leaves {
+                0 => leaf_size,
+                _ => self.min_leaf_size.min(leaf_size),
+            };
+            self.max_leaf_size = self.max_leaf_size.max(leaf_size);
+            self.leaves += 1;
+            self.primitives += leaf_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::{Sphere, Plane};
+
+    use super::*;
+
+    fn spheres_at(xs: &[f64]) -> Vec<Object> {
+        xs
+            .iter()
+            .map(|x| Object::new(Shape::Sphere(Sphere::default()))
+                .with_translation(*x, 0.0, 0.0)
+                .transform()
+            )
+            .collect()
+    }
+
+    #[test]
+    fn the_surface_area_heuristic_separates_clusters() {
+        let g = Object::new(Shape::Group(Group::new().with_objects(
+            spheres_at(&[-10.0, -9.0, -8.0, 8.0, 9.0, 10.0])
+        )))
+        .divide(SahBuilder::new().with_leaf_size(1));
+
+        let children = g.shape().as_group().unwrap().objects();
+        assert_eq!(children.len(), 2);
+        let left = children[0].bounding_box();
+        let right = children[1].bounding_box();
+        assert_eq!(left.max().x, -7.0);
+        assert_eq!(right.min().x, 7.0);
+        assert_eq!(BvhStats::new(&g).primitives, 6);
+    }
+
+    #[test]
+    fn a_small_group_is_not_split() {
+        let g = Object::new(Shape::Group(Group::new().with_objects(
+            spheres_at(&[-10.0, 0.0, 10.0])
+        )))
+        .divide(SahBuilder::new().with_leaf_size(3));
+
+        let stats = BvhStats::new(&g);
+        assert_eq!(stats.nodes, 1);
+        assert_eq!(stats.max_leaf_size, 3);
+    }
+
+    #[test]
+    fn an_expensive_traversal_keeps_the_group_flat() {
+        let g = Object::new(Shape::Group(Group::new().with_objects(
+            spheres_at(&[-10.0, -9.0, -8.0, 8.0, 9.0, 10.0])
+        )))
+        .divide(
+            SahBuilder::new()
+            .with_leaf_size(1)
+            .with_traversal_cost(10.0)
+        );
+
+        assert_eq!(g.shape().as_group().unwrap().objects().len(), 6);
+    }
+
+    #[test]
+    fn unbounded_objects_stay_at_the_top_of_the_hierarchy() {
+        let mut objects = spheres_at(&[-10.0, -9.0, 9.0, 10.0]);
+        objects.push(Object::new(Shape::Plane(Plane::default())));
+        let g = Object::new(Shape::Group(Group::new().with_objects(objects)))
+            .divide(SahBuilder::new().with_leaf_size(1));
+
+        let children = g.shape().as_group().unwrap().objects();
+        assert_eq!(children.len(), 3);
+        assert_eq!(*children[0].shape(), Shape::Plane(Plane::default()));
+    }
+
+    #[test]
+    fn describing_a_hierarchy() {
+        let inner = Object::new(Shape::Group(Group::new().with_objects(spheres_at(&[0.0, 1.0, 2.0]))));
+        let mut objects = spheres_at(&[5.0]);
+        objects.push(inner);
+        objects.push(Object::new(Shape::Group(Group::new().with_objects(spheres_at(&[3.0])))));
+        let g = Object::new(Shape::Group(Group::new().with_objects(objects)));
+
+        let stats = BvhStats::new(&g);
+        assert_eq!(
+            stats,
+            BvhStats {
+                nodes: 3,
+                leaves: 3,
+                depth: 2,
+                primitives: 5,
+                min_leaf_size: 1,
+                max_leaf_size: 3,
+            }
+        );
+        assert!((stats.average_leaf_size() - 5.0 / 3.0).abs() < 0.0001);
+        assert_eq!(BvhStats::new(&spheres_at(&[0.0])[0]), BvhStats::default());
+    }
+}
\ No newline at end of file
@@ -1,21 +1,164 @@
-use std::time::Instant;
+use std::{time::{Instant, Duration}, f64::consts::PI, sync::atomic::{AtomicUsize, Ordering}};
 
-use glam::{DVec3, DMat4, dvec3};
+use glam::{DVec2, DMat4, dvec2, dvec3};
+use rand::Rng;
 use rayon::prelude::*;
 
-use crate::{Canvas, ray::Ray, World, Color};
+use crate::{Canvas, ray::Ray, World, Color, sampler::Sampler, filter::Filter, film::Film, tile::Tile, progress::{RenderObserver, CancellationToken}};
+
+/// shape of the lens opening used for focal blur
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ApertureShape {
+    Disk,
+    /// regular polygon with the given number of blades
+    Polygon(usize),
+}
+
+impl ApertureShape {
+    /// maps a point of the unit square to a point of the aperture of radius 1
+    pub fn sample(&self, u: f64, v: f64) -> DVec2 {
+        match *self {
+            ApertureShape::Disk => {
+                let r = u.sqrt();
+                let theta = 2.0 * PI * v;
+                dvec2(r * theta.cos(), r * theta.sin())
+            }
+            ApertureShape::Polygon(blades) => {
+                let blades = blades.max(3);
+                // picks one of the triangles between the center and two consecutive vertices
+                let sector = usize::min((u * blades as f64) as usize, blades - 1);
+                let mut a = u * blades as f64 - sector as f64;
+                let mut b = v;
+                if a + b > 1.0 {
+                    a = 1.0 - a;
+                    b = 1.0 - b;
+                }
+                let angle = 2.0 * PI / blades as f64;
+                let v0 = dvec2(f64::cos(angle * sector as f64), f64::sin(angle * sector as f64));
+                let v1 = dvec2(f64::cos(angle * (sector + 1) as f64), f64::sin(angle * (sector + 1) as f64));
+                v0 * a + v1 * b
+            }
+        }
+    }
+}
+
+/// spends more samples on the pixels whose samples disagree, or that differ
+/// from their neighbours, until their error falls below the threshold
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub max_samples: usize,
+    pub threshold: f64,
+}
+
+impl AdaptiveSampling {
+    pub const DEFAULT_THRESHOLD: f64 = 0.01;
+}
+
+/// running sums of the samples of a pixel
+#[derive(Debug, Clone, Copy, Default)]
+struct PixelStats {
+    count: usize,
+    sum: Color,
+    squares: Color,
+}
+
+impl PixelStats {
+    fn add(&mut self, color: Color) {
+        self.count += 1;
+        self.sum += color;
+        self.squares += color * color;
+    }
+
+    fn mean(&self) -> Color {
+        self.sum / self.count.max(1) as f64
+    }
+
+    /// standard error of the mean of the worst channel, a single sample
+    /// has no error
+    fn error(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        let n = self.count as f64;
+        let mean = self.mean();
+        let variance = (self.squares / n - mean * mean) * (n / (n - 1.0));
+        f64::max(variance.r, f64::max(variance.g, variance.b)).max(0.0).sqrt() / n.sqrt()
+    }
+
+    fn contrast(&self, other: &PixelStats) -> f64 {
+        let d = self.mean() - other.mean();
+        f64::max(d.r.abs(), f64::max(d.g.abs(), d.b.abs()))
+    }
+}
+
+/// image rendered tile by tile, only the completed tiles are drawn when the
+/// rendering is cancelled
+pub struct Rendering {
+    pub canvas: Canvas,
+    /// gray levels relative to the most sampled pixel
+    pub sample_counts: Canvas,
+    pub tiles: Vec<Tile>,
+    /// some tiles were not sampled or refined
+    pub cancelled: bool,
+}
+
+/// what is shared by the tiles of a rendering pass
+#[derive(Clone, Copy)]
+struct Pass<'a> {
+    world: &'a World,
+    max_recursions: u8,
+    film: &'a Film,
+    sampler: Sampler,
+}
+
+/// how the pixels of the image are turned into rays
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// rays from the eye spreading over the field of view
+    Perspective,
+    /// parallel rays through a view of the given size in world units
+    Orthographic { view_width: f64, view_height: f64 },
+    /// every direction around the camera, the longitude goes along the
+    /// width of the image and the latitude along its height
+    Equirectangular,
+    /// the field of view is mapped to the largest circle of the image, the
+    /// pixels outside of it are black
+    Fisheye(FisheyeMapping),
+}
+
+/// how the angle between a ray and the axis of a fisheye camera depends on
+/// the distance of its pixel to the center of the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FisheyeMapping {
+    /// the distance is proportional to the angle
+    Equidistant,
+    /// the areas of the image are proportional to the solid angles they see
+    Equisolid,
+}
 
 pub struct Camera {
     width: usize,
     height: usize,
     fov: f64,
+    projection: Projection,
     transform: DMat4,
     transform_inverse: DMat4,
     pixel_size: f64,
     half_width: f64,
     half_height: f64,
     background: Color,
-    antialiasing_offsets: Vec<f64>,
+    samples_per_pixel: usize,
+    sampler: Sampler,
+    filter: Filter,
+    adaptive_sampling: Option<AdaptiveSampling>,
+    tile_size: usize,
+    crop: Option<Tile>,
+    aperture: f64,
+    focal_distance: f64,
+    aperture_shape: ApertureShape,
+    blur_samples: usize,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 impl Camera {
@@ -26,11 +169,11 @@ impl Camera {
             if aspect >= 1.0 { 
                 half_view 
             } else { 
-                half_view * aspect as f64 
+                half_view * aspect 
             };
         let half_height = 
             if aspect >= 1.0 { 
-                half_view / aspect as f64 
+                half_view / aspect 
             } else { 
                 half_view 
             };
@@ -40,13 +183,25 @@ impl Camera {
             width,
             height,
             fov,
+            projection: Projection::Perspective,
             transform: DMat4::IDENTITY,
             transform_inverse: DMat4::IDENTITY,
             pixel_size,
             half_width,
             half_height,
             background: Color::black(),
-            antialiasing_offsets: vec![0.0]
+            samples_per_pixel: 1,
+            sampler: Sampler::Regular,
+            filter: Filter::Box,
+            adaptive_sampling: None,
+            tile_size: 16,
+            crop: None,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            aperture_shape: ApertureShape::Disk,
+            blur_samples: 1,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
         }
     }
 
@@ -56,20 +211,114 @@ impl Camera {
         self
     }
 
+    pub fn with_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    /// parallel rays, the view is centered on the camera and the field of
+    /// view is ignored
+    pub fn with_orthographic(self, view_width: f64, view_height: f64) -> Self {
+        self.with_projection(Projection::Orthographic { view_width, view_height })
+    }
+
+    /// the field of view of the camera is the one of the fisheye, it can
+    /// exceed a half turn
+    pub fn with_fisheye(self, mapping: FisheyeMapping) -> Self {
+        self.with_projection(Projection::Fisheye(mapping))
+    }
+
     pub fn with_background(mut self, color: Color) -> Self {
         self.background = color;
         self
     }
 
-    pub fn with_antialiasing(mut self, level: usize) -> Self {
-        if level > 5 { println!("maximum supported antialising is level 5")}
-        self.antialiasing_offsets = match level {
-            2 => vec![-0.25, 0.25],
-            3 => vec![-0.25, 0.0, 0.25],
-            4 => vec![-0.25, -0.12, 0.12, 0.25],
-            5 => vec![-0.25, -0.12, 0.0, 0.12, 0.25],
-            _ => vec![0.0],
-        };
+    /// level x level samples per pixel
+    pub fn with_antialiasing(self, level: usize) -> Self {
+        self.with_samples_per_pixel(level * level)
+    }
+
+    pub fn with_samples_per_pixel(mut self, samples_per_pixel: usize) -> Self {
+        self.samples_per_pixel = samples_per_pixel.max(1);
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// weights the samples around each pixel when they are accumulated
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// every pixel starts with the samples per pixel, the pixels whose error
+    /// or contrast with a neighbour is above the threshold get batches of as
+    /// many samples until their error is below the threshold or they reach
+    /// max samples. the regular sampler is jittered for the extra batches
+    pub fn with_adaptive_sampling(mut self, max_samples: usize, threshold: f64) -> Self {
+        self.adaptive_sampling = Some(AdaptiveSampling { max_samples, threshold });
+        self
+    }
+
+    /// side in pixels of the square tiles rendered in parallel
+    pub fn with_tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size.max(1);
+        self
+    }
+
+    /// only the pixels of the crop window are rendered, the part outside the
+    /// image is ignored
+    pub fn with_crop(mut self, crop: Tile) -> Self {
+        self.crop = Some(crop.intersection(&self.image()).unwrap_or(Tile::new(0, 0, 0, 0)));
+        self
+    }
+
+    /// crop window from (x0, y0) to (x1, y1) in fractions of the width and the
+    /// height of the image, it includes every pixel it touches
+    pub fn with_crop_window(self, x0: f64, y0: f64, x1: f64, y1: f64) -> Self {
+        let (width, height) = (self.width as f64, self.height as f64);
+        let (left, top) = ((x0 * width).floor().max(0.0) as usize, (y0 * height).floor().max(0.0) as usize);
+        let (right, bottom) = ((x1 * width).ceil().max(0.0) as usize, (y1 * height).ceil().max(0.0) as usize);
+        self.with_crop(Tile::new(left, top, right.saturating_sub(left), bottom.saturating_sub(top)))
+    }
+
+    /// diameter of the lens, 0 is a pinhole camera
+    pub fn with_aperture(mut self, aperture: f64) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    /// distance from the camera of the plane where objects are perfectly sharp
+    pub fn with_focal_distance(mut self, focal_distance: f64) -> Self {
+        self.focal_distance = focal_distance;
+        self
+    }
+
+    pub fn with_aperture_shape(mut self, aperture_shape: ApertureShape) -> Self {
+        self.aperture_shape = aperture_shape;
+        self
+    }
+
+    /// number of lens positions and instants sampled per pixel when the
+    /// aperture is not 0 or the shutter stays open for some time
+    pub fn with_blur_samples(mut self, blur_samples: usize) -> Self {
+        self.blur_samples = blur_samples.max(1);
+        self
+    }
+
+    /// same as with_blur_samples, the lens and the shutter share their samples
+    pub fn with_focal_samples(self, focal_samples: usize) -> Self {
+        self.with_blur_samples(focal_samples)
+    }
+
+    /// the rays of each pixel are spread between the opening and the closing
+    /// times of the shutter, moving objects are blurred along their path
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter_open = open;
+        self.shutter_close = close.max(open);
         self
     }
 
@@ -85,67 +334,438 @@ impl Camera {
         self.fov
     }
 
+    /// side of the grid of samples of a pixel
     pub fn antialiasing(&self) -> usize {
-        self.antialiasing_offsets.len()
+        (self.samples_per_pixel as f64).sqrt() as usize
+    }
+
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    pub fn sampler(&self) -> Sampler {
+        self.sampler
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    pub fn adaptive_sampling(&self) -> Option<AdaptiveSampling> {
+        self.adaptive_sampling
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn crop(&self) -> Option<Tile> {
+        self.crop
+    }
+
+    pub fn aperture(&self) -> f64 {
+        self.aperture
+    }
+
+    pub fn focal_distance(&self) -> f64 {
+        self.focal_distance
+    }
+
+    pub fn aperture_shape(&self) -> ApertureShape {
+        self.aperture_shape
+    }
+
+    pub fn blur_samples(&self) -> usize {
+        self.blur_samples
+    }
+
+    pub fn focal_samples(&self) -> usize {
+        self.blur_samples
+    }
+
+    pub fn shutter(&self) -> (f64, f64) {
+        (self.shutter_open, self.shutter_close)
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
     }
 
     pub fn transform(&self) -> &DMat4 {
         &self.transform
     }
  
+    /// silent, render_tiles reports the progress to an observer
     pub fn render(&self, world: &World, max_recursions: u8) -> Canvas {
-        let mut canvas = Canvas::new(self.width, self.height);
+        self.render_tiles(world, max_recursions, &(), &CancellationToken::new()).canvas
+    }
+
+    /// only the crop window, or the whole image without one
+    pub fn render_cropped(&self, world: &World, max_recursions: u8) -> Canvas {
+        self.render(world, max_recursions).crop(&self.crop_region())
+    }
+
+    /// also returns the number of samples of each pixel, as a gray level
+    /// relative to the most sampled pixel, to debug adaptive sampling
+    pub fn render_with_sample_counts(&self, world: &World, max_recursions: u8) -> (Canvas, Canvas) {
+        let rendering = self.render_tiles(world, max_recursions, &(), &CancellationToken::new());
+        (rendering.canvas, rendering.sample_counts)
+    }
+
+    /// renders the tiles of the image in parallel and tells the observer when
+    /// each of them is completed. with adaptive sampling the tiles are
+    /// completed twice, once sampled and once refined. once cancelled no new
+    /// tile is started and the pixels of the missing tiles stay black
+    pub fn render_tiles(
+        &self,
+        world: &World,
+        max_recursions: u8,
+        observer: &dyn RenderObserver,
+        cancel: &CancellationToken,
+    ) -> Rendering {
+        let film = Film::new(self.width, self.height, self.filter);
         let now = Instant::now();
 
-        println!("Start rendering");
+        observer.render_started();
 
-        canvas
-            .pixels_mut()
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(i, color)| {
-                let y = i / self.width;
-                let x = i - y * self.width;
-                for yoffset in &self.antialiasing_offsets {
-                    for xoffset in &self.antialiasing_offsets {
-                        let ray = self.ray_for_pixel(x as f64 + *xoffset, y as f64 + *yoffset);
-                        *color += world
-                            .color_at(&ray, max_recursions)
-                            .unwrap_or(self.background);
+        let tiles = self.traced_region().subdivide(self.tile_size);
+        let work = self.traced_region().area() * if self.adaptive_sampling.is_some() { 2 } else { 1 };
+        let done = AtomicUsize::new(0);
+        let complete = |tile: Tile| {
+            let done = done.fetch_add(tile.area(), Ordering::Relaxed) + tile.area();
+            observer.tile_completed(tile, done as f64 * 100.0 / work as f64);
+        };
+
+        let pass = Pass { world, max_recursions, film: &film, sampler: self.sampler };
+        let sampled: Vec<(Tile, Vec<PixelStats>)> = tiles
+            .par_iter()
+            .filter_map(|tile| {
+                if cancel.is_cancelled() {
+                    return None;
+                }
+                let stats = self.sample_tile(&pass, *tile, self.samples_per_pixel);
+                complete(*tile);
+                Some((*tile, stats))
+            })
+            .collect();
+        let completed: Vec<Tile> = sampled.iter().map(|(tile, _)| *tile).collect();
+        let mut cancelled = completed.len() < tiles.len();
+
+        let mut stats = vec![vec![PixelStats::default(); self.width]; self.height];
+        Self::gather_stats(&mut stats, &sampled);
+
+        if let (Some(adaptive), false) = (self.adaptive_sampling, cancelled) {
+            let refined = self.pixels_to_refine(&stats, adaptive.threshold);
+            let pass = Pass { sampler: self.jittered_sampler(), ..pass };
+            let sampled: Vec<(Tile, Vec<PixelStats>)> = sampled
+                .into_par_iter()
+                .filter_map(|(tile, mut tile_stats)| {
+                    if cancel.is_cancelled() {
+                        return None;
                     }
+                    self.refine_tile(&pass, tile, &mut tile_stats, &refined, adaptive);
+                    complete(tile);
+                    Some((tile, tile_stats))
+                })
+                .collect();
+            cancelled = sampled.len() < tiles.len();
+            Self::gather_stats(&mut stats, &sampled);
+        }
+
+        observer.render_finished(now.elapsed());
+
+        let canvas = self.develop(&film, &completed);
+
+        let most = stats.iter().flatten().map(|pixel| pixel.count).max().unwrap_or(1).max(1);
+        let mut sample_counts = Canvas::new(self.width, self.height);
+        for (y, row) in stats.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                let level = pixel.count as f64 / most as f64;
+                sample_counts[y][x] = Color::new(level, level, level);
+            }
+        }
+
+        Rendering { canvas, sample_counts, tiles: completed, cancelled }
+    }
+
+    /// renders passes of one sample per pixel, accumulated in the image, and
+    /// gives the observer the image after each of them. stops after max
+    /// passes, once the time budget is spent or once cancelled, but always
+    /// renders a first pass unless cancelled before. the percentage of the
+    /// completed tiles is the one of the max passes. adaptive sampling is ignored
+    pub fn render_progressive(
+        &self,
+        world: &World,
+        max_recursions: u8,
+        max_passes: usize,
+        time_budget: Option<Duration>,
+        observer: &dyn RenderObserver,
+        cancel: &CancellationToken,
+    ) -> Canvas {
+        let film = Film::new(self.width, self.height, self.filter);
+        let tiles = self.traced_region().subdivide(self.tile_size);
+        let now = Instant::now();
+
+        observer.render_started();
+
+        let max_passes = max_passes.max(1);
+        let work = self.traced_region().area() * max_passes;
+        let done = AtomicUsize::new(0);
+        let mut canvas = Canvas::new(self.width, self.height);
+        for index in 1..=max_passes {
+            // the regular sampler would put every pass at the center of the pixels
+            let sampler = if index == 1 { self.sampler } else { self.jittered_sampler() };
+            let pass = Pass { world, max_recursions, film: &film, sampler };
+            tiles.par_iter().for_each(|tile| {
+                if cancel.is_cancelled() {
+                    return;
                 }
-                *color /= (self.antialiasing_offsets.len() * self.antialiasing_offsets.len()) as f64; 
+                self.sample_tile(&pass, *tile, 1);
+                let done = done.fetch_add(tile.area(), Ordering::Relaxed) + tile.area();
+                observer.tile_completed(*tile, done as f64 * 100.0 / work as f64);
             });
+            canvas = self.develop(&film, &tiles);
+            observer.pass_completed(index, &canvas);
+            if cancel.is_cancelled() || time_budget.is_some_and(|budget| now.elapsed() >= budget) {
+                break;
+            }
+        }
 
-        println!("Rendering finished in {:.2?} seconds", now.elapsed());
+        observer.render_finished(now.elapsed());
 
         canvas
     }
 
-    fn ray_for_pixel(&self, x: f64, y: f64) -> Ray {
-        let world_x = self.half_width - (x as f64 + 0.5) * self.pixel_size;
-        let world_y = self.half_height - (y as f64 + 0.5) * self.pixel_size;
-        let pixel = self.transform_inverse.transform_point3(dvec3(world_x, world_y, -1.0));
-        let origin = self.transform_inverse.transform_point3(DVec3::ZERO);
-        let direction = (pixel - origin).normalize();
-        Ray {
-            origin,
-            direction
+    fn image(&self) -> Tile {
+        Tile::new(0, 0, self.width, self.height)
+    }
+
+    fn crop_region(&self) -> Tile {
+        self.crop.unwrap_or(self.image())
+    }
+
+    /// the crop window with a margin for the samples that a wide filter
+    /// spreads over its border
+    fn traced_region(&self) -> Tile {
+        let margin = (self.filter.radius() - 0.5).ceil() as usize;
+        self.crop_region().grown(margin, &self.image())
+    }
+
+    /// full size image of the pixels of the crop window in the tiles, the
+    /// others stay black even if a wide filter spread samples over them
+    fn develop(&self, film: &Film, tiles: &[Tile]) -> Canvas {
+        let image = film.to_canvas();
+        let mut canvas = Canvas::new(self.width, self.height);
+        for (x, y) in tiles.iter().filter_map(|tile| tile.intersection(&self.crop_region())).flat_map(|tile| tile.pixels()) {
+            canvas[y][x] = image[y][x];
+        }
+        canvas
+    }
+
+    /// adds count samples to every pixel of the tile, the statistics of the
+    /// pixels are listed row after row
+    fn sample_tile(&self, pass: &Pass, tile: Tile, count: usize) -> Vec<PixelStats> {
+        let mut rng = rand::thread_rng();
+        let mut stats = vec![PixelStats::default(); tile.area()];
+        let mut samples = Vec::with_capacity(tile.area() * count);
+        for (position, pixel) in tile.pixels().zip(stats.iter_mut()) {
+            for sample in self.sample_pixel(pass, position, count, &mut rng) {
+                pixel.add(sample.1);
+                samples.push(sample);
+            }
+        }
+        pass.film.add_samples(&samples);
+        stats
+    }
+
+    /// adds batches of samples to the pixels of the tile to refine, until
+    /// their error is low enough or they have the maximum of samples
+    fn refine_tile(&self, pass: &Pass, tile: Tile, stats: &mut [PixelStats], refined: &[Vec<bool>], adaptive: AdaptiveSampling) {
+        let mut rng = rand::thread_rng();
+        let mut samples = Vec::new();
+        for ((x, y), pixel) in tile.pixels().zip(stats.iter_mut()).filter(|((x, y), _)| refined[*y][*x]) {
+            while pixel.count < adaptive.max_samples {
+                let count = usize::min(self.samples_per_pixel, adaptive.max_samples - pixel.count);
+                for sample in self.sample_pixel(pass, (x, y), count, &mut rng) {
+                    pixel.add(sample.1);
+                    samples.push(sample);
+                }
+                if pixel.error() <= adaptive.threshold {
+                    break;
+                }
+            }
+        }
+        pass.film.add_samples(&samples);
+    }
+
+    fn gather_stats(stats: &mut [Vec<PixelStats>], tiles: &[(Tile, Vec<PixelStats>)]) {
+        for (tile, tile_stats) in tiles {
+            for ((x, y), pixel) in tile.pixels().zip(tile_stats) {
+                stats[y][x] = *pixel;
+            }
+        }
+    }
+
+    /// sampler giving new positions each time it is asked for more samples
+    fn jittered_sampler(&self) -> Sampler {
+        match self.sampler {
+            Sampler::Regular => Sampler::Stratified,
+            sampler => sampler,
+        }
+    }
+
+    /// positions in the image and colors of count samples of the pixel
+    fn sample_pixel(&self, pass: &Pass, (x, y): (usize, usize), count: usize, rng: &mut impl Rng) -> Vec<(DVec2, Color)> {
+        pass.sampler
+            .samples(count, rng)
+            .into_iter()
+            .map(|offset| {
+                let position = dvec2(x as f64, y as f64) + offset;
+                (position, self.color_at_position(pass.world, position, pass.max_recursions, rng))
+            })
+            .collect()
+    }
+
+    /// pixels whose error is above the threshold, or whose mean differs from
+    /// the mean of a neighbour by more than the threshold. the pixels outside
+    /// the crop window have no samples, they are neither refined nor compared
+    fn pixels_to_refine(&self, stats: &[Vec<PixelStats>], threshold: f64) -> Vec<Vec<bool>> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        let pixel = &stats[y][x];
+                        if pixel.count == 0 {
+                            return false;
+                        }
+                        let neighbours = [
+                            (x > 0).then(|| &stats[y][x - 1]),
+                            stats[y].get(x + 1),
+                            (y > 0).then(|| &stats[y - 1][x]),
+                            stats.get(y + 1).map(|row| &row[x]),
+                        ];
+                        pixel.error() > threshold
+                            || neighbours
+                                .into_iter()
+                                .flatten()
+                                .filter(|other| other.count > 0)
+                                .any(|other| pixel.contrast(other) > threshold)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// color seen through a position of the image in pixels, averaged over
+    /// the lens and the time the shutter is open
+    fn color_at_position(&self, world: &World, position: DVec2, max_recursions: u8, rng: &mut impl Rng) -> Color {
+        let exposure = self.shutter_close - self.shutter_open;
+        let blur_samples = if self.aperture > 0.0 || exposure > 0.0 { self.blur_samples } else { 1 };
+        let (px, py) = (position.x - 0.5, position.y - 0.5);
+        let mut color = Color::black();
+        for _ in 0..blur_samples {
+            let ray = match self.aperture > 0.0 {
+                true => self.ray_for_pixel_through_lens(px, py, self.aperture_shape.sample(rng.gen(), rng.gen())),
+                false => self.ray_for_pixel(px, py),
+            };
+            let Some(ray) = ray else {
+                continue;
+            };
+            let ray = match exposure > 0.0 {
+                true => ray.with_time(self.shutter_open + rng.gen::<f64>() * exposure),
+                false => ray.with_time(self.shutter_open),
+            };
+            color += world
+                .color_at(&ray, max_recursions)
+                .unwrap_or(self.background);
         }
+        color / blur_samples as f64
+    }
+
+    /// none outside the image circle of a fisheye camera
+    fn ray_for_pixel(&self, x: f64, y: f64) -> Option<Ray> {
+        self.ray_for_pixel_through_lens(x, y, DVec2::ZERO)
+    }
+
+    /// lens is a point of the aperture of radius 1, the ray goes through the
+    /// pixel projected on the focal plane, or on the focal sphere of the
+    /// panoramic projections
+    fn ray_for_pixel_through_lens(&self, x: f64, y: f64, lens: DVec2) -> Option<Ray> {
+        let lens = lens * self.aperture / 2.0;
+        let (pixel, origin) = match self.projection {
+            Projection::Perspective => {
+                let world_x = (self.half_width - (x + 0.5) * self.pixel_size) * self.focal_distance;
+                let world_y = (self.half_height - (y + 0.5) * self.pixel_size) * self.focal_distance;
+                (dvec3(world_x, world_y, -self.focal_distance), dvec3(lens.x, lens.y, 0.0))
+            }
+            // the lens is centered on the pixel on the plane of the camera
+            Projection::Orthographic { view_width, view_height } => {
+                let world_x = view_width / 2.0 - (x + 0.5) * view_width / self.width as f64;
+                let world_y = view_height / 2.0 - (y + 0.5) * view_height / self.height as f64;
+                (dvec3(world_x, world_y, -self.focal_distance), dvec3(world_x + lens.x, world_y + lens.y, 0.0))
+            }
+            Projection::Equirectangular => {
+                let longitude = (0.5 - (x + 0.5) / self.width as f64) * 2.0 * PI;
+                let latitude = (0.5 - (y + 0.5) / self.height as f64) * PI;
+                let direction = dvec3(
+                    longitude.sin() * latitude.cos(),
+                    latitude.sin(),
+                    -longitude.cos() * latitude.cos(),
+                );
+                (direction * self.focal_distance, dvec3(lens.x, lens.y, 0.0))
+            }
+            Projection::Fisheye(mapping) => {
+                let radius = self.width.min(self.height) as f64 / 2.0;
+                let offset = dvec2(self.width as f64 / 2.0 - (x + 0.5), self.height as f64 / 2.0 - (y + 0.5)) / radius;
+                let distance = offset.length();
+                if distance > 1.0 {
+                    return None;
+                }
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => distance * self.fov / 2.0,
+                    FisheyeMapping::Equisolid => 2.0 * f64::asin((distance * f64::sin(self.fov / 4.0)).min(1.0)),
+                };
+                let around = if distance > 0.0 { offset / distance } else { DVec2::ZERO };
+                let direction = dvec3(around.x * theta.sin(), around.y * theta.sin(), -theta.cos());
+                (direction * self.focal_distance, dvec3(lens.x, lens.y, 0.0))
+            }
+        };
+        let pixel = self.transform_inverse.transform_point3(pixel);
+        let origin = self.transform_inverse.transform_point3(origin);
+        let direction = (pixel - origin).normalize();
+        Some(Ray::new(origin, direction))
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use std::f64::consts::PI;
-
-    use glam::dvec3;
+    use std::{f64::consts::{PI, FRAC_PI_4}, sync::Mutex};
 
     use crate::{world::tests::default_world, transformations::view_transform, Color};
 
     use super::*;
 
+    #[derive(Default)]
+    struct ProgressRecorder {
+        events: Mutex<Vec<(Tile, f64)>>,
+        passes: Mutex<Vec<(usize, Canvas)>>,
+        cancel_after_first_tile: Option<CancellationToken>,
+    }
+
+    impl RenderObserver for ProgressRecorder {
+        fn tile_completed(&self, tile: Tile, percentage: f64) {
+            self.events.lock().unwrap().push((tile, percentage));
+            if let Some(cancel) = &self.cancel_after_first_tile {
+                cancel.cancel();
+            }
+        }
+
+        fn pass_completed(&self, pass: usize, canvas: &Canvas) {
+            self.passes.lock().unwrap().push((pass, canvas.clone()));
+        }
+    }
+
     const EPSILON: f64 = 0.00001;
 
     #[test]
@@ -172,7 +792,7 @@ mod tests {
     #[test]
     fn constructing_a_ray_through_the_center_of_the_canvas() {
         let c = Camera::new(201, 101, PI / 2.0);
-        let r = c.ray_for_pixel(100.0, 50.0);
+        let r = c.ray_for_pixel(100.0, 50.0).unwrap();
         assert!(r.origin.abs_diff_eq(dvec3(0.0, 0.0, 0.0), EPSILON));
         assert!(r.direction.abs_diff_eq(dvec3(0.0, 0.0, -1.0), EPSILON));
     }
@@ -180,7 +800,7 @@ mod tests {
     #[test]
     fn constructing_a_ray_through_a_corner_of_the_canvas() {
         let c = Camera::new(201,101,PI / 2.0);
-        let r = c.ray_for_pixel(0.0, 0.0);
+        let r = c.ray_for_pixel(0.0, 0.0).unwrap();
         assert!(r.origin.abs_diff_eq(dvec3(0.0, 0.0, 0.0), EPSILON));
         assert!(r.direction.abs_diff_eq(dvec3(0.66519, 0.33259, -0.66851), EPSILON));
     }
@@ -192,11 +812,73 @@ mod tests {
                 DMat4::from_rotation_y(PI / 4.0) 
               * DMat4::from_translation(dvec3(0.0, -2.0, 5.0))
             );
-        let r = c.ray_for_pixel(100.0, 50.0);
+        let r = c.ray_for_pixel(100.0, 50.0).unwrap();
         assert!(r.origin.abs_diff_eq(dvec3(0.0, 2.0, -5.0), EPSILON));
         assert!(r.direction.abs_diff_eq(dvec3(2.0_f64.sqrt() / 2.0, 0.0, -2.0_f64.sqrt() / 2.0), EPSILON));
     }
 
+    #[test]
+    fn a_ray_through_the_center_of_the_lens_ignores_the_focal_distance() {
+        let c = Camera::new(201, 101, PI / 2.0)
+            .with_aperture(0.5)
+            .with_focal_distance(4.0);
+        let r = c.ray_for_pixel(0.0, 0.0).unwrap();
+        assert!(r.origin.abs_diff_eq(dvec3(0.0, 0.0, 0.0), EPSILON));
+        assert!(r.direction.abs_diff_eq(dvec3(0.66519, 0.33259, -0.66851), EPSILON));
+    }
+
+    #[test]
+    fn rays_through_the_lens_converge_on_the_focal_plane() {
+        let c = Camera::new(201, 101, PI / 2.0)
+            .with_transform(
+                DMat4::from_rotation_y(PI / 4.0) 
+              * DMat4::from_translation(dvec3(0.0, -2.0, 5.0))
+            )
+            .with_aperture(1.0)
+            .with_focal_distance(5.0);
+        let center = c.ray_for_pixel(100.0, 50.0).unwrap();
+        let edge = c.ray_for_pixel_through_lens(100.0, 50.0, dvec2(1.0, 0.0)).unwrap();
+        assert!(!edge.origin.abs_diff_eq(center.origin, EPSILON));
+        assert!(((edge.origin - center.origin).length() - 0.5).abs() < EPSILON);
+
+        let focus = center.at(5.0);
+        let t = (focus - edge.origin).length();
+        assert!(edge.at(t).abs_diff_eq(focus, EPSILON));
+    }
+
+    #[test]
+    fn sampling_a_disk_aperture() {
+        let aperture = ApertureShape::Disk;
+        assert!(aperture.sample(0.0, 0.3).abs_diff_eq(DVec2::ZERO, EPSILON));
+        assert!(aperture.sample(1.0, 0.0).abs_diff_eq(dvec2(1.0, 0.0), EPSILON));
+        assert!(aperture.sample(0.25, 0.25).abs_diff_eq(dvec2(0.0, 0.5), EPSILON));
+        for (u, v) in [(0.1, 0.9), (0.5, 0.5), (0.99, 0.7)] {
+            assert!(aperture.sample(u, v).length() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn sampling_a_polygon_aperture() {
+        // with 4 blades, the aperture is a square rotated by 45 degrees
+        let aperture = ApertureShape::Polygon(4);
+        assert!(aperture.sample(0.0, 0.0).abs_diff_eq(DVec2::ZERO, EPSILON));
+        assert!(aperture.sample(0.0, 1.0).abs_diff_eq(dvec2(0.0, 1.0), EPSILON));
+        assert!(aperture.sample(0.25, 1.0).abs_diff_eq(dvec2(-1.0, 0.0), EPSILON));
+        for (u, v) in [(0.1, 0.9), (0.5, 0.5), (0.6, 0.95), (0.99, 0.7)] {
+            let p = aperture.sample(u, v);
+            assert!(p.x.abs() + p.y.abs() <= 1.0 + EPSILON);
+        }
+    }
+
+    #[test]
+    fn the_shutter_of_a_camera_is_instantaneous_by_default() {
+        let c = Camera::new(160, 120, PI / 2.0);
+        assert_eq!(c.shutter(), (0.0, 0.0));
+
+        let c = c.with_shutter(0.25, 0.75);
+        assert_eq!(c.shutter(), (0.25, 0.75));
+    }
+
     #[test]
     fn rendering_a_world_with_a_camera() {
         let w = default_world();
@@ -211,4 +893,345 @@ mod tests {
         let image = c.render(&w, 1);
         assert_eq!(image[5][5], Color::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn the_antialiasing_level_is_the_side_of_a_grid_of_samples() {
+        let c = Camera::new(160, 120, PI / 2.0);
+        assert_eq!(c.samples_per_pixel(), 1);
+        assert_eq!(c.sampler(), Sampler::Regular);
+        assert_eq!(c.filter(), Filter::Box);
+
+        let c = c.with_antialiasing(3);
+        assert_eq!(c.samples_per_pixel(), 9);
+        assert_eq!(c.antialiasing(), 3);
+    }
+
+    #[test]
+    fn rendering_the_background_with_any_sampler_and_filter() {
+        let w = World::new();
+        for (sampler, filter) in [(Sampler::Stratified, Filter::Tent), (Sampler::Sobol, Filter::Gaussian)] {
+            let c = Camera::new(4, 3, PI / 2.0)
+                .with_background(Color::new(0.2, 0.4, 0.6))
+                .with_samples_per_pixel(8)
+                .with_sampler(sampler)
+                .with_filter(filter);
+            let image = c.render(&w, 1);
+            assert_eq!(image[2][3], Color::new(0.2, 0.4, 0.6));
+        }
+    }
+
+    #[test]
+    fn the_error_of_the_samples_of_a_pixel() {
+        let mut pixel = PixelStats::default();
+        pixel.add(Color::white());
+        assert_eq!(pixel.error(), 0.0);
+        pixel.add(Color::white());
+        assert_eq!(pixel.error(), 0.0);
+        pixel.add(Color::black());
+        pixel.add(Color::black());
+        // standard deviation of 0.57735 over 4 samples
+        assert!((pixel.error() - 0.288675).abs() < EPSILON);
+        assert_eq!(pixel.mean(), Color::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn adaptive_sampling_refines_only_the_pixels_that_need_it() {
+        let w = default_world();
+        let c = Camera::new(11, 11, PI / 2.0)
+            .with_transform(
+                view_transform(
+                    dvec3(0.0, 0.0, -5.0),
+                    dvec3(0.0, 0.0, 0.0),
+                    dvec3(0.0, 1.0, 0.0)
+                )
+            )
+            .with_samples_per_pixel(2)
+            .with_adaptive_sampling(16, 0.001);
+        let (image, counts) = c.render_with_sample_counts(&w, 1);
+
+        // the background only gets the first samples
+        assert_eq!(image[0][0], Color::black());
+        assert_eq!(counts[0][0], Color::new(0.125, 0.125, 0.125));
+        // the sphere gets the most samples
+        assert_eq!(counts[5][5], Color::white());
+    }
+
+    #[test]
+    fn adaptive_sampling_ignores_the_pixels_outside_the_crop_window() {
+        let c = Camera::new(4, 4, PI / 2.0)
+            .with_crop(Tile::new(1, 1, 2, 2))
+            .with_adaptive_sampling(16, 0.001);
+        let mut stats = vec![vec![PixelStats::default(); 4]; 4];
+        for (x, y) in Tile::new(1, 1, 2, 2).pixels() {
+            stats[y][x].add(Color::white());
+        }
+        let refined = c.pixels_to_refine(&stats, 0.001);
+        assert!(refined.iter().flatten().all(|refine| !refine));
+    }
+
+    #[test]
+    fn without_adaptive_sampling_every_pixel_gets_the_same_samples() {
+        let w = default_world();
+        let c = Camera::new(11, 11, PI / 2.0)
+            .with_samples_per_pixel(4);
+        let (_, counts) = c.render_with_sample_counts(&w, 1);
+        assert!((0..11).all(|y| counts[y].iter().all(|level| *level == Color::white())));
+    }
+
+    #[test]
+    fn rendering_progressively_calls_back_after_each_pass() {
+        let w = default_world();
+        let c = Camera::new(11, 11, PI / 2.0)
+            .with_transform(
+                view_transform(
+                    dvec3(0.0, 0.0, -5.0),
+                    dvec3(0.0, 0.0, 0.0),
+                    dvec3(0.0, 1.0, 0.0)
+                )
+            );
+        let observer = ProgressRecorder::default();
+        let image = c.render_progressive(&w, 1, 3, None, &observer, &CancellationToken::new());
+        let passes = observer.passes.into_inner().unwrap();
+
+        assert_eq!(passes.iter().map(|(pass, _)| *pass).collect::<Vec<usize>>(), vec![1, 2, 3]);
+        // the first pass samples the centers of the pixels
+        assert_eq!(passes[0].1[5][5], Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(passes[2].1, image);
+        assert_eq!(image[0][0], Color::black());
+    }
+
+    #[test]
+    fn rendering_progressively_stops_when_the_time_budget_is_spent() {
+        let w = default_world();
+        let c = Camera::new(11, 11, PI / 2.0);
+        let observer = ProgressRecorder::default();
+        c.render_progressive(&w, 1, 100, Some(Duration::ZERO), &observer, &CancellationToken::new());
+        assert_eq!(observer.passes.into_inner().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rendering_tiles_reports_the_progress() {
+        let w = World::new();
+        let c = Camera::new(5, 3, PI / 2.0)
+            .with_tile_size(2);
+        let observer = ProgressRecorder::default();
+        let rendering = c.render_tiles(&w, 1, &observer, &CancellationToken::new());
+
+        let mut percentages: Vec<f64> = observer.events.into_inner().unwrap().iter().map(|event| event.1).collect();
+        percentages.sort_by(f64::total_cmp);
+        assert_eq!(percentages.len(), 6);
+        assert!(percentages.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(percentages[5], 100.0);
+        assert_eq!(rendering.tiles.len(), 6);
+        assert!(!rendering.cancelled);
+    }
+
+    #[test]
+    fn adaptive_sampling_completes_every_tile_twice() {
+        let w = World::new();
+        let c = Camera::new(5, 3, PI / 2.0)
+            .with_tile_size(2)
+            .with_adaptive_sampling(8, 0.01);
+        let observer = ProgressRecorder::default();
+        c.render_tiles(&w, 1, &observer, &CancellationToken::new());
+
+        let mut percentages: Vec<f64> = observer.events.into_inner().unwrap().iter().map(|event| event.1).collect();
+        percentages.sort_by(f64::total_cmp);
+        assert_eq!(percentages.len(), 12);
+        assert_eq!(percentages[5], 50.0);
+        assert_eq!(percentages[11], 100.0);
+    }
+
+    #[test]
+    fn a_cancelled_rendering_keeps_the_completed_tiles() {
+        let w = World::new();
+        let c = Camera::new(10, 10, PI / 2.0)
+            .with_background(Color::white())
+            .with_filter(Filter::Mitchell)
+            .with_tile_size(2);
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let rendering = c.render_tiles(&w, 1, &(), &cancel);
+        assert!(rendering.cancelled);
+        assert!(rendering.tiles.is_empty());
+        assert_eq!(rendering.canvas, Canvas::new(10, 10));
+
+        let cancel = CancellationToken::new();
+        let observer = ProgressRecorder { cancel_after_first_tile: Some(cancel.clone()), ..ProgressRecorder::default() };
+        let rendering = c.render_tiles(&w, 1, &observer, &cancel);
+        assert!(rendering.cancelled);
+        assert!(!rendering.tiles.is_empty() && rendering.tiles.len() < 25);
+        for y in 0..10 {
+            for x in 0..10 {
+                let completed = rendering.tiles.iter().any(|tile| tile.contains(x, y));
+                assert_eq!(rendering.canvas[y][x], if completed { Color::white() } else { Color::black() });
+            }
+        }
+    }
+
+    #[test]
+    fn a_cancelled_progressive_rendering_stops_after_the_current_pass() {
+        let w = World::new();
+        let c = Camera::new(10, 10, PI / 2.0)
+            .with_tile_size(2);
+
+        let cancel = CancellationToken::new();
+        let observer = ProgressRecorder { cancel_after_first_tile: Some(cancel.clone()), ..ProgressRecorder::default() };
+        c.render_progressive(&w, 1, 4, None, &observer, &cancel);
+        assert_eq!(observer.passes.into_inner().unwrap().len(), 1);
+
+        let events = observer.events.into_inner().unwrap();
+        assert!(!events.is_empty() && events.len() < 25);
+        // the percentage is the one of the four passes
+        assert!(events.iter().all(|event| event.1 < 25.0));
+    }
+
+    #[test]
+    fn a_crop_window_in_pixels_or_in_fractions_of_the_image() {
+        let c = Camera::new(200, 100, PI / 2.0);
+        assert_eq!(c.crop(), None);
+        assert_eq!(c.with_crop(Tile::new(150, 80, 100, 100)).crop(), Some(Tile::new(150, 80, 50, 20)));
+
+        let c = Camera::new(200, 100, PI / 2.0)
+            .with_crop_window(0.25, 0.5, 0.5025, 1.0);
+        assert_eq!(c.crop(), Some(Tile::new(50, 50, 51, 50)));
+    }
+
+    #[test]
+    fn rendering_only_a_crop_window() {
+        let w = default_world();
+        let c = Camera::new(11, 11, PI / 2.0)
+            .with_transform(
+                view_transform(
+                    dvec3(0.0, 0.0, -5.0),
+                    dvec3(0.0, 0.0, 0.0),
+                    dvec3(0.0, 1.0, 0.0)
+                )
+            )
+            .with_background(Color::white())
+            .with_filter(Filter::Tent)
+            .with_crop(Tile::new(4, 4, 3, 2));
+        let full = Camera::new(11, 11, PI / 2.0)
+            .with_transform(*c.transform())
+            .with_background(Color::white())
+            .with_filter(Filter::Tent)
+            .render(&w, 1);
+
+        let image = c.render(&w, 1);
+        assert_eq!((image.width(), image.height()), (11, 11));
+        assert_eq!(image[5][5], full[5][5]);
+        assert_eq!(image[4][6], full[4][6]);
+        assert_eq!(image[0][0], Color::black());
+        assert_eq!(image[6][5], Color::black());
+
+        let cropped = c.render_cropped(&w, 1);
+        assert_eq!((cropped.width(), cropped.height()), (3, 2));
+        assert_eq!(cropped[1][1], full[5][5]);
+    }
+
+    #[test]
+    fn an_orthographic_camera_casts_parallel_rays() {
+        let c = Camera::new(201, 101, PI / 2.0)
+            .with_orthographic(4.0, 2.0);
+        assert_eq!(c.projection(), Projection::Orthographic { view_width: 4.0, view_height: 2.0 });
+
+        let r = c.ray_for_pixel(100.0, 50.0).unwrap();
+        assert!(r.origin.abs_diff_eq(dvec3(0.0, 0.0, 0.0), EPSILON));
+        assert!(r.direction.abs_diff_eq(dvec3(0.0, 0.0, -1.0), EPSILON));
+
+        let r = c.ray_for_pixel(0.0, 0.0).unwrap();
+        assert!(r.origin.abs_diff_eq(dvec3(2.0 - 2.0 / 201.0, 1.0 - 1.0 / 101.0, 0.0), EPSILON));
+        assert!(r.direction.abs_diff_eq(dvec3(0.0, 0.0, -1.0), EPSILON));
+    }
+
+    #[test]
+    fn constructing_an_orthographic_ray_when_the_camera_is_transformed() {
+        let c = Camera::new(201, 101, PI / 2.0)
+            .with_orthographic(4.0, 2.0)
+            .with_transform(
+                DMat4::from_rotation_y(PI / 4.0)
+              * DMat4::from_translation(dvec3(0.0, -2.0, 5.0))
+            );
+        let r = c.ray_for_pixel(100.0, 0.0).unwrap();
+        assert!(r.origin.abs_diff_eq(dvec3(0.0, 3.0 - 1.0 / 101.0, -5.0), EPSILON));
+        assert!(r.direction.abs_diff_eq(dvec3(2.0_f64.sqrt() / 2.0, 0.0, -2.0_f64.sqrt() / 2.0), EPSILON));
+    }
+
+    #[test]
+    fn rendering_a_world_with_an_orthographic_camera() {
+        let w = default_world();
+        let c = Camera::new(11, 11, PI / 2.0)
+            .with_orthographic(4.0, 4.0)
+            .with_transform(
+                view_transform(
+                    dvec3(0.0, 0.0, -5.0),
+                    dvec3(0.0, 0.0, 0.0),
+                    dvec3(0.0, 1.0, 0.0)
+                )
+            );
+        let image = c.render(&w, 1);
+        assert_eq!(image[5][5], Color::new(0.38066, 0.47583, 0.2855));
+        // the sphere of radius 1 covers the pixels within 1 of the center
+        assert_ne!(image[5][3], Color::black());
+        assert_eq!(image[5][1], Color::black());
+        assert_eq!(image[0][0], Color::black());
+    }
+
+    #[test]
+    fn an_equirectangular_camera_sees_every_direction() {
+        let c = Camera::new(360, 180, PI / 2.0)
+            .with_projection(Projection::Equirectangular);
+        let direction = |x, y| c.ray_for_pixel(x, y).unwrap().direction;
+        assert!(direction(179.5, 89.5).abs_diff_eq(dvec3(0.0, 0.0, -1.0), EPSILON));
+        assert!(direction(89.5, 89.5).abs_diff_eq(dvec3(1.0, 0.0, 0.0), EPSILON));
+        assert!(direction(269.5, 89.5).abs_diff_eq(dvec3(-1.0, 0.0, 0.0), EPSILON));
+        assert!(direction(-0.5, 89.5).abs_diff_eq(dvec3(0.0, 0.0, 1.0), EPSILON));
+        assert!(direction(179.5, -0.5).abs_diff_eq(dvec3(0.0, 1.0, 0.0), EPSILON));
+        assert!(direction(179.5, 179.5).abs_diff_eq(dvec3(0.0, -1.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn a_fisheye_camera_maps_its_field_of_view_to_a_circle() {
+        let c = Camera::new(201, 101, PI)
+            .with_fisheye(FisheyeMapping::Equidistant);
+        assert_eq!(c.projection(), Projection::Fisheye(FisheyeMapping::Equidistant));
+        let direction = |x, y| c.ray_for_pixel(x, y).unwrap().direction;
+        assert!(direction(100.0, 50.0).abs_diff_eq(dvec3(0.0, 0.0, -1.0), EPSILON));
+        assert!(direction(100.0, -0.5).abs_diff_eq(dvec3(0.0, 1.0, 0.0), EPSILON));
+        assert!(direction(49.5, 50.0).abs_diff_eq(dvec3(1.0, 0.0, 0.0), EPSILON));
+        // half way to the border of the circle
+        assert!(direction(74.75, 50.0).abs_diff_eq(dvec3(FRAC_PI_4.sin(), 0.0, -FRAC_PI_4.cos()), EPSILON));
+        assert!(c.ray_for_pixel(0.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn an_equisolid_fisheye_compresses_the_border_of_the_image() {
+        let c = Camera::new(201, 101, PI)
+            .with_fisheye(FisheyeMapping::Equisolid);
+        let direction = |x, y| c.ray_for_pixel(x, y).unwrap().direction;
+        assert!(direction(49.5, 50.0).abs_diff_eq(dvec3(1.0, 0.0, 0.0), EPSILON));
+        let theta = 2.0 * f64::asin(0.5 * FRAC_PI_4.sin());
+        assert!(direction(74.75, 50.0).abs_diff_eq(dvec3(theta.sin(), 0.0, -theta.cos()), EPSILON));
+    }
+
+    #[test]
+    fn rendering_a_world_with_panoramic_cameras() {
+        let w = default_world();
+        for projection in [Projection::Equirectangular, Projection::Fisheye(FisheyeMapping::Equisolid)] {
+            let c = Camera::new(11, 11, PI)
+                .with_projection(projection)
+                .with_background(Color::white())
+                .with_transform(
+                    view_transform(
+                        dvec3(0.0, 0.0, -5.0),
+                        dvec3(0.0, 0.0, 0.0),
+                        dvec3(0.0, 1.0, 0.0)
+                    )
+                );
+            let image = c.render(&w, 1);
+            assert_eq!(image[5][5], Color::new(0.38066, 0.47583, 0.2855));
+            assert_eq!(image[5][0], Color::white());
+        }
+    }
 }
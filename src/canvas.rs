@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use crate::color::Color;
+use crate::{color::Color, tile::Tile};
 
 fn scale_color(color: Color) -> (u8, u8, u8) {
     (
@@ -11,17 +11,10 @@ fn scale_color(color: Color) -> (u8, u8, u8) {
 }
 
 fn scale_color_component(component: f64) -> u8 {
-    let component = if component < 0.0 {
-        0.0
-    } else if component > 1.0 {
-        1.0
-    } else {
-        component
-    };
-
-    (component * 255.0) as u8
+    (component.clamp(0.0, 1.0) * 255.0) as u8
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     width: usize,
     height: usize,
@@ -48,9 +41,26 @@ impl Canvas {
         img.save(path)
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn pixels_mut(&mut self) -> &mut Vec<Color> {
         &mut self.pixels
     }
+
+    /// copy of the pixels of the tile, which must be within the canvas
+    pub fn crop(&self, tile: &Tile) -> Canvas {
+        let mut canvas = Canvas::new(tile.width, tile.height);
+        for (x, y) in tile.pixels() {
+            canvas[y - tile.y][x - tile.x] = self[y][x];
+        }
+        canvas
+    }
 }
 
 impl std::ops::Index<usize> for Canvas {
@@ -95,4 +105,14 @@ mod tests {
         c[2][3] = Color::red();
         assert_eq!(c[2][3], Color::red());
     }
+
+    #[test]
+    fn cropping_a_canvas() {
+        let mut c = Canvas::new(10, 20);
+        c[2][3] = Color::red();
+        let cropped = c.crop(&Tile::new(3, 1, 4, 2));
+        assert_eq!((cropped.width(), cropped.height()), (4, 2));
+        assert_eq!(cropped[1][0], Color::red());
+        assert_eq!(cropped[0][0], Color::black());
+    }
 }
\ No newline at end of file
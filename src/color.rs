@@ -33,6 +33,20 @@ impl Color {
     pub fn blue() -> Color {
         Color::new(0.0, 0.0, 1.0)
     }
+
+    /// decodes a color stored with the sRGB transfer function, like most
+    /// image files, to the linear space used by the lighting
+    pub fn srgb_to_linear(self) -> Color {
+        fn decode(component: f64) -> f64 {
+            if component <= 0.04045 {
+                component / 12.92
+            } else {
+                ((component + 0.055) / 1.055).powf(2.4)
+            }
+        }
+
+        Color::new(decode(self.r), decode(self.g), decode(self.b))
+    }
 }
 
 impl Default for Color {
@@ -244,4 +258,11 @@ mod tests {
         c1 *= c2;
         assert_eq!(c1, Color::new(0.9, 0.2, 0.04));
     }
+
+    #[test]
+    fn converting_an_srgb_color_to_linear() {
+        assert_eq!(Color::black().srgb_to_linear(), Color::black());
+        assert_eq!(Color::white().srgb_to_linear(), Color::white());
+        assert_eq!(Color::new(0.5, 0.04, 0.8).srgb_to_linear(), Color::new(0.21404, 0.0031, 0.60383));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,162 @@
+use std::{sync::Mutex, ops::Range};
+
+use glam::{DVec2, dvec2};
+
+use crate::{Canvas, Color, filter::Filter};
+
+/// accumulates the samples of an image, the filter spreads each sample over
+/// the pixels around it
+pub struct Film {
+    width: usize,
+    height: usize,
+    filter: Filter,
+    /// weighted sum of the colors, sum of the weights and sum of their
+    /// absolute values for each pixel, the rows are locked one at a time by
+    /// the threads adding samples
+    rows: Vec<Mutex<Vec<(Color, f64, f64)>>>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize, filter: Filter) -> Self {
+        Self {
+            width,
+            height,
+            filter,
+            rows: (0..height)
+                .map(|_| Mutex::new(vec![(Color::black(), 0.0, 0.0); width]))
+                .collect(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// the position is in pixels, the pixel (x, y) covers the square
+    /// from (x, y) to (x + 1, y + 1)
+    pub fn add_sample(&self, position: DVec2, color: Color) {
+        self.add_samples(&[(position, color)]);
+    }
+
+    /// each row touched by the samples is locked once
+    pub fn add_samples(&self, samples: &[(DVec2, Color)]) {
+        let (low, high) = samples
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), (position, _)| {
+                (low.min(position.y), high.max(position.y))
+            });
+        let radius = self.filter.radius();
+        for y in Self::pixels_around(low, high, radius, self.height) {
+            let mut row = self.rows[y].lock().unwrap();
+            for (position, color) in samples {
+                let dy = position.y - (y as f64 + 0.5);
+                if dy.abs() > radius {
+                    continue;
+                }
+                for x in Self::pixels_around(position.x, position.x, radius, self.width) {
+                    let weight = self.filter.weight(dvec2(position.x - (x as f64 + 0.5), dy));
+                    if weight != 0.0 {
+                        row[x].0 += *color * weight;
+                        row[x].1 += weight;
+                        row[x].2 += weight.abs();
+                    }
+                }
+            }
+        }
+    }
+
+    /// pixels whose center is within the radius of the span from low to high
+    fn pixels_around(low: f64, high: f64, radius: f64, count: usize) -> Range<usize> {
+        let first = (low - 0.5 - radius).ceil().max(0.0);
+        let last = (high - 0.5 + radius).floor() + 1.0;
+        first as usize..(last.max(0.0) as usize).min(count)
+    }
+
+    /// pixels without any sample are black, when the negative lobes of the
+    /// filter cancel most of the weight of a pixel its color is normalised by
+    /// the absolute weights instead and kept from going below black
+    pub fn to_canvas(&self) -> Canvas {
+        let mut canvas = Canvas::new(self.width, self.height);
+        for (y, row) in self.rows.iter().enumerate() {
+            for (x, (color, weight, absolute)) in row.lock().unwrap().iter().enumerate() {
+                if *absolute > 0.0 {
+                    let weight = if weight.abs() >= *absolute * 0.5 { *weight } else { *absolute };
+                    let color = *color / weight;
+                    canvas[y][x] = Color::new(color.r.max(0.0), color.g.max(0.0), color.b.max(0.0));
+                }
+            }
+        }
+        canvas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_box_filter_averages_the_samples_of_each_pixel() {
+        let film = Film::new(2, 2, Filter::Box);
+        film.add_samples(&[
+            (dvec2(0.25, 0.25), Color::white()),
+            (dvec2(0.75, 0.75), Color::black()),
+            (dvec2(1.0, 1.0), Color::red()),
+        ]);
+        let canvas = film.to_canvas();
+        assert_eq!(canvas[0][0], Color::new(0.5, 0.5, 0.5));
+        assert_eq!(canvas[1][1], Color::red());
+        assert_eq!(canvas[0][1], Color::black());
+        assert_eq!(canvas[1][0], Color::black());
+    }
+
+    #[test]
+    fn a_wider_filter_spreads_a_sample_over_the_neighbouring_pixels() {
+        let film = Film::new(3, 1, Filter::Tent);
+        film.add_sample(dvec2(1.0, 0.5), Color::white());
+        film.add_sample(dvec2(2.0, 0.5), Color::black());
+        let canvas = film.to_canvas();
+        assert_eq!(canvas[0][0], Color::white());
+        assert_eq!(canvas[0][1], Color::new(0.5, 0.5, 0.5));
+        assert_eq!(canvas[0][2], Color::black());
+    }
+
+    #[test]
+    fn samples_near_the_border_stay_in_the_film() {
+        let film = Film::new(2, 1, Filter::Mitchell);
+        film.add_sample(dvec2(0.1, 0.1), Color::white());
+        film.add_sample(dvec2(1.9, 0.9), Color::white());
+        let canvas = film.to_canvas();
+        assert_eq!(canvas[0][0], Color::white());
+        assert_eq!(canvas[0][1], Color::white());
+    }
+
+    #[test]
+    fn the_negative_lobes_of_a_filter_neither_blacken_nor_blow_up_a_pixel() {
+        // only the negative lobe of the filter reaches the last pixel
+        let film = Film::new(4, 1, Filter::Mitchell);
+        film.add_sample(dvec2(1.7, 0.5), Color::white());
+        assert_eq!(film.to_canvas()[0][3], Color::white());
+
+        // the weights of the black samples almost cancel the one of the white
+        // sample for the second pixel
+        let film = Film::new(4, 1, Filter::Mitchell);
+        film.add_samples(&[
+            (dvec2(0.0, 0.5), Color::black()),
+            (dvec2(2.47, 0.5), Color::white()),
+            (dvec2(3.0, 0.5), Color::black()),
+        ]);
+        let canvas = film.to_canvas();
+        for x in 0..4 {
+            let color = canvas[0][x];
+            assert!(color.r >= 0.0 && color.r <= 1.0, "pixel {} is {:?}", x, color);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,103 @@
+use glam::DVec2;
+
+/// reconstruction filter weighting the samples around the center of a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Filter {
+    /// plain average of the samples falling in the pixel
+    #[default]
+    Box,
+    Tent,
+    Gaussian,
+    /// cubic with B = C = 1/3, sharper than the gaussian
+    Mitchell,
+}
+
+const GAUSSIAN_FALLOFF: f64 = 2.0;
+const MITCHELL_B: f64 = 1.0 / 3.0;
+const MITCHELL_C: f64 = 1.0 / 3.0;
+
+impl Filter {
+    /// samples farther than the radius from the center of a pixel, along x
+    /// or y, don't contribute to it
+    pub fn radius(&self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+        }
+    }
+
+    /// offset is the position of the sample relative to the center of the pixel
+    pub fn weight(&self, offset: DVec2) -> f64 {
+        self.weight_1d(offset.x) * self.weight_1d(offset.y)
+    }
+
+    fn weight_1d(&self, d: f64) -> f64 {
+        let radius = self.radius();
+        match self {
+            // half open so that a sample on the border belongs to a single pixel
+            Filter::Box => match (-radius..radius).contains(&d) {
+                true => 1.0,
+                false => 0.0,
+            },
+            Filter::Tent => f64::max(0.0, 1.0 - d.abs() / radius),
+            Filter::Gaussian => f64::max(
+                0.0,
+                f64::exp(-GAUSSIAN_FALLOFF * d * d) - f64::exp(-GAUSSIAN_FALLOFF * radius * radius)
+            ),
+            Filter::Mitchell => {
+                let (b, c) = (MITCHELL_B, MITCHELL_C);
+                let x = d.abs() * 2.0 / radius;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b)) / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c)) / 6.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec2;
+
+    use super::*;
+
+    const EPSILON: f64 = 0.00001;
+
+    #[test]
+    fn a_box_filter_keeps_the_samples_of_the_pixel() {
+        let f = Filter::Box;
+        assert_eq!(f.weight(dvec2(0.0, 0.0)), 1.0);
+        assert_eq!(f.weight(dvec2(-0.5, 0.49)), 1.0);
+        assert_eq!(f.weight(dvec2(0.5, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn the_weight_of_a_filter_decreases_away_from_the_center() {
+        for filter in [Filter::Tent, Filter::Gaussian, Filter::Mitchell] {
+            let center = filter.weight(DVec2::ZERO);
+            let near = filter.weight(dvec2(0.25, 0.0));
+            let far = filter.weight(dvec2(0.5, 0.5));
+            assert!(center > near && near > far && far > 0.0);
+            assert!(filter.weight(dvec2(filter.radius(), 0.0)).abs() < EPSILON);
+        }
+        assert_eq!(Filter::Tent.weight(dvec2(0.5, 0.0)), 0.5);
+    }
+
+    #[test]
+    fn the_mitchell_filter_has_negative_lobes() {
+        let f = Filter::Mitchell;
+        assert!((f.weight(DVec2::ZERO) - 64.0 / 81.0).abs() < EPSILON);
+        assert!(f.weight(dvec2(1.5, 0.0)) < 0.0);
+    }
+}
\ No newline at end of file
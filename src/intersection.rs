@@ -1,8 +1,8 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, borrow::Cow};
 
-use glam::DVec3;
+use glam::{DVec3, DMat4};
 
-use crate::{object::Object, ray::Ray};
+use crate::{object::Object, ray::Ray, transformations::Transform, Material};
 
 const EPSILON: f64 = 0.00001;
 
@@ -23,24 +23,58 @@ impl HitPredicate for StandardHit {
     }
 }
 
-pub struct ShadowHit {}
-
-impl HitPredicate for ShadowHit {
-    fn hit_predicate(&self) -> Box<dyn FnMut(&&Intersection<'_>) -> bool> {
-        Box::new(|i| i.object.shadow() && i.t >= 0.0)
-    }
-
-    fn hit_index_predicate(&self) -> Box<dyn FnMut(&Intersection<'_>) -> bool> {
-        Box::new(|i| i.object.shadow() && i.t >= 0.0)
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct Intersection<'a> {
     t: f64,
     object: &'a Object,
     u: f64,
     v: f64,
+    /// face of the mesh that was hit
+    face: usize,
+    /// set when the object is shared by instances
+    placement: Option<Placement<'a>>,
+}
+
+/// where an instance puts the object it shares, the matrix goes from the
+/// space of the shared object to the world. it is kept by every candidate
+/// hit, so its inverse is only computed for the hit that gets shaded
+#[derive(Debug, Clone, Copy)]
+pub struct Placement<'a> {
+    instance: &'a Object,
+    matrix: DMat4,
+    material: Option<&'a Material>,
+}
+
+impl<'a> Placement<'a> {
+    /// the object holding the outermost instance the hit went through
+    pub fn instance(&self) -> &'a Object {
+        self.instance
+    }
+
+    pub fn matrix(&self) -> &DMat4 {
+        &self.matrix
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::from_matrix(self.matrix)
+    }
+
+    /// overrides the material of the object
+    pub fn material(&self) -> Option<&'a Material> {
+        self.material
+    }
+}
+
+/// the same instances put the object at the same place
+impl<'a> PartialEq for Placement<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.instance, other.instance)
+            && self.matrix == other.matrix
+            && match (self.material, other.material) {
+                (Some(a), Some(b)) => std::ptr::eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
 }
 
 impl<'a> Intersection<'a> {
@@ -50,6 +84,8 @@ impl<'a> Intersection<'a> {
             object,
             u: 0.0, 
             v: 0.0,
+            face: 0,
+            placement: None,
         }
     }
 
@@ -59,12 +95,52 @@ impl<'a> Intersection<'a> {
         self
     }
 
+    pub fn with_face(mut self, face: usize) -> Self {
+        self.face = face;
+        self
+    }
+
+    /// the hit went through the instance held by the given object, outer
+    /// instances are applied last and their material wins
+    pub fn placed(mut self, instance: &'a Object, matrix: &DMat4, material: Option<&'a Material>) -> Self {
+        self.placement = Some(match self.placement.take() {
+            Some(inner) => Placement {
+                instance,
+                matrix: *matrix * inner.matrix,
+                material: material.or(inner.material),
+            },
+            None => Placement {
+                instance,
+                matrix: *matrix,
+                material,
+            },
+        });
+        self
+    }
+
     pub fn t(&self) -> f64 {
         self.t
     }
 
-    pub fn object(&self) -> &Object {
-        &self.object
+    pub fn object(&self) -> &'a Object {
+        self.object
+    }
+
+    pub fn placement(&self) -> Option<&Placement<'a>> {
+        self.placement.as_ref()
+    }
+
+    /// the material of the object unless an instance overrides it
+    pub fn material(&self) -> &'a Material {
+        self.placement
+            .as_ref()
+            .and_then(|p| p.material)
+            .unwrap_or(self.object.material())
+    }
+
+    /// same object at the same place, instances of an object are different surfaces
+    pub fn same_surface(&self, other: &Intersection) -> bool {
+        std::ptr::eq(self.object, other.object) && self.placement() == other.placement()
     }
 
     pub fn u(&self) -> f64 {
@@ -74,6 +150,10 @@ impl<'a> Intersection<'a> {
     pub fn v(&self) -> f64 {
         self.v
     }
+
+    pub fn face(&self) -> usize {
+        self.face
+    }
 }
 
 impl<'a> PartialOrd for Intersection<'a> {
@@ -85,28 +165,27 @@ impl<'a> PartialOrd for Intersection<'a> {
 impl<'a> Ord for Intersection<'a> {
     fn cmp(&self, other: &Self) -> Ordering {
         if self.t < other.t {
-            return Ordering::Less;
+            Ordering::Less
         }
         else if self.t > other.t {
-            return Ordering::Greater;
+            Ordering::Greater
         }
         else {
-            return Ordering::Equal;
+            Ordering::Equal
         }
     }
 }
 
 impl<'a> Eq for Intersection<'a> {}
 
+#[derive(Default)]
 pub struct Intersections<'a> {
     intersections: Vec<Intersection<'a>>,
 }
 
 impl<'a> Intersections<'a> {
     pub fn new() -> Self {
-        Self {  
-            intersections: Vec::default()
-        }
+        Self::default()
     }
 
     pub fn from_capacity(size: usize) -> Self {
@@ -133,7 +212,7 @@ impl<'a> Intersections<'a> {
         self
     }
 
-    pub fn hit(&self, predicate: impl HitPredicate) -> Option<&Intersection> {
+    pub fn hit(&self, predicate: impl HitPredicate) -> Option<&Intersection<'_>> {
         self.intersections.iter().find(predicate.hit_predicate())
     }
 
@@ -141,11 +220,22 @@ impl<'a> Intersections<'a> {
         self.intersections.iter().position(predicate.hit_index_predicate())
     }
 
+    /// the intersection with the surface of the hit nearest to it, the same
+    /// hit found by another traversal may be rounded differently
+    pub fn index_of(&self, hit: &Intersection) -> Option<usize> {
+        self.intersections
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.same_surface(hit))
+            .min_by(|(_, a), (_, b)| (a.t() - hit.t()).abs().total_cmp(&(b.t() - hit.t()).abs()))
+            .map(|(index, _)| index)
+    }
+
     pub fn count(&self) -> usize {
         self.intersections.len()
     }
 
-    pub fn get(&self, index: usize) -> Option<&Intersection> {
+    pub fn get(&self, index: usize) -> Option<&Intersection<'_>> {
         self.intersections.get(index)
     }
 
@@ -177,29 +267,20 @@ pub struct IntersectionInfos<'a> {
     pub normalv: DVec3,
     pub reflectv: DVec3,
     pub inside: bool,
-    pub n: (f64, f64)
+    pub n: (f64, f64),
+    pub time: f64,
+    /// material of the object unless an instance overrides it
+    pub material: &'a Material,
+    pub placement: Option<Placement<'a>>,
 }
 
 impl<'a> IntersectionInfos<'a> {
     pub fn new(intersections: &'a Intersections<'a>, intersection_index: usize, ray: &Ray) -> Self {
-        let intersection = intersections.get(intersection_index).unwrap();
-
-        let point = ray.at(intersection.t);
-        let object = intersection.object;
-        let eyev = -ray.direction;
-        let mut normalv = object.normal_at(point, intersection.u, intersection.v);
-        let mut inside = false;
-        if normalv.dot(eyev) < 0.0 {
-            inside = true;
-            normalv = -normalv;
-        }
-        let over_point = point + normalv * EPSILON;
-        let under_point = point - normalv * EPSILON;
-        let reflectv = ray.direction - normalv * 2.0 * ray.direction.dot(normalv);
+        let mut infos = Self::from_hit(&intersections[intersection_index], ray);
 
         // find n1 n2
         let (mut n1, mut n2) = (0.0, 0.0);
-        let mut containers: Vec<&Object> = Vec::default();
+        let mut containers: Vec<&Intersection> = Vec::default();
         for (current_index, i) in intersections.get_all().iter().enumerate() {
             if current_index == intersection_index {
                 if containers.is_empty() {
@@ -211,11 +292,11 @@ impl<'a> IntersectionInfos<'a> {
 
             match containers
                 .iter()
-                .position(|&object| object == i.object) {
+                .position(|c| c.same_surface(i)) {
                     Some(pos) => {
                         containers.remove(pos);
                     }
-                    None => containers.push(i.object),
+                    None => containers.push(i),
             }
 
             if current_index == intersection_index {
@@ -227,6 +308,38 @@ impl<'a> IntersectionInfos<'a> {
             }
         }
 
+        infos.n = (n1, n2);
+        infos
+    }
+
+    /// the refractive indices are unknown without the other intersections of
+    /// the ray, both are those of the vacuum
+    pub fn from_hit(intersection: &Intersection<'a>, ray: &Ray) -> Self {
+        let point = ray.at(intersection.t);
+        let object = intersection.object;
+        let eyev = -ray.direction;
+        let mut normalv = match intersection.placement() {
+            Some(placement) => {
+                let transform = placement.transform();
+                let local_point = transform.inverse_matrix.transform_point3(point);
+                let local_normal = object
+                    .at_time(ray.time)
+                    .normal_at_intersection(local_point, intersection);
+                transform.inverse_transpose_matrix.transform_vector3(local_normal).normalize()
+            }
+            None => object
+                .at_time(ray.time)
+                .normal_at_intersection(point, intersection),
+        };
+        let mut inside = false;
+        if normalv.dot(eyev) < 0.0 {
+            inside = true;
+            normalv = -normalv;
+        }
+        let over_point = point + normalv * EPSILON;
+        let under_point = point - normalv * EPSILON;
+        let reflectv = ray.direction - normalv * 2.0 * ray.direction.dot(normalv);
+
         Self {
             t: intersection.t,
             object,
@@ -237,7 +350,19 @@ impl<'a> IntersectionInfos<'a> {
             normalv,
             reflectv,
             inside,
-            n: (n1, n2)
+            n: (1.0, 1.0),
+            time: ray.time,
+            material: intersection.material(),
+            placement: intersection.placement().copied(),
+        }
+    }
+
+    /// the object standing where it is at the time of the ray,
+    /// placed by the instances it was hit through
+    pub fn shading_object(&self) -> Cow<'a, Object> {
+        match &self.placement {
+            Some(placement) => Cow::Owned(self.object.placed(self.time, placement)),
+            None => self.object.at_time(self.time),
         }
     }
 
@@ -255,7 +380,7 @@ impl<'a> IntersectionInfos<'a> {
 
         let r0 = ((self.n.0 - self.n.1) / (self.n.0 + self.n.1)).powi(2);
 
-        return r0 + (1.0 - r0) * (1.0 - cos).powi(5);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
     }
 }
 
@@ -335,6 +460,22 @@ mod tests {
         assert_eq!(i, Some(&i4));
     }
 
+    #[test]
+    fn finding_a_hit_rounded_differently_in_a_list_of_intersections() {
+        let a = Object::new(Shape::Sphere(Sphere::default()));
+        let b = Object::new(Shape::Sphere(Sphere::default()));
+        let xs = Intersections::new().with_intersections(vec![
+            Intersection::new(4.0, &a),
+            Intersection::new(4.0, &b),
+            Intersection::new(6.0, &b),
+            Intersection::new(6.0, &a),
+        ]);
+        assert_eq!(xs.index_of(&Intersection::new(4.0 + 1e-12, &b)), Some(1));
+        assert_eq!(xs.index_of(&Intersection::new(6.0 - 1e-12, &a)), Some(3));
+        let c = Object::new(Shape::Sphere(Sphere::default()));
+        assert_eq!(xs.index_of(&Intersection::new(4.0, &c)), None);
+    }
+
     #[test]
     fn precomputing_the_state_of_an_intersection() {
         let r = Ray::new(
@@ -362,7 +503,7 @@ mod tests {
         let i = Intersection::new(4.0,&o);
         let xs = Intersections::new().with_intersections(vec![i.clone()]);
         let comps = IntersectionInfos::new(&xs, 0, &r);
-        assert_eq!(comps.inside, false);
+        assert!(!comps.inside);
     }
 
     #[test]
@@ -379,7 +520,7 @@ mod tests {
         assert_eq!(comps.object, i.object);
         assert_eq!(comps.point, dvec3(0.0, 0.0, 1.0));
         assert_eq!(comps.eyev, dvec3(0.0, 0.0, -1.0));
-        assert_eq!(comps.inside, true);
+        assert!(comps.inside);
         assert_eq!(comps.normalv, dvec3(0.0, 0.0, -1.0));
     }
 
@@ -1,23 +1,33 @@
+#![cfg_attr(test, allow(clippy::approx_constant))]
+
 pub mod shapes {
     pub use shape::Shape;
     pub use sphere::Sphere;
     pub use plane::Plane;
     pub use cube::Cube;
     pub use cylinder::Cylinder;
+    pub use cone::Cone;
+    pub use csg::{Csg, CsgOperation};
     pub use group::Group;
     pub use triangle::Triangle;
     pub use smooth_triangle::SmoothTriangle;
-    pub use mesh::Mesh;
+    pub use mesh::{Mesh, Face};
+    pub use linear_bvh::LinearBvh;
+    pub use instance::Instance;
 
     pub mod shape;
     pub mod sphere;
     pub mod plane;
     pub mod cube;
     pub mod cylinder;
+    pub mod cone;
+    pub mod csg;
     pub mod triangle;
     pub mod smooth_triangle;
     pub mod mesh;
     pub mod group;
+    pub mod linear_bvh;
+    pub mod instance;
     pub mod test_shape;
 }
 
@@ -38,11 +48,11 @@ pub use camera::Camera;
 pub use canvas::Canvas;
 pub use color::Color;
 pub use pattern::Pattern;
-pub use yaml::YamlLoader;
-pub use obj::ObjLoader;
+pub use yaml::{YamlLoader, SceneError};
+pub use obj::{ObjLoader, ObjError};
 
-mod ray;
-mod intersection;
+pub mod ray;
+pub mod intersection;
 pub mod transformations;
 pub mod object;
 pub mod material;
@@ -50,8 +60,15 @@ pub mod world;
 pub mod canvas;
 pub mod color;
 pub mod camera;
+pub mod sampler;
+pub mod filter;
+pub mod film;
+pub mod tile;
+pub mod progress;
 pub mod pattern;
+pub mod texture;
 pub mod bounds;
+pub mod bvh;
 mod sequence;
 pub mod yaml;
 pub mod obj;
\ No newline at end of file
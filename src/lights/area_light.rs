@@ -55,13 +55,14 @@ impl AreaLight {
         self.vvec * (v as f64 + jitter_by.next().unwrap()) 
     }
 
-    fn intensity_at_impl(&self, world_point: DVec3, world: &crate::World, jitter_by: &mut Sequence<f64>) -> f64 {
+    fn intensity_at_impl(&self, world_point: DVec3, time: f64, world: &crate::World, jitter_by: &mut Sequence<f64>) -> f64 {
         let mut total = 0.0;
         for v in 0..self.vsteps {
             for u in 0..self.usteps {
                 total += match world.is_shadowed(
                     world_point,
-                    self.point_on_light(u, v, jitter_by)
+                    self.point_on_light(u, v, jitter_by),
+                    time
                 ) {
                     true => 0.0,
                     false => 1.0,
@@ -81,14 +82,14 @@ impl LightSource for AreaLight {
         self.intensity
     }
 
-    fn intensity_at(&self, world_point: DVec3, world: &crate::World) -> f64 {
+    fn intensity_at(&self, world_point: DVec3, time: f64, world: &crate::World) -> f64 {
         let mut rng = rand::thread_rng();
         let mut random_values: Vec<f64> = Vec::with_capacity(self.samples);
         for _ in 0..self.samples {
             random_values.push(rng.gen());
         }
         let mut jitter_by = Sequence::new(random_values);
-        self.intensity_at_impl(world_point, world, &mut jitter_by)
+        self.intensity_at_impl(world_point, time, world, &mut jitter_by)
     }
 }
 
@@ -169,7 +170,7 @@ mod tests {
         let mut jitter_by = Sequence::new(vec![0.5]);
 
         for data in point_result {
-            assert_eq!(light.intensity_at_impl(data.0, &w, &mut jitter_by), data.1);
+            assert_eq!(light.intensity_at_impl(data.0, 0.0, &w, &mut jitter_by), data.1);
         }
     }
 
@@ -219,10 +220,9 @@ mod tests {
             (dvec3(0.0, 0.0, -2.0), 1.0),
         ];
 
-        let mut jitter_by = Sequence::new(vec![0.7, 0.3, 0.9, 0.1, 0.5]);
-
         for data in point_result {
-            assert_eq!(light.intensity_at_impl(data.0, &w, &mut jitter_by), data.1);
+            let mut jitter_by = Sequence::new(vec![0.7, 0.3, 0.9, 0.1, 0.5]);
+            assert_eq!(light.intensity_at_impl(data.0, 0.0, &w, &mut jitter_by), data.1);
         }
     }
 }
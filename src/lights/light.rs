@@ -13,7 +13,7 @@ pub enum Light {
 pub trait LightSource {
     fn positions(&self) -> &[DVec3];
     fn intensity(&self) -> Color;
-    fn intensity_at(&self, world_point: DVec3, world: &World) -> f64;
+    fn intensity_at(&self, world_point: DVec3, time: f64, world: &World) -> f64;
 }
 
 impl LightSource for Light {
@@ -31,10 +31,10 @@ impl LightSource for Light {
         }
     }
 
-    fn intensity_at(&self, world_point: DVec3, world: &World) -> f64 {
+    fn intensity_at(&self, world_point: DVec3, time: f64, world: &World) -> f64 {
         match self {
-            Light::PointLight(l) => l.intensity_at(world_point, world),
-            Light::AreaLight(l) => l.intensity_at(world_point, world),
+            Light::PointLight(l) => l.intensity_at(world_point, time, world),
+            Light::AreaLight(l) => l.intensity_at(world_point, time, world),
         }
     }
 }
\ No newline at end of file
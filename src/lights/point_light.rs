@@ -30,8 +30,8 @@ impl LightSource for PointLight {
         self.intensity
     }
 
-    fn intensity_at(&self, world_point: DVec3, world: &World) -> f64 {
-        match world.is_shadowed(world_point, self.position[0]) {
+    fn intensity_at(&self, world_point: DVec3, time: f64, world: &World) -> f64 {
+        match world.is_shadowed(world_point, self.position[0], time) {
             true => 0.0,
             false => 1.0,
         } 
@@ -67,7 +67,7 @@ mod tests {
             (dvec3(0.0, 0.0, 0.0), 0.0),
         ];
         for data in datas {
-            assert_eq!(light.intensity_at(data.0, &w), data.1);
+            assert_eq!(light.intensity_at(data.0, 0.0, &w), data.1);
         }
     }   
 }
\ No newline at end of file
@@ -1,115 +1,232 @@
-use std::{path::Path, fmt::Debug};
+use std::{path::{Path, PathBuf}, fmt};
 
-use glam::dvec3;
+use glam::{DVec2, DVec3, dvec2, dvec3};
 
-use crate::{shapes::{Triangle, Shape, SmoothTriangle, Mesh}, Object};
+use crate::{shapes::{Shape, Mesh, Face}, Object};
+
+/// the obj file could not be read or parsed
+#[derive(Debug)]
+pub struct ObjError {
+    pub path: PathBuf,
+    pub error: tobj::LoadError,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unable to load {}: {}", self.path.display(), self.error)
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// what was found in the loaded file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ObjSummary {
+    pub models: usize,
+    /// models without any triangle
+    pub models_skipped: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+}
 
 pub struct ObjLoader {
     objects: Vec<Object>,
+    summary: ObjSummary,
 }
 
 impl ObjLoader {
-    pub fn load_from_path<P: AsRef<Path> + Debug>(path: P) -> Self {
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Self, ObjError> {
+        Self::load_from_path_with_logger(path, |_| {})
+    }
+
+    /// the logger receives a message for each model loaded or skipped
+    pub fn load_from_path_with_logger<P: AsRef<Path>, L: FnMut(&str)>(path: P, mut logger: L) -> Result<Self, ObjError> {
         let (models, _) =
             tobj::load_obj(
-                &path,
+                path.as_ref(),
                 &tobj::LoadOptions {
                     triangulate: true,
                     ..Default::default()
                 }
             )
-            .expect("Failed to OBJ load file");
-
-        println!("Number of models = {}", models.len());
+            .map_err(|error| ObjError {
+                path: path.as_ref().to_path_buf(),
+                error,
+            })?;
 
         let mut objects = Vec::default();
+        let mut summary = ObjSummary {
+            models: models.len(),
+            ..Default::default()
+        };
 
         for (i, m) in models.iter().enumerate() {
             let mesh = &m.mesh;
-            println!("");
-            println!("model[{}].name = \'{}\'", i, m.name);
-
-            let mut triangles = Vec::default();
-
-            let has_normals = !mesh.normals.is_empty();
-
-            for index in (0..mesh.indices.len()).step_by(3) {
-                let vertex_index = mesh.indices[index] as usize;
-                let p1 = dvec3(
-                    mesh.positions[3 * vertex_index] as f64,
-                    mesh.positions[3 * vertex_index + 1] as f64,
-                    mesh.positions[3 * vertex_index + 2] as f64
-                );
-                let vertex_index = mesh.indices[index + 1] as usize;
-                let p2 = dvec3(
-                    mesh.positions[3 * vertex_index] as f64,
-                    mesh.positions[3 * vertex_index + 1] as f64,
-                    mesh.positions[3 * vertex_index + 2] as f64
-                );
-                let vertex_index = mesh.indices[index + 2] as usize;
-                let p3 = dvec3(
-                    mesh.positions[3 * vertex_index] as f64,
-                    mesh.positions[3 * vertex_index + 1] as f64,
-                    mesh.positions[3 * vertex_index + 2] as f64
-                );
-                
-                match has_normals {
-                    true => {
-                        let vertex_index = mesh.indices[index] as usize;
-                        let n1 = dvec3(
-                            mesh.normals[3 * vertex_index] as f64,
-                            mesh.normals[3 * vertex_index + 1] as f64,
-                            mesh.normals[3 * vertex_index + 2] as f64
-                        );
-                        let vertex_index = mesh.indices[index + 1] as usize;
-                        let n2 = dvec3(
-                            mesh.normals[3 * vertex_index] as f64,
-                            mesh.normals[3 * vertex_index + 1] as f64,
-                            mesh.normals[3 * vertex_index + 2] as f64
-                        );
-                        let vertex_index = mesh.indices[index + 2] as usize;
-                        let n3 = dvec3(
-                            mesh.normals[3 * vertex_index] as f64,
-                            mesh.normals[3 * vertex_index + 1] as f64,
-                            mesh.normals[3 * vertex_index + 2] as f64
-                        );
-
-                        triangles.push(
-                            Object::new(
-                                Shape::SmoothTriangle(
-                                    SmoothTriangle::new(p1, p2, p3, n1, n2, n3)
-                                )
-                            )
-                        );
-                    },
-                    false => {
-                        triangles.push(
-                            Object::new(
-                                Shape::Triangle(
-                                    Triangle::new(p1, p2, p3)
-                                )
-                            )
-                        );
-                    },
-                }                
+            if mesh.indices.len() < 3 {
+                logger(&format!("model[{}] '{}' skipped, it has no triangle", i, m.name));
+                summary.models_skipped += 1;
+                continue;
             }
 
+            let positions = Self::dvec3s(&mesh.positions);
+            let normals = Self::dvec3s(&mesh.normals);
+            let uvs: Vec<DVec2> = mesh.texcoords
+                .chunks_exact(2)
+                .map(|uv| dvec2(uv[0] as f64, uv[1] as f64))
+                .collect();
+
+            // normals and uvs have their own indices unless they match the positions
+            let indices = |own_indices: &[u32], values: usize| match (values, own_indices.is_empty()) {
+                (0, _) => None,
+                (_, true) => Some(mesh.indices.clone()),
+                (_, false) => Some(own_indices.to_vec()),
+            };
+            let normal_indices = indices(&mesh.normal_indices, normals.len());
+            let uv_indices = indices(&mesh.texcoord_indices, uvs.len());
+
+            let corners = |indices: &[u32], face: usize| [
+                indices[3 * face] as usize,
+                indices[3 * face + 1] as usize,
+                indices[3 * face + 2] as usize,
+            ];
+            let faces: Vec<Face> = (0..mesh.indices.len() / 3)
+                .map(|face| {
+                    let mut new_face = Face::new(corners(&mesh.indices, face));
+                    if let Some(normal_indices) = &normal_indices {
+                        new_face = new_face.with_normals(corners(normal_indices, face));
+                    }
+                    if let Some(uv_indices) = &uv_indices {
+                        new_face = new_face.with_uvs(corners(uv_indices, face));
+                    }
+                    new_face
+                })
+                .collect();
+
+            let vertices = positions.len();
+            logger(&format!("model[{}] '{}' loaded, {} vertices, {} triangles", i, m.name, vertices, faces.len()));
+            summary.vertices += vertices;
+            summary.triangles += faces.len();
+
             objects.push(
                 Object::new(
                     Shape::Mesh(
-                        Mesh::default()
-                        .with_triangles(triangles)
+                        Mesh::new(positions, faces)
+                        .with_normals(normals)
+                        .with_uvs(uvs)
                     )
                 )
             );
         }
 
-        Self {
+        Ok(Self {
             objects,
-        }
+            summary,
+        })
+    }
+
+    fn dvec3s(values: &[f32]) -> Vec<DVec3> {
+        values
+            .chunks_exact(3)
+            .map(|v| dvec3(v[0] as f64, v[1] as f64, v[2] as f64))
+            .collect()
+    }
+
+    pub fn summary(&self) -> &ObjSummary {
+        &self.summary
     }
 
     pub fn objects(self) -> Vec<Object> {
         self.objects
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::Triangle;
+
+    use super::*;
+
+    /// the process id keeps simultaneous test runs from sharing the file
+    fn write_obj(name: &str, source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ray_tracer_{}_{}.obj", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn loading_an_obj_file_reports_a_summary() {
+        let path = write_obj("summary_test", "
+v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+o quad
+f 1 2 3 4
+o point
+p 1
+");
+        let mut messages = Vec::new();
+        let loader = ObjLoader::load_from_path_with_logger(&path, |message| messages.push(message.to_string())).unwrap();
+
+        assert_eq!(
+            *loader.summary(),
+            ObjSummary {
+                models: 2,
+                models_skipped: 1,
+                vertices: 4,
+                triangles: 2,
+            }
+        );
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("quad"));
+        assert!(messages[1].contains("skipped"));
+
+        let objects = loader.objects();
+        assert_eq!(objects.len(), 1);
+        let mesh = match objects[0].shape() {
+            Shape::Mesh(mesh) => mesh,
+            _ => unreachable!(),
+        };
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(
+            mesh.triangle(1),
+            Triangle::new(dvec3(-1.0, 1.0, 0.0), dvec3(1.0, 0.0, 0.0), dvec3(1.0, 1.0, 0.0))
+        );
+        assert_eq!(mesh.faces()[1].normals(), None);
+    }
+
+    #[test]
+    fn loading_an_obj_file_with_normals_and_uvs() {
+        let path = write_obj("normals_test", "
+v 0 1 0
+v -1 0 0
+v 1 0 0
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+vt 0 0
+vt 1 1
+f 1/2/3 2/1/1 3/1/2
+");
+        let objects = ObjLoader::load_from_path(path).unwrap().objects();
+        let mesh = match objects[0].shape() {
+            Shape::Mesh(mesh) => mesh,
+            _ => unreachable!(),
+        };
+        assert_eq!(mesh.normal_at_face(0, 0.0, 0.0), dvec3(0.0, 1.0, 0.0));
+        assert_eq!(mesh.normal_at_face(0, 1.0, 0.0), dvec3(-1.0, 0.0, 0.0));
+        assert_eq!(mesh.uv_at_face(0, 0.0, 0.0), Some(dvec2(1.0, 1.0)));
+        assert_eq!(mesh.uv_at_face(0, 0.0, 1.0), Some(dvec2(0.0, 0.0)));
+    }
+
+    #[test]
+    fn loading_a_missing_obj_file_fails() {
+        let error = ObjLoader::load_from_path("examples/obj/missing.obj").err().unwrap();
+        assert_eq!(error.path, PathBuf::from("examples/obj/missing.obj"));
+        assert_eq!(error.error, tobj::LoadError::OpenFileFailed);
+    }
 }
\ No newline at end of file
@@ -1,10 +1,13 @@
+use std::borrow::Cow;
+
 use glam::{DMat4, DVec3};
 
 use crate::{
     shapes::shape::{Shape, Hittable}, 
     ray::Ray, 
-    intersection::Intersections, 
-    material::Material, transformations::{Transform, TransformBuilder, Transformable}, bounds::BoundingBox
+    intersection::{Intersections, Intersection, Placement}, 
+    material::Material, transformations::{Transform, TransformBuilder, Transformable, Motion}, bounds::BoundingBox,
+    bvh::{BvhBuilder, BvhStats, SahBuilder}, shapes::{Group, LinearBvh}
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,8 +17,13 @@ pub struct Object {
     transform: Transform,
     shadow: bool,
     bounding_box: BoundingBox,
+    /// applied after the transform, depending on the time of the ray
+    motion: Option<Motion>,
 }
 
+/// number of instants sampled between two keyframes to bound a moving object
+const MOTION_BOUNDS_STEPS: usize = 8;
+
 impl Object {
     pub fn new(shape: Shape) -> Self {
         let bounds = shape.bounds();        
@@ -25,22 +33,19 @@ impl Object {
             transform: Transform::default(),
             shadow: true,
             bounding_box: bounds,
+            motion: None,
         }
     }
 
     pub fn with_material(mut self, material: Material) -> Self {
-        self.material = material.clone();
-        self.shape = match self.shape {
-            Shape::Mesh(mut m) => {
-                let mut new_triangles = Vec::with_capacity(m.triangles().len());
-                for triangle in m.triangles().iter() {
-                    new_triangles.push(triangle.clone().with_material(material.clone()));
-                }
-                m = m.with_triangles(new_triangles);
-                Shape::Mesh(m)
-            }
-            _ => { self.shape }
-        };
+        self.material = material;
+        self
+    }
+
+    /// like with_material, but groups and csgs also hand the material down
+    /// to their children
+    pub fn with_propagated_material(mut self, material: Material) -> Self {
+        self.set_propagated_material(&material);
         self
     }
 
@@ -49,9 +54,22 @@ impl Object {
         self
     }
 
+    /// like with_shadow, but groups and csgs also hand the flag down to their
+    /// children, which are the ones tested by the shadow rays
+    pub fn with_propagated_shadow(mut self, shadow: bool) -> Self {
+        self.set_propagated_shadow(shadow);
+        self
+    }
+
     pub fn with_transform(mut self, transform: Transform) -> Self {
-        self.bounding_box = self.shape.bounds().transform(&transform.matrix);
-        self.transform = transform;
+        self.set_transform(transform);
+        self
+    }
+
+    /// moves the object over time, groups, meshes and csgs hand the motion
+    /// down to their children
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.set_motion(motion);
         self
     }
 
@@ -95,11 +113,32 @@ impl Object {
         .with_rotation_z(angle)
     }
 
-    pub fn divide(mut self, threshold: usize) -> Self{
-        self.shape.divide(threshold);
+    /// a threshold divides at the midpoint, a SahBuilder with the surface area heuristic
+    pub fn divide(mut self, builder: impl Into<BvhBuilder>) -> Self {
+        self.shape.divide(builder);
         self
     }
 
+    /// turns a group into a linear bvh of its shapes,
+    /// a mesh sorts its faces in its own hierarchy
+    pub fn flatten(mut self, builder: &SahBuilder) -> Self {
+        if let Shape::Mesh(m) = &mut self.shape {
+            m.divide(builder.clone());
+        }
+        if let Shape::Group(_) = self.shape {
+            let transform = std::mem::replace(&mut self.transform, Transform::identity());
+            let shape = std::mem::replace(&mut self.shape, Shape::Group(Group::default()));
+            let flat = Object::new(shape).with_transform(transform);
+            self.shape = Shape::Bvh(LinearBvh::new(vec![flat], builder));
+            self.update_bounds();
+        }
+        self
+    }
+
+    pub fn bvh_stats(&self) -> BvhStats {
+        BvhStats::new(self)
+    }
+
     pub fn shape(&self) -> &Shape {
         &self.shape
     }
@@ -124,6 +163,31 @@ impl Object {
         &self.transform
     }
 
+    pub fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    /// the transform of the object at the given time
+    pub fn transform_at(&self, time: f64) -> Cow<'_, Transform> {
+        match &self.motion {
+            Some(motion) => Cow::Owned(self.transform.clone().apply(motion.transform_at(time))),
+            None => Cow::Borrowed(&self.transform),
+        }
+    }
+
+    /// the object standing still where it is at the given time
+    pub fn at_time(&self, time: f64) -> Cow<'_, Object> {
+        match self.motion {
+            Some(_) => {
+                let mut object = self.clone();
+                object.motion = None;
+                object.set_transform(self.transform_at(time).into_owned());
+                Cow::Owned(object)
+            }
+            None => Cow::Borrowed(self),
+        }
+    }
+
     pub fn inverse_transform(&self) -> &DMat4 {
         &self.transform.inverse_matrix
     }
@@ -132,9 +196,42 @@ impl Object {
         &self.bounding_box
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Intersections {
-        let local_ray = ray.transform(&self.transform.inverse_matrix);
-        self.shape.intersect(&local_ray, &self)
+    /// true if the hit is on this object or one of its descendants, instances
+    /// sharing an object are told apart by the placement of the hit
+    pub fn includes(&self, hit: &Intersection) -> bool {
+        match &self.shape {
+            Shape::Group(g) => g.objects().iter().any(|o| o.includes(hit)),
+            Shape::Bvh(b) => b.primitives().iter().any(|o| o.includes(hit)),
+            Shape::Csg(c) => c.left().includes(hit) || c.right().includes(hit),
+            Shape::Instance(_) => hit.placement().is_some_and(|p| std::ptr::eq(p.instance(), self)),
+            _ => std::ptr::eq(self, hit.object()),
+        }
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        if self.motion.is_none() && self.transform.matrix == DMat4::IDENTITY {
+            return self.shape.intersect(ray, self);
+        }
+        let local_ray = ray.transform(&self.transform_at(ray.time).inverse_matrix);
+        self.shape.intersect(&local_ray, self)
+    }
+
+    /// nearest hit within the range of the ray
+    pub fn closest_hit(&self, ray: &Ray) -> Option<Intersection<'_>> {
+        if self.motion.is_none() && self.transform.matrix == DMat4::IDENTITY {
+            return self.shape.closest_hit(ray, self);
+        }
+        let local_ray = ray.transform(&self.transform_at(ray.time).inverse_matrix);
+        self.shape.closest_hit(&local_ray, self)
+    }
+
+    /// true if something casting a shadow is hit by the ray before t_max
+    pub fn occluded(&self, ray: &Ray, t_max: f64) -> bool {
+        if self.motion.is_none() && self.transform.matrix == DMat4::IDENTITY {
+            return self.shape.occluded(ray, t_max, self);
+        }
+        let local_ray = ray.transform(&self.transform_at(ray.time).inverse_matrix);
+        self.shape.occluded(&local_ray, t_max, self)
     }
 
     pub fn normal_at(&self, world_point: DVec3, u: f64, v: f64) -> DVec3 {
@@ -142,6 +239,29 @@ impl Object {
         self.normal_to_world(object_normal)
     }
 
+    /// meshes need to know which of their faces was hit
+    pub fn normal_at_intersection(&self, world_point: DVec3, intersection: &Intersection) -> DVec3 {
+        match &self.shape {
+            Shape::Mesh(m) => self.normal_to_world(
+                m.normal_at_face(intersection.face(), intersection.u(), intersection.v())
+            ),
+            _ => self.normal_at(world_point, intersection.u(), intersection.v()),
+        }
+    }
+
+    /// the object as seen through the instances sharing it, its geometry is
+    /// left out as shading only needs its transform and material
+    pub(crate) fn placed(&self, time: f64, placement: &Placement) -> Object {
+        Object {
+            shape: Shape::Group(Group::new()),
+            material: placement.material().unwrap_or(&self.material).clone(),
+            transform: self.transform_at(time).into_owned().apply(placement.transform()),
+            shadow: self.shadow,
+            bounding_box: BoundingBox::default(),
+            motion: None,
+        }
+    }
+
     pub fn world_to_object(&self, world_point: DVec3) -> DVec3 {
         self.transform.inverse_matrix.transform_point3(world_point)
     }
@@ -151,8 +271,123 @@ impl Object {
     }
 
     fn set_transform(&mut self, transform: Transform) {
-        self.bounding_box = self.shape().bounds().transform(&transform.matrix);
         self.transform = transform;
+        self.update_bounds();
+    }
+
+    fn set_propagated_material(&mut self, material: &Material) {
+        match &mut self.shape {
+            Shape::Group(g) => {
+                for object in g.objects_mut() {
+                    object.set_propagated_material(material);
+                }
+            },
+            Shape::Bvh(b) => {
+                for object in b.primitives_mut() {
+                    object.set_propagated_material(material);
+                }
+            },
+            Shape::Csg(c) => {
+                c.left_mut().set_propagated_material(material);
+                c.right_mut().set_propagated_material(material);
+            },
+            Shape::Instance(i) => i.set_material(material.clone()),
+            _other => {}
+        };
+        self.material = material.clone();
+    }
+
+    fn set_propagated_shadow(&mut self, shadow: bool) {
+        match &mut self.shape {
+            Shape::Group(g) => {
+                for object in g.objects_mut() {
+                    object.set_propagated_shadow(shadow);
+                }
+            },
+            Shape::Bvh(b) => {
+                for object in b.primitives_mut() {
+                    object.set_propagated_shadow(shadow);
+                }
+            },
+            Shape::Csg(c) => {
+                c.left_mut().set_propagated_shadow(shadow);
+                c.right_mut().set_propagated_shadow(shadow);
+            },
+            _other => {}
+        };
+        self.shadow = shadow;
+    }
+
+    fn set_motion(&mut self, motion: Motion) {
+        match &mut self.shape {
+            Shape::Group(g) => {
+                for object in g.objects_mut() {
+                    object.set_motion(motion.clone());
+                }
+            },
+            Shape::Bvh(b) => {
+                for object in b.primitives_mut() {
+                    object.set_motion(motion.clone());
+                }
+                b.refit();
+            },
+            Shape::Csg(c) => {
+                c.left_mut().set_motion(motion.clone());
+                c.right_mut().set_motion(motion);
+            },
+            _other => {
+                self.motion = Some(match self.motion.take() {
+                    Some(current) => current.then(&motion),
+                    None => motion,
+                });
+            }
+        };
+        self.update_bounds();
+    }
+
+    /// a moving object is bounded by the volume it sweeps
+    fn update_bounds(&mut self) {
+        let bounds = self.shape.bounds().transform(&self.transform.matrix);
+        self.bounding_box = match &self.motion {
+            Some(motion) => Self::swept_bounds(&bounds, motion),
+            None => bounds,
+        };
+    }
+
+    /// the boxes at the sampled instants, grown by how far a rotating object
+    /// strays from them in between, before the transform applied after the motion
+    fn swept_bounds(bounds: &BoundingBox, motion: &Motion) -> BoundingBox {
+        let locals: Vec<Transform> = motion
+            .sample_times(MOTION_BOUNDS_STEPS)
+            .into_iter()
+            .map(|time| motion.local_transform_at(time))
+            .collect();
+        let swept = locals
+            .iter()
+            .fold(BoundingBox::default(), |swept, local| swept.merge(&bounds.transform(&local.matrix)));
+
+        // a point at distance d of the origin turning by an angle a moves at
+        // most 2 d sin(a / 2) away from where it would be without turning,
+        // once from the start of the step and once from its end
+        let radius = bounds.min().abs().max(bounds.max().abs()).length();
+        let stray = locals
+            .windows(2)
+            .map(|step| {
+                let (s0, r0, _) = step[0].matrix.to_scale_rotation_translation();
+                let (s1, r1, _) = step[1].matrix.to_scale_rotation_translation();
+                match r0.angle_between(r1) {
+                    angle if angle > 0.0 => {
+                        4.0 * (angle / 2.0).sin() * s0.abs().max(s1.abs()).max_element() * radius
+                    }
+                    _ => 0.0,
+                }
+            })
+            .fold(0.0, f64::max);
+        let swept = match stray > 0.0 {
+            true => BoundingBox::new(swept.min() - DVec3::splat(stray), swept.max() + DVec3::splat(stray)),
+            false => swept,
+        };
+        swept.transform(&motion.outer().matrix)
     }
 }
 
@@ -165,15 +400,30 @@ impl Transformable for Object {
                 }
                 self.set_transform(Transform::identity());
             },
-            Shape::Mesh(m) => {
-                for object in m.triangles_mut() {
+            Shape::Bvh(b) => {
+                for object in b.primitives_mut() {
                     object.apply_transform(transform.clone());
                 }
+                b.refit();
+                self.set_transform(Transform::identity());
+            },
+            Shape::Csg(c) => {
+                c.left_mut().apply_transform(transform.clone());
+                c.right_mut().apply_transform(transform);
                 self.set_transform(Transform::identity());
             },
             _other => {
-                let new_transform = self.transform.clone().apply(transform);
-                self.set_transform(new_transform);
+                match self.motion.take() {
+                    Some(motion) => {
+                        // the transform is applied after the motion
+                        self.motion = Some(motion.apply(&transform));
+                        self.update_bounds();
+                    }
+                    None => {
+                        let new_transform = self.transform.clone().apply(transform);
+                        self.set_transform(new_transform);
+                    }
+                }
             }
         };
     }
@@ -187,7 +437,7 @@ mod tests {
 
     use glam::dvec3;
 
-    use crate::shapes::{sphere::Sphere, Group};
+    use crate::shapes::{sphere::Sphere, Group, Cube};
     use super::*;
 
     #[test]
@@ -334,4 +584,133 @@ mod tests {
 
         assert!(retrieved_s.normal_at(dvec3(1.7321, 1.1547, -5.5774), 0.0, 0.0).abs_diff_eq(dvec3(0.2857, 0.4286, -0.8571), EPSILON));
     }
+
+    #[test]
+    fn intersecting_a_moving_object_depends_on_the_time_of_the_ray() {
+        let o = Object::new(Shape::Sphere(Sphere::default()))
+            .with_motion(Motion::new(
+                Transform::new(),
+                Transform::new().with_translation(0.0, 4.0, 0.0)
+            ));
+        let r = Ray::new(dvec3(0.0, 2.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(o.intersect(&r).count(), 0);
+
+        let xs = o.intersect(&r.with_time(0.5));
+        assert_eq!(xs.count(), 2);
+        assert!((xs[0].t() - 4.0).abs() < EPSILON);
+        assert!((xs[1].t() - 6.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn the_motion_is_applied_after_the_transform() {
+        let o = Object::new(Shape::Sphere(Sphere::default()))
+            .with_scale(2.0, 2.0, 2.0)
+            .transform()
+            .with_motion(Motion::new(
+                Transform::new(),
+                Transform::new().with_translation(10.0, 0.0, 0.0)
+            ));
+        assert_eq!(o.transform_at(1.0).matrix.transform_point3(dvec3(1.0, 0.0, 0.0)), dvec3(12.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn a_moving_object_is_bounded_by_its_swept_volume() {
+        let o = Object::new(Shape::Sphere(Sphere::default()))
+            .with_motion(Motion::new(
+                Transform::new(),
+                Transform::new().with_translation(5.0, 0.0, 0.0)
+            ))
+            .with_translation(0.0, 1.0, 0.0)
+            .transform();
+        let bb = o.bounding_box();
+        assert!(bb.min().abs_diff_eq(dvec3(-1.0, 0.0, -1.0), EPSILON));
+        assert!(bb.max().abs_diff_eq(dvec3(6.0, 2.0, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn a_turning_object_stays_in_its_swept_volume() {
+        let o = Object::new(Shape::Cube(Cube::default()))
+            .with_scale(4.0, 0.1, 0.1)
+            .transform()
+            .with_motion(Motion::from_keyframes(vec![
+                (0.0, Transform::new()),
+                (0.5, Transform::new().with_rotation_z(PI / 2.0)),
+                (1.0, Transform::new().with_rotation_z(PI)),
+            ]));
+        let bounds = o.shape().bounds();
+        for step in 0..=1000 {
+            let at_time = bounds.transform(&o.transform_at(step as f64 / 1000.0).matrix);
+            assert!(o.bounding_box().contains_box(&at_time));
+        }
+    }
+
+    #[test]
+    fn a_group_hands_its_motion_down_to_its_children() {
+        let s = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(2.0, 0.0, 0.0)
+            .transform();
+        let g = Object::new(Shape::Group(Group::new().with_objects(vec![s])))
+            .with_motion(Motion::new(
+                Transform::new(),
+                Transform::new().with_translation(0.0, 0.0, 3.0)
+            ));
+        assert!(g.motion().is_none());
+        let child = &g.shape().as_group().unwrap().objects()[0];
+        assert!(child.motion().is_some());
+        assert!(g.bounding_box().min().abs_diff_eq(dvec3(1.0, -1.0, -1.0), EPSILON));
+        assert!(g.bounding_box().max().abs_diff_eq(dvec3(3.0, 1.0, 4.0), EPSILON));
+
+        let r = Ray::new(dvec3(2.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0)).with_time(1.0);
+        let xs = g.intersect(&r);
+        assert_eq!(xs.count(), 2);
+        assert!((xs[0].t() - 7.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn a_moving_child_of_a_stretched_group_turns_inside_the_group() {
+        let c = Object::new(Shape::Cube(Cube::default()))
+            .with_motion(Motion::new(
+                Transform::new(),
+                Transform::new().with_rotation_z(PI / 2.0)
+            ));
+        let g = Object::new(Shape::Group(Group::new().with_objects(vec![c])))
+            .with_scale(2.0, 1.0, 1.0)
+            .transform();
+        let child = &g.shape().as_group().unwrap().objects()[0];
+        let p = child.transform_at(0.5).matrix.transform_point3(dvec3(1.0, 0.0, 0.0));
+        assert!(p.abs_diff_eq(dvec3(f64::sqrt(2.0), f64::sqrt(2.0) / 2.0, 0.0), EPSILON));
+        let p = child.transform_at(1.0).matrix.transform_point3(dvec3(1.0, 0.0, 0.0));
+        assert!(p.abs_diff_eq(dvec3(0.0, 1.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn a_moving_object_stands_still_at_a_given_time() {
+        let o = Object::new(Shape::Sphere(Sphere::default()))
+            .with_motion(Motion::new(
+                Transform::new(),
+                Transform::new().with_translation(4.0, 0.0, 0.0)
+            ));
+        let still = o.at_time(0.5);
+        assert!(still.motion().is_none());
+        assert!(still.normal_at(dvec3(3.0, 0.0, 0.0), 0.0, 0.0).abs_diff_eq(dvec3(1.0, 0.0, 0.0), EPSILON));
+
+        let s = Object::new(Shape::Sphere(Sphere::default()));
+        assert!(std::ptr::eq(s.at_time(0.5).as_ref(), &s));
+    }
+
+    #[test]
+    fn a_group_can_hand_its_material_down_to_its_children() {
+        let material = Material::default().with_ambient(0.5);
+        let s = Object::new(Shape::Sphere(Sphere::default()));
+        let g = Object::new(Shape::Group(Group::new().with_objects(vec![s.clone()])));
+
+        let kept = g.clone().with_material(material.clone());
+        assert_eq!(*kept.shape().as_group().unwrap().objects()[0].material(), Material::default());
+
+        let propagated = Object::new(Shape::Group(Group::new().with_objects(vec![g])))
+            .with_propagated_material(material.clone());
+        let child = &propagated.shape().as_group().unwrap().objects()[0];
+        assert_eq!(*child.material(), material);
+        assert_eq!(*child.shape().as_group().unwrap().objects()[0].material(), material);
+    }
 }
\ No newline at end of file
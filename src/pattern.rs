@@ -1,6 +1,6 @@
 use glam::{DVec3, DMat4};
 
-use crate::{Color, Object, transformations::{Transform, TransformBuilder, Transformable}};
+use crate::{Color, Object, transformations::{Transform, TransformBuilder, Transformable}, texture::{TextureMapPattern, CubeMapPattern}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PatternObject {
@@ -98,6 +98,8 @@ pub enum Pattern {
     Gradient(GradientPattern),
     Ring(RingPattern),
     Checker(CheckerPattern),
+    TextureMap(TextureMapPattern),
+    CubeMap(CubeMapPattern),
     Test(TestPattern),
 }
 
@@ -113,6 +115,8 @@ impl PatternFunc for Pattern {
             Pattern::Gradient(p) => p.color_at(point),
             Pattern::Ring(p) => p.color_at(point),
             Pattern::Checker(p) => p.color_at(point),
+            Pattern::TextureMap(p) => p.color_at(point),
+            Pattern::CubeMap(p) => p.color_at(point),
             Pattern::Test(p) => p.color_at(point),
         }
     }
@@ -242,6 +246,12 @@ impl PatternFunc for CheckerPattern {
 #[derive(Debug, Clone, PartialEq)]
 pub struct TestPattern {}
 
+impl Default for TestPattern {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TestPattern {
     pub fn new() -> Self {
         Self {}
@@ -0,0 +1,65 @@
+use std::{sync::{Arc, atomic::{AtomicBool, Ordering}}, time::Duration};
+
+use crate::{tile::Tile, Canvas};
+
+/// follows a rendering, the tiles are completed from several threads
+pub trait RenderObserver: Sync {
+    fn render_started(&self) {}
+
+    /// percentage of the work done once the tile is completed
+    fn tile_completed(&self, _tile: Tile, _percentage: f64) {}
+
+    /// image after each pass of a progressive rendering, passes count from 1
+    fn pass_completed(&self, _pass: usize, _canvas: &Canvas) {}
+
+    fn render_finished(&self, _elapsed: Duration) {}
+}
+
+/// prints when a rendering starts and how long it took
+pub struct ConsoleObserver;
+
+impl RenderObserver for ConsoleObserver {
+    fn render_started(&self) {
+        println!("Start rendering");
+    }
+
+    fn render_finished(&self, elapsed: Duration) {
+        println!("Rendering finished in {:.2?} seconds", elapsed);
+    }
+}
+
+/// silent rendering
+impl RenderObserver for () {}
+
+/// shared between the caller and a rendering, which stops starting new tiles
+/// once it is cancelled
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelling_a_shared_token() {
+        let token = CancellationToken::new();
+        let shared = token.clone();
+        assert!(!shared.is_cancelled());
+        token.cancel();
+        assert!(shared.is_cancelled());
+    }
+}
\ No newline at end of file
@@ -4,16 +4,43 @@ use glam::{DVec3, DMat4};
 pub struct Ray {
     pub origin: DVec3,
     pub direction: DVec3,
+    /// instant at which the ray is cast, used to place moving objects
+    pub time: f64,
+    /// only hits between t_min included and t_max excluded count as the closest hit
+    pub t_min: f64,
+    pub t_max: f64,
 }
 
 impl Ray {
     pub fn new(origin: DVec3, direction: DVec3) -> Self {
         Self {
             origin,
-            direction
+            direction,
+            time: 0.0,
+            t_min: 0.0,
+            t_max: f64::INFINITY,
         }
     }
 
+    pub fn with_time(mut self, time: f64) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn with_t_min(mut self, t_min: f64) -> Self {
+        self.t_min = t_min;
+        self
+    }
+
+    pub fn with_t_max(mut self, t_max: f64) -> Self {
+        self.t_max = t_max;
+        self
+    }
+
+    pub fn in_range(&self, t: f64) -> bool {
+        t >= self.t_min && t < self.t_max
+    }
+
     pub fn at(&self, t: f64) -> DVec3 {
         self.origin + self.direction * t
     }
@@ -21,7 +48,8 @@ impl Ray {
     pub fn transform(&self, mat: &DMat4) -> Ray {
         Ray {
             origin:  mat.transform_point3(self.origin),
-            direction: mat.transform_vector3(self.direction)
+            direction: mat.transform_vector3(self.direction),
+            ..*self
         }
     }
 }
@@ -40,6 +68,34 @@ mod tests {
         let r = Ray::new(origin, direction);
         assert_eq!(r.origin, origin);
         assert_eq!(r.direction, direction);
+        assert_eq!(r.time, 0.0);
+        assert_eq!(r.t_min, 0.0);
+        assert_eq!(r.t_max, f64::INFINITY);
+    }
+
+    #[test]
+    fn transforming_a_ray_keeps_its_time() {
+        let r = Ray::new(
+            DVec3::new(1.0, 2.0, 3.0), 
+            DVec3::new(0.0, 1.0, 0.0)
+        )
+        .with_time(0.5);
+        let r2 = r.transform(&DMat4::from_translation(DVec3::new(3.0, 4.0, 5.0)));
+        assert_eq!(r2.time, 0.5);
+    }
+
+    #[test]
+    fn transforming_a_ray_keeps_its_range() {
+        let r = Ray::new(
+            DVec3::new(1.0, 2.0, 3.0), 
+            DVec3::new(0.0, 1.0, 0.0)
+        )
+        .with_t_min(1.0)
+        .with_t_max(2.0);
+        let r2 = r.transform(&DMat4::from_scale(DVec3::new(2.0, 3.0, 4.0)));
+        assert_eq!((r2.t_min, r2.t_max), (1.0, 2.0));
+        assert!(r2.in_range(1.0));
+        assert!(!r2.in_range(2.0));
     }
 
     // Computing a point from a distance
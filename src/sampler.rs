@@ -0,0 +1,175 @@
+use glam::{DVec2, dvec2};
+use rand::Rng;
+
+/// strategy placing the samples of a pixel
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sampler {
+    /// centers of the cells of a grid
+    #[default]
+    Regular,
+    /// a random point in each cell of a grid
+    Stratified,
+    Random,
+    /// low discrepancy sequence in bases 2 and 3, shifted randomly for each pixel
+    Halton,
+    /// first two dimensions of the Sobol sequence, scrambled for each pixel
+    Sobol,
+}
+
+impl Sampler {
+    /// positions of the samples in the unit square of the pixel
+    pub fn samples(&self, count: usize, rng: &mut impl Rng) -> Vec<DVec2> {
+        let count = count.max(1);
+        match self {
+            Sampler::Regular => Self::grid(count, |_| dvec2(0.5, 0.5)),
+            Sampler::Stratified => Self::grid(count, |_| dvec2(rng.gen(), rng.gen())),
+            Sampler::Random => (0..count)
+                .map(|_| dvec2(rng.gen(), rng.gen()))
+                .collect(),
+            Sampler::Halton => {
+                let shift = dvec2(rng.gen(), rng.gen());
+                (1..=count)
+                    .map(|i| (dvec2(radical_inverse(i, 2), radical_inverse(i, 3)) + shift).fract())
+                    .collect()
+            }
+            Sampler::Sobol => {
+                let (sx, sy): (u32, u32) = (rng.gen(), rng.gen());
+                (0..count as u32)
+                    .map(|i| dvec2(to_unit(i.reverse_bits() ^ sx), to_unit(sobol_y(i) ^ sy)))
+                    .collect()
+            }
+        }
+    }
+
+    /// the grid is as square as possible, its rows are split in as many cells
+    /// as they hold samples so that the cells always cover the whole pixel
+    fn grid(count: usize, mut jitter: impl FnMut(usize) -> DVec2) -> Vec<DVec2> {
+        let rows = (count as f64).sqrt().round().max(1.0) as usize;
+        let mut samples = Vec::with_capacity(count);
+        for row in 0..rows {
+            let columns = count * (row + 1) / rows - count * row / rows;
+            let cell = dvec2(1.0 / columns as f64, 1.0 / rows as f64);
+            for column in 0..columns {
+                let offset = jitter(samples.len());
+                samples.push((dvec2(column as f64, row as f64) + offset) * cell);
+            }
+        }
+        samples
+    }
+}
+
+/// digits of i in the given base mirrored around the decimal point
+fn radical_inverse(mut i: usize, base: usize) -> f64 {
+    let mut result = 0.0;
+    let mut scale = 1.0 / base as f64;
+    while i > 0 {
+        result += (i % base) as f64 * scale;
+        i /= base;
+        scale /= base as f64;
+    }
+    result
+}
+
+/// second dimension of the Sobol sequence, its direction numbers come from
+/// the polynomial x + 1
+fn sobol_y(mut i: u32) -> u32 {
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= direction;
+        }
+        i >>= 1;
+        direction ^= direction >> 1;
+    }
+    result
+}
+
+fn to_unit(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn cells(samples: &[DVec2], side: usize) -> Vec<usize> {
+        let mut cells: Vec<usize> = samples
+            .iter()
+            .map(|s| (s.y * side as f64) as usize * side + (s.x * side as f64) as usize)
+            .collect();
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn a_single_regular_sample_is_the_center_of_the_pixel() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(Sampler::Regular.samples(1, &mut rng), vec![dvec2(0.5, 0.5)]);
+        assert_eq!(Sampler::Regular.samples(0, &mut rng).len(), 1);
+    }
+
+    #[test]
+    fn regular_samples_are_the_centers_of_a_grid() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = Sampler::Regular.samples(4, &mut rng);
+        assert_eq!(samples, vec![dvec2(0.25, 0.25), dvec2(0.75, 0.25), dvec2(0.25, 0.75), dvec2(0.75, 0.75)]);
+
+        let samples = Sampler::Regular.samples(6, &mut rng);
+        assert_eq!(samples.len(), 6);
+        assert!(samples[5].abs_diff_eq(dvec2(2.5 / 3.0, 0.75), 1e-12));
+    }
+
+    #[test]
+    fn every_row_of_a_grid_is_full() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = Sampler::Regular.samples(3, &mut rng);
+        assert_eq!(samples, vec![dvec2(0.5, 0.25), dvec2(0.25, 0.75), dvec2(0.75, 0.75)]);
+
+        let samples = Sampler::Stratified.samples(3, &mut rng);
+        assert!(samples[0].y < 0.5);
+        assert!(samples[1].x < 0.5 && samples[1].y >= 0.5);
+        assert!(samples[2].x >= 0.5 && samples[2].y >= 0.5);
+    }
+
+    #[test]
+    fn stratified_samples_fall_in_every_cell() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = Sampler::Stratified.samples(16, &mut rng);
+        assert_eq!(cells(&samples, 4), (0..16).collect::<Vec<usize>>());
+        assert_ne!(samples, Sampler::Regular.samples(16, &mut rng));
+    }
+
+    #[test]
+    fn every_sampler_stays_in_the_pixel() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for sampler in [Sampler::Regular, Sampler::Stratified, Sampler::Random, Sampler::Halton, Sampler::Sobol] {
+            let samples = sampler.samples(37, &mut rng);
+            assert_eq!(samples.len(), 37);
+            assert!(samples.iter().all(|s| s.cmpge(DVec2::ZERO).all() && s.cmplt(DVec2::ONE).all()));
+        }
+    }
+
+    #[test]
+    fn the_halton_sequence() {
+        assert_eq!(radical_inverse(1, 2), 0.5);
+        assert_eq!(radical_inverse(3, 2), 0.75);
+        assert_eq!(radical_inverse(4, 2), 0.125);
+        assert!((radical_inverse(5, 3) - 7.0 / 9.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn low_discrepancy_samples_are_stratified() {
+        let mut rng = StdRng::seed_from_u64(1);
+        // 16 sobol points fill each cell of a 4x4 grid once, even once scrambled
+        let samples = Sampler::Sobol.samples(16, &mut rng);
+        assert_eq!(cells(&samples, 4), (0..16).collect::<Vec<usize>>());
+
+        assert_eq!(
+            (0..4).map(|i| to_unit(sobol_y(i))).collect::<Vec<f64>>(),
+            vec![0.0, 0.5, 0.75, 0.25]
+        );
+    }
+}
\ No newline at end of file
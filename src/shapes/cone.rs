@@ -0,0 +1,257 @@
+use std::mem::swap;
+
+use glam::{DVec3, dvec3};
+
+use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox};
+use super::shape::Hittable;
+
+/// double-napped cone centered on the y axis, truncated by min and max
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cone {
+    min: f64,
+    max: f64,
+    closed: bool,
+}
+
+impl Hittable for Cone {
+    fn intersect<'a>(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        let a = ray.direction.x * ray.direction.x
+            - ray.direction.y * ray.direction.y
+            + ray.direction.z * ray.direction.z;
+        let b = 2.0 * ray.origin.x * ray.direction.x
+            - 2.0 * ray.origin.y * ray.direction.y
+            + 2.0 * ray.origin.z * ray.direction.z;
+        let c = ray.origin.x * ray.origin.x
+            - ray.origin.y * ray.origin.y
+            + ray.origin.z * ray.origin.z;
+
+        let mut xs = Vec::default();
+
+        if f64::abs(a) < f64::EPSILON {
+            // the ray is parallel to one of the cone's halves
+            if f64::abs(b) >= f64::EPSILON {
+                let t = -c / (2.0 * b);
+                let y = ray.origin.y + t * ray.direction.y;
+                if self.min < y && y < self.max {
+                    xs.push(Intersection::new(t, object));
+                }
+            }
+        } else {
+            let disc = b*b - 4.0*a*c;
+            if disc < 0.0 {
+                return Intersections::new();
+            }
+
+            let mut t0 = (-b - f64::sqrt(disc)) / (2.0 * a);
+            let mut t1 = (-b + f64::sqrt(disc)) / (2.0 * a);
+
+            if t0 > t1 {
+                swap(&mut t0, &mut t1);
+            }
+
+            let y0 = ray.origin.y + t0 * ray.direction.y;
+            if self.min < y0 && y0 < self.max {
+                xs.push(Intersection::new(t0, object));
+            }
+
+            let y1 = ray.origin.y + t1 * ray.direction.y;
+            if self.min < y1 && y1 < self.max {
+                xs.push(Intersection::new(t1, object));
+            }
+        }
+
+        let mut intersections = Intersections::new()
+        .with_intersections(xs);
+
+        intersections.append(self.intersect_caps(ray, object));
+        intersections
+    }
+
+    fn normal_at(&self, point: DVec3, _: f64, _: f64) -> DVec3 {
+        let dist = point.x * point.x + point.z * point.z;
+        if dist < self.max * self.max && point.y >= self.max - f64::EPSILON {
+            dvec3(0.0, 1.0, 0.0)
+        } else if dist < self.min * self.min && point.y <= self.min + f64::EPSILON {
+            dvec3(0.0, -1.0, 0.0)
+        } else {
+            let y = if point.y > 0.0 { -dist.sqrt() } else { dist.sqrt() };
+            dvec3(point.x, y, point.z)
+        }
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let limit = f64::max(self.min.abs(), self.max.abs());
+        BoundingBox::new(
+            dvec3(-limit, self.min, -limit),
+            dvec3(limit, self.max, limit)
+        )
+    }
+}
+
+impl Default for Cone {
+    fn default() -> Self {
+        Self {
+            min: f64::NEG_INFINITY,
+            max: f64::INFINITY,
+            closed: true,
+        }
+    }
+}
+
+impl Cone {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_min(mut self, min: f64) -> Self {
+        self.min = min;
+        self
+    }
+
+    pub fn with_max(mut self, max: f64) -> Self {
+        self.max = max;
+        self
+    }
+
+    pub fn with_closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
+    // the radius of a cone cap is the absolute value of its y coordinate
+    fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
+        // an unbounded cone has no cap
+        if !radius.is_finite() {
+            return false;
+        }
+
+        let x = ray.origin.x + t * ray.direction.x;
+        let z = ray.origin.z + t * ray.direction.z;
+
+        (x * x + z * z) <= radius * radius
+    }
+
+    fn intersect_caps<'a>(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
+        if !self.closed || f64::abs(ray.direction.y) < f64::EPSILON {
+            return Intersections::new();
+        }
+
+        let mut xs = Vec::default();
+
+        let t = (self.min - ray.origin.y) / ray.direction.y;
+        if Cone::check_cap(ray, t, self.min) {
+            xs.push(
+                Intersection::new(t, object)
+            );
+        }
+
+        let t = (self.max - ray.origin.y) / ray.direction.y;
+        if Cone::check_cap(ray, t, self.max) {
+            xs.push(
+                Intersection::new(t, object)
+            );
+        }
+
+        Intersections::new()
+        .with_intersections(xs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::Shape;
+
+    use super::*;
+
+    const EPSILON: f64 = 0.00001;
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray() {
+        let shape = Object::new(Shape::Cone(Cone::new()));
+        let datas = vec![
+            (dvec3(0.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0), 5.0, 5.0),
+            (dvec3(0.0, 0.0, -5.0), dvec3(1.0, 1.0, 1.0), 8.66025, 8.66025),
+            (dvec3(1.0, 1.0, -5.0), dvec3(-0.5, -1.0, 1.0), 4.55006, 49.44994),
+        ];
+
+        for data in datas {
+            let r = Ray::new(data.0, data.1.normalize());
+            let xs = shape.intersect(&r);
+            assert_eq!(xs.count(), 2);
+            assert!(f64::abs(xs[0].t() - data.2) < EPSILON);
+            assert!(f64::abs(xs[1].t() - data.3) < EPSILON);
+        }
+    }
+
+    #[test]
+    fn intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
+        let shape = Object::new(Shape::Cone(Cone::new()));
+        let r = Ray::new(
+            dvec3(0.0, 0.0, -1.0),
+            dvec3(0.0, 1.0, 1.0).normalize()
+        );
+        let xs = shape.intersect(&r);
+        assert_eq!(xs.count(), 1);
+        assert!(f64::abs(xs[0].t() - 0.35355) < EPSILON);
+    }
+
+    #[test]
+    fn intersecting_a_cone_s_end_caps() {
+        let shape = Object::new(
+            Shape::Cone(
+                Cone::new()
+                .with_min(-0.5)
+                .with_max(0.5)
+                .with_closed(true)
+            )
+        );
+        let datas = vec![
+            (dvec3(0.0, 0.0, -5.0), dvec3(0.0, 1.0, 0.0), 0),
+            (dvec3(0.0, 0.0, -0.25), dvec3(0.0, 1.0, 1.0), 2),
+            (dvec3(0.0, 0.0, -0.25), dvec3(0.0, 1.0, 0.0), 4),
+        ];
+
+        for data in datas {
+            let r = Ray::new(data.0, data.1.normalize());
+            assert_eq!(shape.intersect(&r).count(), data.2);
+        }
+    }
+
+    #[test]
+    fn computing_the_normal_vector_on_a_cone() {
+        let shape = Cone::new();
+        assert_eq!(shape.normal_at(dvec3(0.0, 0.0, 0.0), 0.0, 0.0), dvec3(0.0, 0.0, 0.0));
+        assert_eq!(shape.normal_at(dvec3(1.0, 1.0, 1.0), 0.0, 0.0), dvec3(1.0, -f64::sqrt(2.0), 1.0));
+        assert_eq!(shape.normal_at(dvec3(-1.0, -1.0, 0.0), 0.0, 0.0), dvec3(-1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn an_unbounded_cone_has_a_bounding_box() {
+        let shape = Cone::new();
+        let bb = shape.bounds();
+        assert_eq!(bb.min(), dvec3(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY));
+        assert_eq!(bb.max(), dvec3(f64::INFINITY, f64::INFINITY, f64::INFINITY));
+    }
+
+    #[test]
+    fn a_bounded_cone_has_a_bounding_box() {
+        let shape = Cone::new()
+            .with_min(-5.0)
+            .with_max(3.0);
+        let bb = shape.bounds();
+        assert_eq!(bb.min(), dvec3(-5.0, -5.0, -5.0));
+        assert_eq!(bb.max(), dvec3(5.0, 3.0, 5.0));
+    }
+}
@@ -0,0 +1,378 @@
+use glam::DVec3;
+
+use crate::{ray::Ray, Object, intersection::Intersections, bounds::BoundingBox, bvh::BvhBuilder};
+use super::shape::Hittable;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation {
+    /// lhit: true if the left shape was hit, false if it was the right one
+    /// inl: true if the hit occurs inside the left shape
+    /// inr: true if the hit occurs inside the right shape
+    pub fn intersection_allowed(&self, lhit: bool, inl: bool, inr: bool) -> bool {
+        match self {
+            CsgOperation::Union => (lhit && !inr) || (!lhit && !inl),
+            CsgOperation::Intersection => (lhit && inr) || (!lhit && inl),
+            CsgOperation::Difference => (lhit && !inr) || (!lhit && inl),
+        }
+    }
+}
+
+/// constructive solid geometry combining two objects with a boolean operation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Csg {
+    operation: CsgOperation,
+    left: Box<Object>,
+    right: Box<Object>,
+}
+
+impl Hittable for Csg {
+    fn intersect<'a>(&'a self, ray: &Ray, this: &'a Object) -> Intersections<'a> {
+        if !this.bounding_box().intersects(ray) {
+            return Intersections::new();
+        }
+
+        let mut xs = self.left.intersect(ray);
+        xs.append(self.right.intersect(ray));
+        self.filter_intersections(xs.sort())
+    }
+
+    fn normal_at(&self, _: DVec3, _: f64, _: f64) -> DVec3 {
+        DVec3::default()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+        match self.operation {
+            CsgOperation::Union => left.merge(right),
+            CsgOperation::Intersection => left.overlap(right),
+            CsgOperation::Difference => *left,
+        }
+    }
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Object, right: Object) -> Self {
+        Self {
+            operation,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+
+    pub fn left(&self) -> &Object {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Object {
+        &self.right
+    }
+
+    pub fn left_mut(&mut self) -> &mut Object {
+        &mut self.left
+    }
+
+    pub fn right_mut(&mut self) -> &mut Object {
+        &mut self.right
+    }
+
+    pub fn divide(&mut self, builder: impl Into<BvhBuilder>) {
+        let builder = builder.into();
+        self.left.shape_mut().divide(builder.clone());
+        self.right.shape_mut().divide(builder);
+    }
+
+    /// keeps the intersections that lie on the surface of the combined shape.
+    /// intersections must be sorted
+    pub fn filter_intersections<'a>(&self, intersections: Intersections<'a>) -> Intersections<'a> {
+        let mut inl = false;
+        let mut inr = false;
+
+        let mut result = Intersections::new();
+        for i in intersections.move_all() {
+            let lhit = self.left.includes(&i);
+            if self.operation.intersection_allowed(lhit, inl, inr) {
+                result.push(i);
+            }
+
+            if lhit {
+                inl = !inl;
+            } else {
+                inr = !inr;
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glam::dvec3;
+
+    use crate::{shapes::{Shape, Sphere, Cube, Group, Mesh, Face, Instance}, intersection::Intersection};
+
+    use super::*;
+
+    #[test]
+    fn csg_is_created_with_an_operation_and_two_shapes() {
+        let s1 = Object::new(Shape::Sphere(Sphere::default()));
+        let s2 = Object::new(Shape::Cube(Cube::default()));
+        let c = Csg::new(CsgOperation::Union, s1.clone(), s2.clone());
+        assert_eq!(c.operation(), CsgOperation::Union);
+        assert_eq!(*c.left(), s1);
+        assert_eq!(*c.right(), s2);
+    }
+
+    #[test]
+    fn evaluating_the_rule_for_a_csg_operation() {
+        let datas = vec![
+            (CsgOperation::Union, true, true, true, false),
+            (CsgOperation::Union, true, true, false, true),
+            (CsgOperation::Union, true, false, true, false),
+            (CsgOperation::Union, true, false, false, true),
+            (CsgOperation::Union, false, true, true, false),
+            (CsgOperation::Union, false, true, false, false),
+            (CsgOperation::Union, false, false, true, true),
+            (CsgOperation::Union, false, false, false, true),
+            (CsgOperation::Intersection, true, true, true, true),
+            (CsgOperation::Intersection, true, true, false, false),
+            (CsgOperation::Intersection, true, false, true, true),
+            (CsgOperation::Intersection, true, false, false, false),
+            (CsgOperation::Intersection, false, true, true, true),
+            (CsgOperation::Intersection, false, true, false, true),
+            (CsgOperation::Intersection, false, false, true, false),
+            (CsgOperation::Intersection, false, false, false, false),
+            (CsgOperation::Difference, true, true, true, false),
+            (CsgOperation::Difference, true, true, false, true),
+            (CsgOperation::Difference, true, false, true, false),
+            (CsgOperation::Difference, true, false, false, true),
+            (CsgOperation::Difference, false, true, true, true),
+            (CsgOperation::Difference, false, true, false, true),
+            (CsgOperation::Difference, false, false, true, false),
+            (CsgOperation::Difference, false, false, false, false),
+        ];
+
+        for data in datas {
+            assert_eq!(data.0.intersection_allowed(data.1, data.2, data.3), data.4);
+        }
+    }
+
+    #[test]
+    fn filtering_a_list_of_intersections() {
+        let datas = vec![
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+
+        for data in datas {
+            let c = Csg::new(
+                data.0,
+                Object::new(Shape::Sphere(Sphere::default())),
+                Object::new(Shape::Cube(Cube::default()))
+            );
+            let s1 = c.left();
+            let s2 = c.right();
+            let xs = Intersections::new()
+                .with_intersections(vec![
+                    Intersection::new(1.0, s1),
+                    Intersection::new(2.0, s2),
+                    Intersection::new(3.0, s1),
+                    Intersection::new(4.0, s2),
+                ]);
+            let result = c.filter_intersections(xs);
+            assert_eq!(result.count(), 2);
+            assert_eq!(result[0].t(), data.1 as f64 + 1.0);
+            assert_eq!(result[1].t(), data.2 as f64 + 1.0);
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_csg_object() {
+        let c = Object::new(
+            Shape::Csg(
+                Csg::new(
+                    CsgOperation::Union,
+                    Object::new(Shape::Sphere(Sphere::default())),
+                    Object::new(Shape::Cube(Cube::default()))
+                )
+            )
+        );
+        let r = Ray::new(dvec3(0.0, 2.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(c.intersect(&r).count(), 0);
+    }
+
+    #[test]
+    fn a_ray_hits_a_csg_object() {
+        let s2 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(0.0, 0.0, 0.5)
+            .transform();
+        let c = Object::new(
+            Shape::Csg(
+                Csg::new(
+                    CsgOperation::Union,
+                    Object::new(Shape::Sphere(Sphere::default())),
+                    s2
+                )
+            )
+        );
+        let csg = match c.shape() {
+            Shape::Csg(csg) => csg,
+            _ => unreachable!(),
+        };
+        let r = Ray::new(dvec3(0.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.count(), 2);
+        assert_eq!(xs[0].t(), 4.0);
+        assert!(std::ptr::eq(xs[0].object(), csg.left()));
+        assert_eq!(xs[1].t(), 6.5);
+        assert!(std::ptr::eq(xs[1].object(), csg.right()));
+    }
+
+    #[test]
+    fn a_csg_difference_with_a_group_as_its_left_child() {
+        let g = Object::new(
+            Shape::Group(
+                Group::new()
+                .with_objects(vec![
+                    Object::new(Shape::Cube(Cube::default()))
+                ])
+            )
+        );
+        let hole = Object::new(Shape::Sphere(Sphere::default()))
+            .with_scale(0.5, 0.5, 2.0)
+            .transform();
+        let c = Object::new(
+            Shape::Csg(
+                Csg::new(CsgOperation::Difference, g, hole)
+            )
+        );
+
+        let r = Ray::new(dvec3(0.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(c.intersect(&r).count(), 0);
+
+        let r = Ray::new(dvec3(0.75, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.count(), 2);
+        assert_eq!(xs[0].t(), 4.0);
+        assert_eq!(xs[1].t(), 6.0);
+    }
+
+    #[test]
+    fn a_csg_intersection_with_a_mesh_as_its_right_child() {
+        let mesh = Object::new(
+            Shape::Mesh(
+                Mesh::new(
+                    vec![dvec3(-2.0, -2.0, 0.0), dvec3(2.0, -2.0, 0.0), dvec3(0.0, 2.0, 0.0)],
+                    vec![Face::new([0, 1, 2])]
+                )
+            )
+        );
+        let c = Object::new(
+            Shape::Csg(
+                Csg::new(
+                    CsgOperation::Intersection,
+                    Object::new(Shape::Sphere(Sphere::default())),
+                    mesh
+                )
+            )
+        );
+
+        // the single triangle toggles the inside of the right child
+        let r = Ray::new(dvec3(0.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.count(), 2);
+        assert_eq!(xs[0].t(), 5.0);
+        assert_eq!(xs[1].t(), 6.0);
+    }
+
+    #[test]
+    fn a_csg_difference_of_two_instances_of_the_same_object() {
+        let sphere = Arc::new(Object::new(Shape::Sphere(Sphere::default())));
+        let c = Object::new(
+            Shape::Csg(
+                Csg::new(
+                    CsgOperation::Difference,
+                    Object::new(Shape::Instance(Instance::new(sphere.clone()))),
+                    Object::new(Shape::Instance(Instance::new(sphere.clone())))
+                        .with_translation(0.5, 0.0, 0.0)
+                        .transform()
+                )
+            )
+        );
+
+        let r = Ray::new(dvec3(-5.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0));
+        let xs = c.intersect(&r);
+        assert_eq!(xs.count(), 2);
+        assert!((xs[0].t() - 4.0).abs() < 0.0001);
+        assert!((xs[1].t() - 4.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn a_csg_object_has_a_bounding_box_reflecting_its_operation() {
+        let left = Object::new(Shape::Sphere(Sphere::default()));
+        let right = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(1.0, 0.0, 0.0)
+            .transform();
+        let datas = vec![
+            (CsgOperation::Union, dvec3(-1.0, -1.0, -1.0), dvec3(2.0, 1.0, 1.0)),
+            (CsgOperation::Intersection, dvec3(0.0, -1.0, -1.0), dvec3(1.0, 1.0, 1.0)),
+            (CsgOperation::Difference, dvec3(-1.0, -1.0, -1.0), dvec3(1.0, 1.0, 1.0)),
+        ];
+
+        for data in datas {
+            let c = Csg::new(data.0, left.clone(), right.clone());
+            let bb = c.bounds();
+            assert_eq!(bb.min(), data.1);
+            assert_eq!(bb.max(), data.2);
+        }
+    }
+
+    #[test]
+    fn subdividing_a_csg_shape_subdivides_its_children() {
+        let s1 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(-1.5, 0.0, 0.0)
+            .transform();
+        let s2 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(1.5, 0.0, 0.0)
+            .transform();
+        let left = Object::new(Shape::Group(Group::new().with_objects(vec![s1, s2])));
+        let s3 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(0.0, 0.0, -1.5)
+            .transform();
+        let s4 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(0.0, 0.0, 1.5)
+            .transform();
+        let right = Object::new(Shape::Group(Group::new().with_objects(vec![s3, s4])));
+
+        let c = Object::new(Shape::Csg(Csg::new(CsgOperation::Difference, left, right)))
+            .divide(1);
+        let csg = match c.shape() {
+            Shape::Csg(csg) => csg,
+            _ => unreachable!(),
+        };
+
+        let left = csg.left().shape().as_group().unwrap();
+        assert_eq!(left.objects().len(), 2);
+        assert!(left.objects()[0].shape().as_group().is_some());
+        assert!(left.objects()[1].shape().as_group().is_some());
+
+        let right = csg.right().shape().as_group().unwrap();
+        assert_eq!(right.objects().len(), 2);
+        assert!(right.objects()[0].shape().as_group().is_some());
+        assert!(right.objects()[1].shape().as_group().is_some());
+    }
+}
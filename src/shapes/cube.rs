@@ -5,7 +5,7 @@ use glam::{DVec3, dvec3};
 use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox};
 use super::shape::Hittable;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Cube {}
 
 impl Hittable for Cube {
@@ -70,12 +70,6 @@ impl Hittable for Cube {
     }
 }
 
-impl Default for Cube {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl Cube {
     pub fn new() -> Self {
         Self::default()
@@ -1,4 +1,4 @@
-use std::{mem::swap, f64::EPSILON};
+use std::mem::swap;
 
 use glam::{DVec3, dvec3};
 
@@ -55,17 +55,21 @@ impl Hittable for Cylinder {
 
     fn normal_at(&self, point: DVec3, _: f64, _: f64) -> DVec3 {
         let dist = point.x * point.x + point.z * point.z;
-        if dist < 1.0 && point.y >= self.max - EPSILON {
+        if dist < 1.0 && point.y >= self.max - f64::EPSILON {
             dvec3(0.0, 1.0, 0.0)
-        } else if dist < 1.0 && point.y <= self.min + EPSILON {
+        } else if dist < 1.0 && point.y <= self.min + f64::EPSILON {
             dvec3(0.0, -1.0, 0.0)
         } else {
             dvec3(point.x, 0.0, point.z)
         }
     }
 
+    /// infinite along y unless min and max are set
     fn bounds(&self) -> BoundingBox {
-        BoundingBox::default()
+        BoundingBox::new(
+            dvec3(-1.0, self.min, -1.0),
+            dvec3(1.0, self.max, 1.0)
+        )
     }
 }
 
@@ -99,6 +103,18 @@ impl Cylinder {
         self
     }
 
+    pub fn min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn closed(&self) -> bool {
+        self.closed
+    }
+
     fn check_cap(ray: &Ray, t: f64) -> bool {
         let x = ray.origin.x + t * ray.direction.x;
         let z = ray.origin.z + t * ray.direction.z;
@@ -113,18 +129,14 @@ impl Cylinder {
 
         let mut xs = Vec::default();
 
-        let t = (self.min - ray.origin.y) / ray.direction.y;
-        if Cylinder::check_cap(ray, t) {
-            xs.push(
-                Intersection::new(t, object)
-            );
-        }
-
-        let t = (self.max - ray.origin.y) / ray.direction.y;
-        if Cylinder::check_cap(ray, t) {
-            xs.push(
-                Intersection::new(t, object)
-            );
+        // an infinite end has no cap
+        for y in [self.min, self.max] {
+            let t = (y - ray.origin.y) / ray.direction.y;
+            if y.is_finite() && Cylinder::check_cap(ray, t) {
+                xs.push(
+                    Intersection::new(t, object)
+                );
+            }
         }
 
         Intersections::new()
@@ -134,7 +146,7 @@ impl Cylinder {
 
 #[cfg(test)]
 mod tests {
-    use crate::shapes::Shape;
+    use crate::{shapes::{Shape, Group, Plane}, World, intersection::IntersectionInfos};
 
     use super::*;
 
@@ -262,7 +274,7 @@ mod tests {
     #[test]
     fn the_default_closed_value_for_a_cylinder() {
         let cyl = Cylinder::default();
-        assert_eq!(cyl.closed, true);
+        assert!(cyl.closed);
     }
 
     #[test]
@@ -316,4 +328,63 @@ mod tests {
             assert_eq!(xs.count(), 2);
         }
     }
+
+    #[test]
+    fn an_unbounded_cylinder_has_a_bounding_box() {
+        let cyl = Cylinder::new();
+        let bb = cyl.bounds();
+        assert_eq!(bb.min(), dvec3(-1.0, f64::NEG_INFINITY, -1.0));
+        assert_eq!(bb.max(), dvec3(1.0, f64::INFINITY, 1.0));
+        assert!(!bb.is_finite());
+    }
+
+    #[test]
+    fn a_bounded_cylinder_has_a_bounding_box() {
+        let cyl = Cylinder::new()
+            .with_min(-5.0)
+            .with_max(3.0);
+        let bb = cyl.bounds();
+        assert_eq!(bb.min(), dvec3(-1.0, -5.0, -1.0));
+        assert_eq!(bb.max(), dvec3(1.0, 3.0, 1.0));
+    }
+
+    #[test]
+    fn a_cylinder_in_a_divided_group_is_still_hit() {
+        let cylinders = (0..4)
+            .map(|i| Object::new(Shape::Cylinder(Cylinder::new().with_min(0.0).with_max(1.0)))
+                .with_translation(i as f64 * 3.0, 0.0, 0.0)
+                .transform()
+            )
+            .collect();
+        let g = Object::new(Shape::Group(Group::new().with_objects(cylinders))).divide(1);
+        assert!(g.bvh_stats().depth > 1);
+
+        let r = Ray::new(dvec3(6.0, 0.5, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = g.intersect(&r).sort();
+        assert_eq!(xs.count(), 2);
+        assert_eq!(xs[0].t(), 4.0);
+    }
+
+    #[test]
+    fn looking_into_an_open_and_a_closed_cylinder() {
+        let r = Ray::new(dvec3(0.0, 5.0, 0.2), dvec3(0.0, -1.0, 0.0));
+        // the floor is seen through an open cylinder
+        for (closed, t, index) in [(true, 4.0, 0), (false, 6.0, 1)] {
+            let w = World::new()
+                .with_objects(vec![
+                    Object::new(Shape::Cylinder(Cylinder::new().with_min(0.0).with_max(1.0).with_closed(closed)))
+                    .with_translation(0.0, 0.0, 0.5)
+                    .transform(),
+                    Object::new(Shape::Plane(Plane::new()))
+                    .with_translation(0.0, -1.0, 0.0)
+                    .transform()
+                ]);
+            let hit = w.closest_hit(&r).unwrap();
+            assert_eq!(hit.t(), t);
+            assert!(std::ptr::eq(hit.object(), &w.objects()[index]));
+            let infos = IntersectionInfos::from_hit(&hit, &r);
+            assert!(infos.normalv.abs_diff_eq(dvec3(0.0, 1.0, 0.0), EPSILON));
+            assert_eq!(w.occluded(&r, 5.0), closed);
+        }
+    }
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use glam::DVec3;
 
-use crate::{ray::Ray, Object, intersection::Intersections, bounds::BoundingBox};
+use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox, bvh::BvhBuilder};
 use super::{shape::Hittable, Shape};
 
 #[derive(Clone, Debug, PartialEq, Default)]
@@ -30,6 +30,26 @@ impl Hittable for Group {
                 bounds.merge(object.bounding_box())
             })
     }
+
+    fn closest_hit<'a>(&'a self, ray: &Ray, this: &'a Object) -> Option<Intersection<'a>> {
+        if !this.bounding_box().intersects(ray) {
+            return None;
+        }
+        let mut ray = *ray;
+        let mut closest = None;
+        for object in &self.objects {
+            if let Some(x) = object.closest_hit(&ray) {
+                ray.t_max = x.t();
+                closest = Some(x);
+            }
+        }
+        closest
+    }
+
+    fn occluded(&self, ray: &Ray, t_max: f64, this: &Object) -> bool {
+        this.bounding_box().intersects(ray) &&
+        self.objects.iter().any(|object| object.occluded(ray, t_max))
+    }
 }
 
 impl Group {
@@ -50,8 +70,14 @@ impl Group {
         &mut self.objects
     }
 
+    /// children with infinite bounds, like planes, stay in the group
+    /// and are left out of the box being split
     fn partition_children(&mut self) -> (Group, Group) {
-        let (left, right) = self.bounds().split();
+        let (left, right) = self.objects
+            .iter()
+            .filter(|object| object.bounding_box().is_finite())
+            .fold(BoundingBox::default(), |bounds, object| bounds.merge(object.bounding_box()))
+            .split();
 
         let mut left_objects = Vec::default();
         let mut right_objects = Vec::default();
@@ -86,7 +112,19 @@ impl Group {
         )
     }
 
-    pub fn divide(&mut self, threshold: usize) {
+    pub fn divide(&mut self, builder: impl Into<BvhBuilder>) {
+        match builder.into() {
+            BvhBuilder::Midpoint { threshold } => self.divide_at_midpoint(threshold),
+            BvhBuilder::Sah(sah) => {
+                for child in &mut self.objects {
+                    child.shape_mut().divide(sah.clone());
+                }
+                self.objects = sah.build(std::mem::take(&mut self.objects));
+            }
+        }
+    }
+
+    fn divide_at_midpoint(&mut self, threshold: usize) {
         if threshold <= self.objects.len() {
             let (left, right) = self.partition_children();
             if !left.objects.is_empty() {
@@ -97,7 +135,7 @@ impl Group {
             }
         }
         for child in &mut self.objects {
-            *child = child.clone().divide(threshold); //todo: improve
+            child.shape_mut().divide(threshold);
         }
     }
 }
@@ -105,7 +143,7 @@ impl Group {
 #[cfg(test)]
 mod tests {
     use glam::{DMat4, dvec3};
-    use crate::shapes::{Shape, Sphere};
+    use crate::shapes::{Shape, Sphere, Plane, Cylinder};
 
     use super::*;
 
@@ -187,6 +225,29 @@ mod tests {
         assert_eq!(xs.count(), 2);
     }
 
+    #[test]
+    fn the_closest_hit_of_a_transformed_group() {
+        let s1 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(5.0, 0.0, 2.0)
+            .transform();
+        let s2 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(5.0, 0.0, -2.0)
+            .transform();
+        let g = Object::new(Shape::Group(Group::new().with_objects(vec![s1, s2])))
+        .with_scale(2.0, 2.0, 2.0)
+        .transform();
+
+        let r = Ray::new(
+            dvec3(10.0, 0.0, -10.0),
+            dvec3(0.0, 0.0, 1.0)
+        );
+        let hit = g.closest_hit(&r).unwrap();
+        assert_eq!(hit.t(), 4.0);
+        assert_eq!(hit.object().transform().translation(), dvec3(10.0, 0.0, -4.0));
+        assert_eq!(g.closest_hit(&r.with_t_min(5.0)).unwrap().t(), 8.0);
+        assert!(g.closest_hit(&r.with_t_max(4.0)).is_none());
+    }
+
     #[test]
     fn partitionning_a_group_s_children() {
         let s1 = Object::new(Shape::Sphere(Sphere::default()))
@@ -272,14 +333,52 @@ mod tests {
         g.divide(3);
 
         let subgroup = g.objects[0].shape().as_group();
-        assert_eq!(subgroup.is_some(), true);
+        assert!(subgroup.is_some());
         assert_eq!(g.objects[1], s4);
         assert_eq!(subgroup.unwrap().objects.len(), 2);
         let subgroup0 = subgroup.unwrap().objects[0].shape().as_group();
-        assert_eq!(subgroup0.is_some(), true);
+        assert!(subgroup0.is_some());
         assert_eq!(subgroup0.unwrap().objects, vec![s1]);
         let subgroup1 = subgroup.unwrap().objects[1].shape().as_group();
-        assert_eq!(subgroup1.is_some(), true);
+        assert!(subgroup1.is_some());
         assert_eq!(subgroup1.unwrap().objects, vec![s2, s3]);
     }
+
+    #[test]
+    fn a_group_is_occluded_by_its_children_casting_shadows() {
+        let s1 = Object::new(Shape::Sphere(Sphere::default()))
+        .with_translation(0.0, 0.0, -3.0)
+        .transform()
+        .with_shadow(false);
+        let s2 = Object::new(Shape::Sphere(Sphere::default()))
+        .with_translation(0.0, 0.0, 3.0)
+        .transform();
+        let g = Object::new(Shape::Group(Group::default().with_objects(vec![s1, s2])));
+        let r = Ray::new(dvec3(0.0, 0.0, -10.0), dvec3(0.0, 0.0, 1.0));
+        assert!(!g.occluded(&r, 11.0));
+        assert!(g.occluded(&r, 13.0));
+    }
+
+    #[test]
+    fn unbounded_children_stay_at_the_top_of_a_divided_group() {
+        let p = Object::new(Shape::Plane(Plane::new()))
+            .with_translation(0.0, -1.0, 0.0)
+            .transform();
+        let c = Object::new(Shape::Cylinder(Cylinder::new()));
+        let s1 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(-2.0, 0.0, 0.0)
+            .transform();
+        let s2 = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(2.0, 0.0, 0.0)
+            .transform();
+        let mut g = Group::new().with_objects(vec![p.clone(), s1.clone(), c.clone(), s2.clone()]);
+
+        g.divide(1);
+
+        assert_eq!(g.objects.len(), 4);
+        assert_eq!(g.objects[0], p);
+        assert_eq!(g.objects[1], c);
+        assert_eq!(g.objects[2].shape().as_group().unwrap().objects, vec![s1]);
+        assert_eq!(g.objects[3].shape().as_group().unwrap().objects, vec![s2]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use glam::DVec3;
+
+use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox, Material};
+use super::shape::Hittable;
+
+/// places an object shared with other instances, the object holding the
+/// instance gives the transform and the instance may override the material
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    object: Arc<Object>,
+    material: Option<Box<Material>>,
+}
+
+impl Hittable for Instance {
+    fn intersect<'a>(&'a self, ray: &Ray, this: &'a Object) -> Intersections<'a> {
+        let transform = this.transform_at(ray.time);
+        Intersections::new().with_intersections(
+            self.object
+                .intersect(ray)
+                .move_all()
+                .into_iter()
+                .map(|x| x.placed(this, &transform.matrix, self.material.as_deref()))
+                .collect()
+        )
+    }
+
+    fn closest_hit<'a>(&'a self, ray: &Ray, this: &'a Object) -> Option<Intersection<'a>> {
+        self.object
+            .closest_hit(ray)
+            .map(|x| x.placed(this, &this.transform_at(ray.time).matrix, self.material.as_deref()))
+    }
+
+    /// an instance casting no shadow hides the one of its object
+    fn occluded(&self, ray: &Ray, t_max: f64, this: &Object) -> bool {
+        this.shadow() && self.object.occluded(ray, t_max)
+    }
+
+    /// the normal is the one of the shared object, see IntersectionInfos
+    fn normal_at(&self, _: DVec3, _: f64, _: f64) -> DVec3 {
+        DVec3::default()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        *self.object.bounding_box()
+    }
+}
+
+impl Instance {
+    pub fn new(object: Arc<Object>) -> Self {
+        Self {
+            object,
+            material: None,
+        }
+    }
+
+    pub fn with_material(mut self, material: Material) -> Self {
+        self.material = Some(Box::new(material));
+        self
+    }
+
+    pub fn object(&self) -> &Arc<Object> {
+        &self.object
+    }
+
+    pub fn material(&self) -> Option<&Material> {
+        self.material.as_deref()
+    }
+
+    pub(crate) fn set_material(&mut self, material: Material) {
+        self.material = Some(Box::new(material));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec3;
+
+    use crate::{shapes::{Shape, Sphere, Group, Mesh, Face}, intersection::IntersectionInfos, bvh::SahBuilder, transformations::Transform};
+
+    use super::*;
+
+    fn unit_mesh() -> Arc<Object> {
+        Arc::new(Object::new(Shape::Mesh(Mesh::new(
+            vec![dvec3(0.0, 0.0, 0.0), dvec3(0.0, 1.0, 0.0), dvec3(1.0, 0.0, 0.0)],
+            vec![Face::new([0, 1, 2])]
+        ))))
+    }
+
+    #[test]
+    fn instances_share_their_object() {
+        let mesh = unit_mesh();
+        let instances: Vec<Object> = (0..3)
+            .map(|i| Object::new(Shape::Instance(Instance::new(mesh.clone())))
+                .with_translation(i as f64 * 2.0, 0.0, 0.0)
+                .transform()
+            )
+            .collect();
+        assert_eq!(Arc::strong_count(&mesh), 4);
+        assert_eq!(
+            *instances[2].bounding_box(),
+            BoundingBox::new(dvec3(4.0, 0.0, 0.0), dvec3(5.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn intersecting_an_instance_places_the_hit() {
+        let sphere = Arc::new(Object::new(Shape::Sphere(Sphere::default()))
+            .with_scale(2.0, 2.0, 2.0)
+            .transform());
+        let inner = Object::new(Shape::Instance(Instance::new(sphere.clone())))
+            .with_translation(0.0, 0.0, 5.0)
+            .transform();
+        let outer = Object::new(Shape::Instance(Instance::new(Arc::new(inner))))
+            .with_translation(3.0, 0.0, 0.0)
+            .transform();
+
+        let r = Ray::new(dvec3(3.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = outer.intersect(&r).sort();
+        assert_eq!(xs.count(), 2);
+        assert_eq!(xs[0].t(), 8.0);
+        assert_eq!(xs[1].t(), 12.0);
+        assert!(std::ptr::eq(xs[0].object(), sphere.as_ref()));
+        assert_eq!(xs[0].placement().unwrap().transform(), Transform::new().with_translation(3.0, 0.0, 5.0));
+        assert_eq!(outer.closest_hit(&r).unwrap(), xs[0]);
+        assert!(outer.occluded(&r, 9.0));
+        assert!(!outer.occluded(&r, 7.0));
+    }
+
+    #[test]
+    fn shading_an_instance_with_its_own_material() {
+        let sphere = Arc::new(Object::new(Shape::Sphere(Sphere::default())));
+        let red = Material::new().with_ambient(1.0);
+        let o = Object::new(Shape::Instance(Instance::new(sphere.clone()).with_material(red.clone())))
+            .with_scale(1.0, 2.0, 1.0)
+            .with_translation(0.0, 0.0, 5.0)
+            .transform();
+
+        let r = Ray::new(dvec3(0.0, 0.0, 0.0), dvec3(0.0, 0.2, 1.0).normalize());
+        let hit = o.closest_hit(&r).unwrap();
+        assert_eq!(hit.material(), &red);
+        let placed = Object::new(Shape::Sphere(Sphere::default()))
+            .with_scale(1.0, 2.0, 1.0)
+            .with_translation(0.0, 0.0, 5.0)
+            .transform();
+        let infos = IntersectionInfos::from_hit(&hit, &r);
+        assert_eq!(hit.t(), placed.closest_hit(&r).unwrap().t());
+        assert!(infos.normalv.abs_diff_eq(placed.normal_at(infos.point, 0.0, 0.0), 0.0001));
+        assert!(infos.shading_object().world_to_object(infos.point).abs_diff_eq(placed.world_to_object(infos.point), 0.0001));
+        assert_eq!(infos.shading_object().material(), &red);
+    }
+
+    #[test]
+    fn an_instance_casting_no_shadow_is_not_occluding() {
+        let sphere = Arc::new(Object::new(Shape::Sphere(Sphere::default())));
+        let shadow = Object::new(Shape::Instance(Instance::new(sphere.clone())))
+            .with_translation(0.0, 0.0, 5.0)
+            .transform();
+        let no_shadow = shadow.clone().with_shadow(false);
+
+        let r = Ray::new(dvec3(0.0, 0.0, 0.0), dvec3(0.0, 0.0, 1.0));
+        assert!(shadow.occluded(&r, 10.0));
+        assert!(!no_shadow.occluded(&r, 10.0));
+    }
+
+    #[test]
+    fn building_a_hierarchy_over_instances() {
+        let mesh = unit_mesh();
+        let instances: Vec<Object> = (0..100)
+            .map(|i| Object::new(Shape::Instance(Instance::new(mesh.clone())))
+                .with_translation((i % 10) as f64 * 2.0, (i / 10) as f64 * 2.0, 0.0)
+                .transform()
+            )
+            .collect();
+        let g = Object::new(Shape::Group(Group::new().with_objects(instances)))
+            .flatten(&SahBuilder::new());
+        assert_eq!(g.bvh_stats().primitives, 100);
+        assert!(g.bvh_stats().depth > 1);
+
+        let r = Ray::new(dvec3(14.25, 6.25, -5.0), dvec3(0.0, 0.0, 1.0));
+        let hit = g.closest_hit(&r).unwrap();
+        assert_eq!(hit.t(), 5.0);
+        assert_eq!(hit.placement().unwrap().transform().translation(), dvec3(14.0, 6.0, 0.0));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,230 @@
+use glam::DVec3;
+
+use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox, bvh::{BvhBuilder, SahBuilder, BvhStats, BvhTree}, transformations::{Transformable, Transform}};
+use super::{shape::Hittable, Shape};
+
+/// bounding volume hierarchy stored as an array of nodes,
+/// its primitives are the shapes of a flattened group
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct LinearBvh {
+    tree: BvhTree,
+    primitives: Vec<Object>,
+}
+
+impl Hittable for LinearBvh {
+    fn intersect<'a>(&'a self, ray: &Ray, _: &'a Object) -> Intersections<'a> {
+        let mut xs = Intersections::new();
+        self.tree.traverse(ray, f64::INFINITY, |index, _| {
+            xs.append(self.primitives[index].intersect(ray));
+            f64::INFINITY
+        });
+        xs
+    }
+
+    fn normal_at(&self, _: DVec3, _: f64, _: f64) -> DVec3 {
+        DVec3::default()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.tree
+            .unbounded()
+            .iter()
+            .fold(self.tree.bounds(), |bounds, &index| bounds.merge(self.primitives[index].bounding_box()))
+    }
+
+    fn closest_hit<'a>(&'a self, ray: &Ray, _: &'a Object) -> Option<Intersection<'a>> {
+        let mut range = *ray;
+        let mut closest = None;
+        self.tree.traverse(ray, ray.t_max, |index, t_max| {
+            range.t_max = t_max;
+            if let Some(x) = self.primitives[index].closest_hit(&range) {
+                range.t_max = x.t();
+                closest = Some(x);
+            }
+            range.t_max
+        });
+        closest
+    }
+
+    fn occluded(&self, ray: &Ray, t_max: f64, _: &Object) -> bool {
+        self.tree.any_hit(ray, t_max, |index| self.primitives[index].occluded(ray, t_max))
+    }
+}
+
+impl LinearBvh {
+    /// nested groups are flattened, their shapes become the primitives
+    pub fn new(objects: Vec<Object>, builder: &SahBuilder) -> Self {
+        let mut primitives = Vec::with_capacity(objects.len());
+        for object in objects {
+            Self::flatten(object, &Transform::identity(), &mut primitives);
+        }
+
+        let bounds: Vec<BoundingBox> = primitives.iter().map(|p| *p.bounding_box()).collect();
+        Self {
+            tree: BvhTree::new(&bounds, &BvhBuilder::Sah(builder.clone())),
+            primitives,
+        }
+    }
+
+    fn flatten(object: Object, parent: &Transform, primitives: &mut Vec<Object>) {
+        let children = match object.shape() {
+            Shape::Group(g) => g.objects(),
+            Shape::Bvh(b) => &b.primitives,
+            _ => {
+                let mut primitive = object;
+                if *parent != Transform::identity() {
+                    primitive.apply_transform(parent.clone());
+                }
+                primitives.push(primitive);
+                return;
+            }
+        };
+        let transform = object.transform().clone().apply(parent.clone());
+        for child in children {
+            Self::flatten(child.clone(), &transform, primitives);
+        }
+    }
+
+    pub fn primitives(&self) -> &Vec<Object> {
+        &self.primitives
+    }
+
+    pub(crate) fn primitives_mut(&mut self) -> &mut Vec<Object> {
+        &mut self.primitives
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.tree.node_count()
+    }
+
+    /// recomputes the bounds of the nodes once the primitives moved
+    pub(crate) fn refit(&mut self) {
+        let primitives = &self.primitives;
+        self.tree.refit(|index| *primitives[index].bounding_box());
+    }
+
+    pub(crate) fn add_stats(&self, stats: &mut BvhStats, depth: usize) {
+        self.tree.add_stats(stats, depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec3;
+
+    use crate::shapes::{Sphere, Group, Plane, Triangle, Mesh, Face};
+
+    use super::*;
+
+    fn spheres_along_x(count: usize) -> Vec<Object> {
+        (0..count)
+            .map(|i| Object::new(Shape::Sphere(Sphere::default()))
+                .with_translation(3.0 * i as f64, 0.0, 0.0)
+                .transform()
+            )
+            .collect()
+    }
+
+    #[test]
+    fn building_a_linear_bvh_from_a_group() {
+        let inner = Object::new(Shape::Group(Group::new().with_objects(spheres_along_x(4))))
+            .with_translation(0.0, 5.0, 0.0)
+            .transform();
+        let mut objects = spheres_along_x(4);
+        objects.push(inner);
+        let g = Object::new(Shape::Group(Group::new().with_objects(objects)));
+
+        let bvh = LinearBvh::new(vec![g.clone()], &SahBuilder::new().with_leaf_size(1));
+        assert_eq!(bvh.primitives().len(), 8);
+        // neighbouring pairs are cheaper to test together than to split
+        assert_eq!(bvh.node_count(), 7);
+        assert_eq!(bvh.bounds(), *g.bounding_box());
+        assert!(bvh.primitives().iter().all(|p| matches!(p.shape(), Shape::Sphere(_))));
+        assert_eq!(bvh.primitives()[7].transform().translation(), dvec3(9.0, 5.0, 0.0));
+    }
+
+    #[test]
+    fn intersecting_a_linear_bvh_finds_every_hit() {
+        let bvh = Object::new(Shape::Bvh(LinearBvh::new(spheres_along_x(8), &SahBuilder::new().with_leaf_size(1))));
+        let r = Ray::new(dvec3(-5.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0));
+        let xs = bvh.intersect(&r).sort();
+        assert_eq!(xs.count(), 16);
+        assert_eq!(xs[0].t(), 4.0);
+        assert_eq!(xs[15].t(), 27.0);
+
+        let r = Ray::new(dvec3(3.0, 5.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(bvh.intersect(&r).count(), 0);
+    }
+
+    #[test]
+    fn the_closest_hit_of_a_linear_bvh() {
+        let bvh = Object::new(Shape::Bvh(LinearBvh::new(spheres_along_x(8), &SahBuilder::new().with_leaf_size(1))));
+
+        let r = Ray::new(dvec3(30.0, 0.0, 0.0), dvec3(-1.0, 0.0, 0.0));
+        let hit = bvh.closest_hit(&r).unwrap();
+        assert_eq!(hit.t(), 8.0);
+        assert_eq!(hit.object().transform().translation(), dvec3(21.0, 0.0, 0.0));
+
+        let r = Ray::new(dvec3(10.5, 0.0, 0.0), dvec3(1.0, 0.0, 0.0));
+        assert_eq!(bvh.closest_hit(&r).unwrap().t(), 0.5);
+        assert!(bvh.closest_hit(&r.with_t_max(0.4)).is_none());
+        assert_eq!(bvh.closest_hit(&r.with_t_min(0.6)).unwrap().t(), 2.5);
+    }
+
+    #[test]
+    fn unbounded_primitives_are_kept_out_of_the_nodes() {
+        let mut objects = spheres_along_x(2);
+        objects.push(Object::new(Shape::Plane(Plane::default())));
+        let bvh = LinearBvh::new(objects, &SahBuilder::new().with_leaf_size(1));
+        assert_eq!(bvh.node_count(), 1);
+
+        let r = Ray::new(dvec3(20.0, 5.0, 0.0), dvec3(0.0, -1.0, 0.0));
+        let o = Object::new(Shape::Bvh(bvh));
+        assert_eq!(o.closest_hit(&r).unwrap().t(), 5.0);
+    }
+
+    #[test]
+    fn meshes_stay_whole_in_a_linear_bvh() {
+        let mut objects: Vec<Object> = (0..10)
+            .map(|i| Object::new(Shape::Triangle(Triangle::new(
+                dvec3(i as f64, 1.0, 0.0), dvec3(i as f64, 0.0, 0.0), dvec3(i as f64 + 0.5, 0.0, 0.0)
+            ))))
+            .collect();
+        objects.push(Object::new(Shape::Mesh(Mesh::new(
+            vec![dvec3(0.0, 0.0, 5.0), dvec3(0.0, 1.0, 5.0), dvec3(1.0, 0.0, 5.0)],
+            vec![Face::new([0, 1, 2])]
+        ))));
+        let g = Object::new(Shape::Group(Group::new().with_objects(objects)))
+            .with_translation(0.0, 0.0, 2.0)
+            .transform();
+        let flat = g.clone().flatten(&SahBuilder::new());
+
+        assert_eq!(flat.bounding_box(), g.bounding_box());
+        let stats = flat.bvh_stats();
+        assert_eq!(stats.primitives, 11);
+        assert!(stats.max_leaf_size <= 4);
+
+        let r = Ray::new(dvec3(7.1, 0.1, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(flat.intersect(&r).count(), 1);
+        assert_eq!(flat.intersect(&r)[0].t(), 7.0);
+
+        let r = Ray::new(dvec3(0.1, 0.1, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = flat.intersect(&r).sort();
+        assert_eq!(xs.count(), 2);
+        assert!(matches!(xs[1].object().shape(), Shape::Mesh(_)));
+        assert_eq!(xs[1].t(), 12.0);
+    }
+
+    #[test]
+    fn a_linear_bvh_is_occluded_by_primitives_casting_shadows() {
+        let mut objects = spheres_along_x(8);
+        objects[0] = objects[0].clone().with_shadow(false);
+        let bvh = Object::new(Shape::Bvh(LinearBvh::new(objects, &SahBuilder::new().with_leaf_size(1))));
+
+        let r = Ray::new(dvec3(-5.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0));
+        assert!(!bvh.occluded(&r, 6.5));
+        assert!(bvh.occluded(&r, 7.5));
+        let r = Ray::new(dvec3(1.5, 0.0, 0.0), dvec3(1.0, 0.0, 0.0));
+        assert!(!bvh.occluded(&r, 0.25));
+    }
+}
\ No newline at end of file
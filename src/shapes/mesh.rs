@@ -1,51 +1,290 @@
-use glam::DVec3;
+use glam::{DVec2, DVec3};
 
-use crate::{ray::Ray, Object, intersection::Intersections, bounds::BoundingBox};
-use super::{shape::Hittable, Group};
+use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox, bvh::{BvhBuilder, BvhTree, BvhStats}};
+use super::{shape::Hittable, Triangle, triangle::intersect_triangle};
 
+/// a triangle of a mesh, its vertices are indices in the buffers of the mesh
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Face {
+    positions: [usize; 3],
+    normals: Option<[usize; 3]>,
+    uvs: Option<[usize; 3]>,
+}
+
+impl Face {
+    pub fn new(positions: [usize; 3]) -> Self {
+        Self {
+            positions,
+            ..Default::default()
+        }
+    }
+
+    /// the normals are interpolated across the face
+    pub fn with_normals(mut self, normals: [usize; 3]) -> Self {
+        self.normals = Some(normals);
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: [usize; 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    pub fn positions(&self) -> [usize; 3] {
+        self.positions
+    }
+
+    pub fn normals(&self) -> Option<[usize; 3]> {
+        self.normals
+    }
+
+    pub fn uvs(&self) -> Option<[usize; 3]> {
+        self.uvs
+    }
+}
+
+/// triangles sharing vertex, normal and uv buffers, the whole mesh is a single
+/// object with a single material
 #[derive(Clone, Debug, PartialEq, Default)]
 pub struct Mesh {
-    triangle_group: Group,
+    positions: Vec<DVec3>,
+    normals: Vec<DVec3>,
+    uvs: Vec<DVec2>,
+    faces: Vec<Face>,
+    tree: BvhTree,
 }
 
 impl Hittable for Mesh {
     fn intersect<'a>(&'a self, ray: &Ray, this: &'a Object) -> Intersections<'a> {
-        self.triangle_group.intersect(ray, this)
+        let mut xs = Intersections::new();
+        self.tree.traverse(ray, f64::INFINITY, |face, t_max| {
+            let (p1, p2, p3) = self.vertices(face);
+            if let Some((t, u, v)) = intersect_triangle(p1, p2 - p1, p3 - p1, ray) {
+                xs.push(
+                    Intersection::new(t, this)
+                    .with_u_v(u, v)
+                    .with_face(face)
+                );
+            }
+            t_max
+        });
+        xs
     }
 
+    /// the normal depends on the face, see normal_at_face
     fn normal_at(&self, _: DVec3, _: f64, _: f64) -> DVec3 {
         DVec3::default()
     }
 
     fn bounds(&self) -> BoundingBox {
-        self.triangle_group.bounds()
+        self.tree.bounds()
+    }
+
+    fn closest_hit<'a>(&'a self, ray: &Ray, this: &'a Object) -> Option<Intersection<'a>> {
+        let mut closest = None;
+        self.tree.traverse(ray, ray.t_max, |face, t_max| {
+            let (p1, p2, p3) = self.vertices(face);
+            match intersect_triangle(p1, p2 - p1, p3 - p1, ray) {
+                Some((t, u, v)) if t >= ray.t_min && t < t_max => {
+                    closest = Some(Intersection::new(t, this).with_u_v(u, v).with_face(face));
+                    t
+                }
+                _ => t_max,
+            }
+        });
+        closest
+    }
+
+    fn occluded(&self, ray: &Ray, t_max: f64, this: &Object) -> bool {
+        this.shadow() && self.tree.any_hit(ray, t_max, |face| {
+            let (p1, p2, p3) = self.vertices(face);
+            intersect_triangle(p1, p2 - p1, p3 - p1, ray).is_some_and(|(t, _, _)| t >= 0.0 && t < t_max)
+        })
     }
 }
 
 impl Mesh {
-    pub fn new() -> Self {
-        Self::default()
+    /// the faces are kept in a single leaf until the mesh is divided
+    pub fn new(positions: Vec<DVec3>, faces: Vec<Face>) -> Self {
+        let mut mesh = Self {
+            positions,
+            faces,
+            ..Default::default()
+        };
+        mesh.divide(usize::MAX);
+        mesh
     }
 
-    pub fn with_triangles(mut self, triangles: Vec<Object>) -> Self {
-        self.triangle_group = Group::new().with_objects(triangles);
+    pub fn with_normals(mut self, normals: Vec<DVec3>) -> Self {
+        self.normals = normals;
         self
     }
 
-    pub fn triangles(&mut self) -> &Vec<Object> {
-        self.triangle_group.objects()
+    pub fn with_uvs(mut self, uvs: Vec<DVec2>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn positions(&self) -> &Vec<DVec3> {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &Vec<DVec3> {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &Vec<DVec2> {
+        &self.uvs
     }
 
-    pub fn triangles_mut(&mut self) -> &mut Vec<Object> {
-        self.triangle_group.objects_mut()
+    pub fn faces(&self) -> &Vec<Face> {
+        &self.faces
     }
 
-    pub fn divide(&mut self, threshold: usize) {
-        self.triangle_group.divide(threshold);
+    fn vertices(&self, face: usize) -> (DVec3, DVec3, DVec3) {
+        let [p1, p2, p3] = self.faces[face].positions;
+        (self.positions[p1], self.positions[p2], self.positions[p3])
+    }
+
+    pub fn triangle(&self, face: usize) -> Triangle {
+        let (p1, p2, p3) = self.vertices(face);
+        Triangle::new(p1, p2, p3)
+    }
+
+    /// u and v locate the hit on the face like for smooth triangles
+    pub fn normal_at_face(&self, face: usize, u: f64, v: f64) -> DVec3 {
+        match self.faces[face].normals {
+            Some([n1, n2, n3]) => {
+                self.normals[n2] * u +
+                self.normals[n3] * v +
+                self.normals[n1] * (1.0 - u - v)
+            }
+            None => {
+                let (p1, p2, p3) = self.vertices(face);
+                (p3 - p1).cross(p2 - p1).normalize()
+            }
+        }
+    }
+
+    pub fn uv_at_face(&self, face: usize, u: f64, v: f64) -> Option<DVec2> {
+        self.faces[face].uvs.map(|[uv1, uv2, uv3]| {
+            self.uvs[uv2] * u +
+            self.uvs[uv3] * v +
+            self.uvs[uv1] * (1.0 - u - v)
+        })
+    }
+
+    /// sorts the faces in a hierarchy of their own
+    pub fn divide(&mut self, builder: impl Into<BvhBuilder>) {
+        let builder = builder.into();
+        let bounds: Vec<BoundingBox> = (0..self.faces.len())
+            .map(|face| {
+                let (p1, p2, p3) = self.vertices(face);
+                BoundingBox::default()
+                    .add_point(p1)
+                    .add_point(p2)
+                    .add_point(p3)
+            })
+            .collect();
+        self.tree = BvhTree::new(&bounds, &builder);
+    }
+
+    pub(crate) fn add_stats(&self, stats: &mut BvhStats, depth: usize) {
+        self.tree.add_stats(stats, depth);
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use glam::{dvec2, dvec3};
+
+    use crate::{shapes::Shape, transformations::Transform, bvh::SahBuilder};
+
+    use super::*;
+
+    fn strip(count: usize) -> Mesh {
+        let positions = (0..=count)
+            .flat_map(|i| [dvec3(i as f64, 0.0, 0.0), dvec3(i as f64, 1.0, 0.0)])
+            .collect();
+        let faces = (0..count)
+            .flat_map(|i| [
+                Face::new([2 * i, 2 * i + 1, 2 * i + 2]),
+                Face::new([2 * i + 1, 2 * i + 3, 2 * i + 2]),
+            ])
+            .collect();
+        Mesh::new(positions, faces)
+    }
 
+    #[test]
+    fn faces_share_the_vertices_of_a_mesh() {
+        let mesh = strip(2);
+        assert_eq!(mesh.positions().len(), 6);
+        assert_eq!(mesh.faces().len(), 4);
+        assert_eq!(
+            mesh.triangle(1),
+            Triangle::new(dvec3(0.0, 1.0, 0.0), dvec3(1.0, 1.0, 0.0), dvec3(1.0, 0.0, 0.0))
+        );
+        assert_eq!(mesh.bounds(), BoundingBox::new(dvec3(0.0, 0.0, 0.0), dvec3(2.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn intersecting_a_mesh_reports_the_face_hit() {
+        let o = Object::new(Shape::Mesh(strip(4)))
+            .with_transform(Transform::new().with_translation(0.0, 0.0, 1.0));
+        let r = Ray::new(dvec3(2.25, 0.5, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = o.intersect(&r);
+        assert_eq!(xs.count(), 1);
+        assert_eq!(xs[0].t(), 6.0);
+        assert_eq!(xs[0].face(), 4);
+        assert!(std::ptr::eq(xs[0].object(), &o));
+        assert_eq!(o.normal_at_intersection(r.at(6.0), &xs[0]), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(o.closest_hit(&r).unwrap(), xs[0]);
+        assert!(o.closest_hit(&r.with_t_max(6.0)).is_none());
+    }
+
+    #[test]
+    fn a_mesh_with_normals_interpolates_them() {
+        let mesh = Mesh::new(
+            vec![dvec3(0.0, 1.0, 0.0), dvec3(-1.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0)],
+            vec![Face::new([0, 1, 2]).with_normals([0, 1, 2]).with_uvs([0, 1, 2])],
+        )
+        .with_normals(vec![dvec3(0.0, 1.0, 0.0), dvec3(-1.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0)])
+        .with_uvs(vec![dvec2(0.5, 1.0), dvec2(0.0, 0.0), dvec2(1.0, 0.0)]);
+
+        let n = mesh.normal_at_face(0, 0.45, 0.25);
+        assert!(n.abs_diff_eq(dvec3(-0.2, 0.3, 0.0), 0.0001));
+        assert_eq!(mesh.uv_at_face(0, 0.0, 1.0), Some(dvec2(1.0, 0.0)));
+        assert_eq!(strip(1).uv_at_face(0, 0.5, 0.5), None);
+    }
+
+    #[test]
+    fn dividing_a_mesh_sorts_its_faces() {
+        let mut o = Object::new(Shape::Mesh(strip(16)));
+        assert_eq!(o.bvh_stats().max_leaf_size, 32);
+
+        o = o.divide(4);
+        let stats = o.bvh_stats();
+        assert_eq!(stats.primitives, 32);
+        assert!(stats.max_leaf_size <= 4);
+        assert!(stats.nodes > 1);
+
+        let r = Ray::new(dvec3(9.75, 0.5, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = o.intersect(&r);
+        assert_eq!(xs.count(), 1);
+        assert_eq!(xs[0].face(), 19);
+    }
+
+    #[test]
+    fn dividing_a_mesh_with_the_surface_area_heuristic() {
+        let o = Object::new(Shape::Mesh(strip(16)))
+            .divide(SahBuilder::new().with_leaf_size(2));
+        let stats = o.bvh_stats();
+        assert_eq!(stats.primitives, 32);
+        assert!(stats.max_leaf_size <= 2);
+
+        let r = Ray::new(dvec3(9.75, 0.5, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = o.intersect(&r);
+        assert_eq!(xs.count(), 1);
+        assert_eq!(xs[0].face(), 19);
+    }
 }
\ No newline at end of file
@@ -1,18 +1,16 @@
-use std::f64::EPSILON;
-
 use glam::{DVec3, dvec3};
 
 use crate::{ray::Ray, intersection::{Intersections, Intersection}, Object, bounds::BoundingBox};
 use super::shape::Hittable;
 
 /// infinite xz plane 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Plane {}
 
 impl Hittable for Plane {
     fn intersect<'a>(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
         let mut xs = Intersections::new();
-        if ray.direction.y.abs() > EPSILON {
+        if ray.direction.y.abs() > f64::EPSILON {
             xs.push(
                 Intersection::new(
                     -ray.origin.y / ray.direction.y,
@@ -36,12 +34,6 @@ impl Hittable for Plane {
     }
 }
 
-impl Default for Plane {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl Plane {
     pub fn new() -> Self {
         Self::default()
@@ -51,8 +43,6 @@ impl Plane {
 
 #[cfg(test)]
 mod tests {
-    use std::f64::{NEG_INFINITY, INFINITY};
-
     use glam::dvec3;
 
     use crate::shapes::Shape;
@@ -70,10 +60,10 @@ mod tests {
     #[test]
     fn intersect_with_a_ray_parallel_to_the_plane() {
         let p = Object::new(Shape::Plane(Plane::default()));
-        let r = Ray {
-            origin: dvec3(0.0, 10.0, 0.0),
-            direction: dvec3(0.0, 0.0, 1.0)
-        };
+        let r = Ray::new(
+            dvec3(0.0, 10.0, 0.0),
+            dvec3(0.0, 0.0, 1.0)
+        );
         let xs = p.intersect(&r);
         assert_eq!(xs.count(), 0);
     }
@@ -81,10 +71,10 @@ mod tests {
     #[test]
     fn intersect_with_a_ray_coplanar_to_the_plane() {
         let p = Object::new(Shape::Plane(Plane::default()));
-        let r = Ray {
-            origin: dvec3(0.0, 0.0, 0.0),
-            direction: dvec3(0.0, 0.0, 1.0)
-        };
+        let r = Ray::new(
+            dvec3(0.0, 0.0, 0.0),
+            dvec3(0.0, 0.0, 1.0)
+        );
         let xs = p.intersect(&r);
         assert_eq!(xs.count(), 0);
     }
@@ -92,10 +82,10 @@ mod tests {
     #[test]
     fn a_ray_intersecting_a_plane_from_above() {
         let p = Object::new(Shape::Plane(Plane::default()));
-        let r = Ray {
-            origin: dvec3(0.0, 1.0, 0.0),
-            direction: dvec3(0.0, -1.0, 0.0)
-        };
+        let r = Ray::new(
+            dvec3(0.0, 1.0, 0.0),
+            dvec3(0.0, -1.0, 0.0)
+        );
         let xs = p.intersect(&r);
         assert_eq!(xs.count(), 1);
         assert_eq!(xs[0].t(), 1.0);
@@ -104,10 +94,10 @@ mod tests {
     #[test]
     fn a_ray_intersecting_a_plane_from_below() {
         let p = Object::new(Shape::Plane(Plane::default()));
-        let r = Ray {
-            origin: dvec3(0.0, -1.0, 0.0),
-            direction: dvec3(0.0, 1.0, 1.0)
-        };
+        let r = Ray::new(
+            dvec3(0.0, -1.0, 0.0),
+            dvec3(0.0, 1.0, 1.0)
+        );
         let xs = p.intersect(&r);
         assert_eq!(xs.count(), 1);
         assert_eq!(xs[0].t(), 1.0);
@@ -117,7 +107,7 @@ mod tests {
     fn a_plane_has_a_bounding_box() {
         let p = Plane::default();
         let bb = p.bounds();
-        assert_eq!(bb.min(), dvec3(NEG_INFINITY, 0.0, NEG_INFINITY));
-        assert_eq!(bb.max(), dvec3(INFINITY, 0.0, INFINITY));
+        assert_eq!(bb.min(), dvec3(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY));
+        assert_eq!(bb.max(), dvec3(f64::INFINITY, 0.0, f64::INFINITY));
     }
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 use glam::DVec3;
 
-use crate::{ray::Ray, intersection::Intersections, Object, bounds::BoundingBox};
-use super::{Sphere, test_shape::TestShape, Plane, Cube, Group, Triangle, SmoothTriangle, Mesh, Cylinder};
+use crate::{ray::Ray, intersection::{Intersections, Intersection}, Object, bounds::BoundingBox, bvh::BvhBuilder};
+use super::{Sphere, test_shape::TestShape, Plane, Cube, Group, Triangle, SmoothTriangle, Mesh, Cylinder, Cone, Csg, LinearBvh, Instance};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Shape {
@@ -9,10 +9,14 @@ pub enum Shape {
     Plane(Plane),
     Cube(Cube),
     Cylinder(Cylinder),
+    Cone(Cone),
     Triangle(Triangle),
     SmoothTriangle(SmoothTriangle),
     Mesh(Mesh),
     Group(Group),
+    Bvh(LinearBvh),
+    Instance(Instance),
+    Csg(Csg),
     TestShape(TestShape),
 }
 
@@ -31,10 +35,19 @@ impl Shape {
         }
     }
 
-    pub fn divide(&mut self, threshold: usize) {
+    pub fn as_csg(&self) -> Option<&Csg> {
         match self {
-            Shape::Group(g) => g.divide(threshold),
-            Shape::Mesh(m) => m.divide(threshold),
+            Shape::Csg(c) => Some(c),
+            _ => None
+        }
+    }
+
+    pub fn divide(&mut self, builder: impl Into<BvhBuilder>) {
+        let builder = builder.into();
+        match self {
+            Shape::Group(g) => g.divide(builder),
+            Shape::Mesh(m) => m.divide(builder),
+            Shape::Csg(c) => c.divide(builder),
             _ => ()
         }
     }
@@ -44,6 +57,25 @@ pub trait Hittable {
     fn intersect<'a>(&'a self, ray: &Ray, object: &'a Object) -> Intersections<'a>;
     fn normal_at(&self, world_point: DVec3, u: f64, v: f64) -> DVec3;
     fn bounds(&self) -> BoundingBox;
+
+    /// nearest hit within the range of the ray, shapes holding other objects
+    /// narrow the range as they find closer hits
+    fn closest_hit<'a>(&'a self, ray: &Ray, object: &'a Object) -> Option<Intersection<'a>> {
+        self.intersect(ray, object)
+            .move_all()
+            .into_iter()
+            .filter(|x| ray.in_range(x.t()))
+            .min()
+    }
+
+    /// true if an object casting shadows is hit between 0 and t_max,
+    /// shapes holding other objects stop at the first such hit
+    fn occluded(&self, ray: &Ray, t_max: f64, object: &Object) -> bool {
+        self.intersect(ray, object)
+            .get_all()
+            .iter()
+            .any(|x| x.object().shadow() && x.t() >= 0.0 && x.t() < t_max)
+    }
 }
 
 impl Hittable for Shape {
@@ -53,10 +85,14 @@ impl Hittable for Shape {
             Shape::Plane(p) => p.intersect(ray, object),
             Shape::Cube(c) => c.intersect(ray, object),
             Shape::Cylinder(c) => c.intersect(ray, object),
+            Shape::Cone(c) => c.intersect(ray, object),
             Shape::Triangle(t) => t.intersect(ray, object),
             Shape::SmoothTriangle(t) => t.intersect(ray, object),
             Shape::Mesh(m) => m.intersect(ray, object),
             Shape::Group(g) => g.intersect(ray, object),
+            Shape::Bvh(b) => b.intersect(ray, object),
+            Shape::Instance(i) => i.intersect(ray, object),
+            Shape::Csg(c) => c.intersect(ray, object),
             Shape::TestShape(s) => s.intersect(ray, object),
         }
     }
@@ -67,10 +103,14 @@ impl Hittable for Shape {
             Shape::Plane(p) => p.normal_at(point, u, v),
             Shape::Cube(c) => c.normal_at(point, u, v),
             Shape::Cylinder(c) => c.normal_at(point, u, v),
+            Shape::Cone(c) => c.normal_at(point, u, v),
             Shape::Triangle(t) => t.normal_at(point, u, v),
             Shape::SmoothTriangle(t) => t.normal_at(point, u, v),
             Shape::Mesh(m) => m.normal_at(point, u, v),
             Shape::Group(g) => g.normal_at(point, u, v),
+            Shape::Bvh(b) => b.normal_at(point, u, v),
+            Shape::Instance(i) => i.normal_at(point, u, v),
+            Shape::Csg(c) => c.normal_at(point, u, v),
             Shape::TestShape(s) => s.normal_at(point, u, v),
         }
     }
@@ -81,11 +121,51 @@ impl Hittable for Shape {
             Shape::Plane(p) => p.bounds(),
             Shape::Cube(c) => c.bounds(),
             Shape::Cylinder(c) => c.bounds(),
+            Shape::Cone(c) => c.bounds(),
             Shape::Triangle(t) => t.bounds(),
             Shape::SmoothTriangle(t) => t.bounds(),
             Shape::Mesh(m) => m.bounds(),
             Shape::Group(g) => g.bounds(),
+            Shape::Bvh(b) => b.bounds(),
+            Shape::Instance(i) => i.bounds(),
+            Shape::Csg(c) => c.bounds(),
             Shape::TestShape(s) => s.bounds(),
         }
     }
+
+    fn closest_hit<'a>(&'a self, ray: &Ray, object: &'a Object) -> Option<Intersection<'a>> {
+        match self {
+            Shape::Sphere(s) => s.closest_hit(ray, object),
+            Shape::Plane(p) => p.closest_hit(ray, object),
+            Shape::Cube(c) => c.closest_hit(ray, object),
+            Shape::Cylinder(c) => c.closest_hit(ray, object),
+            Shape::Cone(c) => c.closest_hit(ray, object),
+            Shape::Triangle(t) => t.closest_hit(ray, object),
+            Shape::SmoothTriangle(t) => t.closest_hit(ray, object),
+            Shape::Mesh(m) => m.closest_hit(ray, object),
+            Shape::Group(g) => g.closest_hit(ray, object),
+            Shape::Bvh(b) => b.closest_hit(ray, object),
+            Shape::Instance(i) => i.closest_hit(ray, object),
+            Shape::Csg(c) => c.closest_hit(ray, object),
+            Shape::TestShape(s) => s.closest_hit(ray, object),
+        }
+    }
+
+    fn occluded(&self, ray: &Ray, t_max: f64, object: &Object) -> bool {
+        match self {
+            Shape::Sphere(s) => s.occluded(ray, t_max, object),
+            Shape::Plane(p) => p.occluded(ray, t_max, object),
+            Shape::Cube(c) => c.occluded(ray, t_max, object),
+            Shape::Cylinder(c) => c.occluded(ray, t_max, object),
+            Shape::Cone(c) => c.occluded(ray, t_max, object),
+            Shape::Triangle(t) => t.occluded(ray, t_max, object),
+            Shape::SmoothTriangle(t) => t.occluded(ray, t_max, object),
+            Shape::Mesh(m) => m.occluded(ray, t_max, object),
+            Shape::Group(g) => g.occluded(ray, t_max, object),
+            Shape::Bvh(b) => b.occluded(ray, t_max, object),
+            Shape::Instance(i) => i.occluded(ray, t_max, object),
+            Shape::Csg(c) => c.occluded(ray, t_max, object),
+            Shape::TestShape(s) => s.occluded(ray, t_max, object),
+        }
+    }
 }
\ No newline at end of file
@@ -3,7 +3,7 @@ use glam::{DVec3, dvec3};
 use crate::{ray::Ray, Object, intersection::{Intersections, Intersection}, bounds::BoundingBox};
 use super::shape::Hittable;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Sphere {}
 
 impl Hittable for Sphere {
@@ -49,12 +49,6 @@ impl Hittable for Sphere {
     }
 }
 
-impl Default for Sphere {
-    fn default() -> Self {
-        Self {}
-    }
-}
-
 impl Sphere {
     pub fn new() -> Self {
         Self::default()
@@ -44,27 +44,12 @@ impl Triangle {
 impl Hittable for Triangle {
     fn intersect<'a>(&self, ray: &Ray, object: &'a Object) -> Intersections<'a> {
         let mut xs = Intersections::new();
-        
-        let dir_cross_e2 = ray.direction.cross(self.e2);
-        let det = self.e1.dot(dir_cross_e2);
-        if det.abs() < f64::EPSILON { return xs }
-
-        let f = 1.0 / det;
-        let p1_to_origin = ray.origin - self.p1;
-        let u = f * p1_to_origin.dot(dir_cross_e2);
-        if u < 0.0 || u > 1.0 { return xs }
-
-        let origin_cross_e1 = p1_to_origin.cross(self.e1);
-        let v = f * ray.direction.dot(origin_cross_e1);
-        if v < 0.0 || u + v > 1.0 { return xs }
-
-        xs.push(
-            Intersection::new(
-                f * self.e2.dot(origin_cross_e1), 
-                object
-            )
-            .with_u_v(u, v)
-        );
+        if let Some((t, u, v)) = intersect_triangle(self.p1, self.e1, self.e2, ray) {
+            xs.push(
+                Intersection::new(t, object)
+                .with_u_v(u, v)
+            );
+        }
         xs
     }
 
@@ -88,6 +73,24 @@ impl Hittable for Triangle {
     }
 }
 
+/// Möller–Trumbore intersection, returns the distance and the u, v coordinates of the hit
+pub(crate) fn intersect_triangle(p1: DVec3, e1: DVec3, e2: DVec3, ray: &Ray) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(dir_cross_e2);
+    if det.abs() < f64::EPSILON { return None }
+
+    let f = 1.0 / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) { return None }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 { return None }
+
+    Some((f * e2.dot(origin_cross_e1), u, v))
+}
+
 #[cfg(test)]
 mod tests {
     use glam::dvec3;
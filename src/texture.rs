@@ -0,0 +1,659 @@
+use std::{f64::consts::PI, sync::Arc, path::Path};
+
+use glam::{DVec2, DVec3, dvec2};
+
+use crate::{Color, Canvas, pattern::PatternFunc};
+
+/// projection of a 3D point of the pattern space onto the (u, v) unit square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+    Cylindrical,
+    /// each face of the unit cube is mapped on the whole square
+    Cubic,
+}
+
+impl UvMapping {
+    pub fn map(&self, point: DVec3) -> DVec2 {
+        match self {
+            UvMapping::Spherical => spherical_map(point),
+            UvMapping::Planar => planar_map(point),
+            UvMapping::Cylindrical => cylindrical_map(point),
+            UvMapping::Cubic => CubeFace::from_point(point).map(point),
+        }
+    }
+}
+
+fn spherical_map(point: DVec3) -> DVec2 {
+    // azimuthal angle, from -pi to pi
+    let theta = f64::atan2(point.x, point.z);
+    let radius = point.length();
+    // polar angle, from 0 to pi
+    let phi = f64::acos(point.y / radius);
+    let raw_u = theta / (2.0 * PI);
+    // flipped so u grows counterclockwise when viewed from above
+    dvec2(1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+}
+
+fn planar_map(point: DVec3) -> DVec2 {
+    dvec2(point.x.rem_euclid(1.0), point.z.rem_euclid(1.0))
+}
+
+fn cylindrical_map(point: DVec3) -> DVec2 {
+    let theta = f64::atan2(point.x, point.z);
+    let raw_u = theta / (2.0 * PI);
+    dvec2(1.0 - (raw_u + 0.5), point.y.rem_euclid(1.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+impl CubeFace {
+    /// the face of the unit cube the point belongs to
+    pub fn from_point(point: DVec3) -> Self {
+        let coord = point.abs().max_element();
+        if coord == point.x {
+            CubeFace::Right
+        } else if coord == -point.x {
+            CubeFace::Left
+        } else if coord == point.y {
+            CubeFace::Up
+        } else if coord == -point.y {
+            CubeFace::Down
+        } else if coord == point.z {
+            CubeFace::Front
+        } else {
+            CubeFace::Back
+        }
+    }
+
+    pub fn map(&self, point: DVec3) -> DVec2 {
+        let (u, v) = match self {
+            CubeFace::Front => (point.x + 1.0, point.y + 1.0),
+            CubeFace::Back => (1.0 - point.x, point.y + 1.0),
+            CubeFace::Left => (point.z + 1.0, point.y + 1.0),
+            CubeFace::Right => (1.0 - point.z, point.y + 1.0),
+            CubeFace::Up => (point.x + 1.0, 1.0 - point.z),
+            CubeFace::Down => (point.x + 1.0, point.z + 1.0),
+        };
+        dvec2(u.rem_euclid(2.0) / 2.0, v.rem_euclid(2.0) / 2.0)
+    }
+}
+
+/// pattern defined on the (u, v) unit square
+#[derive(Debug, Clone, PartialEq)]
+pub enum UvPattern {
+    Checkers(UvCheckers),
+    AlignCheck(UvAlignCheck),
+    Image(UvImage),
+}
+
+pub trait UvPatternFunc {
+    fn uv_color_at(&self, uv: DVec2) -> Color;
+}
+
+impl UvPatternFunc for UvPattern {
+    fn uv_color_at(&self, uv: DVec2) -> Color {
+        match self {
+            UvPattern::Checkers(p) => p.uv_color_at(uv),
+            UvPattern::AlignCheck(p) => p.uv_color_at(uv),
+            UvPattern::Image(p) => p.uv_color_at(uv),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvCheckers {
+    width: f64,
+    height: f64,
+    c0: Color,
+    c1: Color,
+}
+
+impl UvCheckers {
+    /// width and height are the number of squares along u and v
+    pub fn new(width: f64, height: f64, c0: Color, c1: Color) -> Self {
+        Self {
+            width, height, c0, c1
+        }
+    }
+}
+
+impl UvPatternFunc for UvCheckers {
+    fn uv_color_at(&self, uv: DVec2) -> Color {
+        let u = (uv.x * self.width).floor();
+        let v = (uv.y * self.height).floor();
+        if (u + v).rem_euclid(2.0) == 0.0 {
+            self.c0
+        } else {
+            self.c1
+        }
+    }
+}
+
+/// a main color with a different color in each corner, used to check the
+/// orientation of a mapping
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvAlignCheck {
+    main: Color,
+    ul: Color,
+    ur: Color,
+    bl: Color,
+    br: Color,
+}
+
+impl UvAlignCheck {
+    pub fn new(main: Color, ul: Color, ur: Color, bl: Color, br: Color) -> Self {
+        Self {
+            main, ul, ur, bl, br
+        }
+    }
+}
+
+impl UvPatternFunc for UvAlignCheck {
+    fn uv_color_at(&self, uv: DVec2) -> Color {
+        if uv.y > 0.8 {
+            if uv.x < 0.2 {
+                return self.ul;
+            }
+            if uv.x > 0.8 {
+                return self.ur;
+            }
+        } else if uv.y < 0.2 {
+            if uv.x < 0.2 {
+                return self.bl;
+            }
+            if uv.x > 0.8 {
+                return self.br;
+            }
+        }
+        self.main
+    }
+}
+
+/// how the color between the pixels of an image is reconstructed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    Nearest,
+    Bilinear,
+}
+
+/// how the pixels outside of an image are looked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureWrap {
+    Repeat,
+    Clamp,
+}
+
+impl TextureWrap {
+    fn apply(&self, index: i64, size: usize) -> usize {
+        match self {
+            TextureWrap::Repeat => index.rem_euclid(size as i64) as usize,
+            TextureWrap::Clamp => index.clamp(0, size as i64 - 1) as usize,
+        }
+    }
+}
+
+/// canvas stretched over the unit square, v goes up while canvas rows go down
+#[derive(Debug, Clone, PartialEq)]
+pub struct UvImage {
+    canvas: Arc<Canvas>,
+    filter: TextureFilter,
+    wrap: TextureWrap,
+}
+
+impl UvImage {
+    /// none for an image without pixels
+    pub fn new(canvas: Canvas) -> Option<Self> {
+        if canvas.width() == 0 || canvas.height() == 0 {
+            return None;
+        }
+        Some(Self {
+            canvas: Arc::new(canvas),
+            filter: TextureFilter::Nearest,
+            wrap: TextureWrap::Repeat,
+        })
+    }
+
+    /// loads a PNG or JPEG file, srgb decodes the colors to the linear space
+    pub fn load<P: AsRef<Path>>(path: P, srgb: bool) -> image::ImageResult<Self> {
+        let img = image::open(path)?.into_rgb32f();
+        let mut canvas = Canvas::new(img.width() as usize, img.height() as usize);
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let color = Color::new(pixel[0] as f64, pixel[1] as f64, pixel[2] as f64);
+            canvas[y as usize][x as usize] = match srgb {
+                true => color.srgb_to_linear(),
+                false => color,
+            };
+        }
+        Self::new(canvas).ok_or_else(|| image::ImageError::Parameter(
+            image::error::ParameterError::from_kind(image::error::ParameterErrorKind::DimensionMismatch)
+        ))
+    }
+
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: TextureWrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        self.filter
+    }
+
+    pub fn wrap(&self) -> TextureWrap {
+        self.wrap
+    }
+
+    fn pixel_at(&self, x: i64, y: i64) -> Color {
+        let x = self.wrap.apply(x, self.canvas.width());
+        let y = self.wrap.apply(y, self.canvas.height());
+        self.canvas[y][x]
+    }
+}
+
+impl UvPatternFunc for UvImage {
+    fn uv_color_at(&self, uv: DVec2) -> Color {
+        // coordinates relative to the centers of the pixels, so that a
+        // repeated image tiles without seams
+        let x = uv.x * self.canvas.width() as f64 - 0.5;
+        let y = (1.0 - uv.y) * self.canvas.height() as f64 - 0.5;
+        match self.filter {
+            TextureFilter::Nearest => self.pixel_at((x + 0.5).floor() as i64, (y + 0.5).floor() as i64),
+            TextureFilter::Bilinear => {
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = self.pixel_at(x0, y0) * (1.0 - tx) + self.pixel_at(x0 + 1, y0) * tx;
+                let bottom = self.pixel_at(x0, y0 + 1) * (1.0 - tx) + self.pixel_at(x0 + 1, y0 + 1) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+        }
+    }
+}
+
+/// uv pattern projected on the pattern space by a mapping
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureMapPattern {
+    pattern: UvPattern,
+    mapping: UvMapping,
+}
+
+impl TextureMapPattern {
+    pub fn new(pattern: UvPattern, mapping: UvMapping) -> Self {
+        Self {
+            pattern, mapping
+        }
+    }
+
+    pub fn pattern(&self) -> &UvPattern {
+        &self.pattern
+    }
+
+    pub fn mapping(&self) -> UvMapping {
+        self.mapping
+    }
+}
+
+impl PatternFunc for TextureMapPattern {
+    fn color_at(&self, point: DVec3) -> Color {
+        self.pattern.uv_color_at(self.mapping.map(point))
+    }
+}
+
+/// one uv pattern for each face of the unit cube
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeMapPattern {
+    // ordered like the CubeFace variants
+    faces: Box<[UvPattern; 6]>,
+}
+
+impl CubeMapPattern {
+    pub fn new(left: UvPattern, front: UvPattern, right: UvPattern, back: UvPattern, up: UvPattern, down: UvPattern) -> Self {
+        Self {
+            faces: Box::new([left, front, right, back, up, down]),
+        }
+    }
+
+    pub fn face(&self, face: CubeFace) -> &UvPattern {
+        &self.faces[face as usize]
+    }
+}
+
+impl PatternFunc for CubeMapPattern {
+    fn color_at(&self, point: DVec3) -> Color {
+        let face = CubeFace::from_point(point);
+        self.face(face).uv_color_at(face.map(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec3;
+
+    use super::*;
+
+    const EPSILON: f64 = 0.0001;
+
+    fn align_check(main: Color, ul: Color, ur: Color, bl: Color, br: Color) -> UvPattern {
+        UvPattern::AlignCheck(UvAlignCheck::new(main, ul, ur, bl, br))
+    }
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let checkers = UvCheckers::new(2.0, 2.0, Color::black(), Color::white());
+        let datas = vec![
+            (0.0, 0.0, Color::black()),
+            (0.5, 0.0, Color::white()),
+            (0.0, 0.5, Color::white()),
+            (0.5, 0.5, Color::black()),
+            (1.0, 1.0, Color::black()),
+        ];
+
+        for data in datas {
+            assert_eq!(checkers.uv_color_at(dvec2(data.0, data.1)), data.2);
+        }
+    }
+
+    #[test]
+    fn using_a_spherical_mapping_on_a_3d_point() {
+        let datas = vec![
+            (dvec3(0.0, 0.0, -1.0), dvec2(0.0, 0.5)),
+            (dvec3(1.0, 0.0, 0.0), dvec2(0.25, 0.5)),
+            (dvec3(0.0, 0.0, 1.0), dvec2(0.5, 0.5)),
+            (dvec3(-1.0, 0.0, 0.0), dvec2(0.75, 0.5)),
+            (dvec3(0.0, 1.0, 0.0), dvec2(0.5, 1.0)),
+            (dvec3(0.0, -1.0, 0.0), dvec2(0.5, 0.0)),
+            (dvec3(f64::sqrt(2.0) / 2.0, f64::sqrt(2.0) / 2.0, 0.0), dvec2(0.25, 0.75)),
+        ];
+
+        for data in datas {
+            assert!(UvMapping::Spherical.map(data.0).abs_diff_eq(data.1, EPSILON));
+        }
+    }
+
+    #[test]
+    fn using_a_texture_map_pattern_with_a_spherical_map() {
+        let pattern = TextureMapPattern::new(
+            UvPattern::Checkers(UvCheckers::new(16.0, 8.0, Color::black(), Color::white())),
+            UvMapping::Spherical
+        );
+        let datas = vec![
+            (dvec3(0.4315, 0.4670, 0.7719), Color::white()),
+            (dvec3(-0.9654, 0.2552, -0.0534), Color::black()),
+            (dvec3(0.1039, 0.7090, 0.6975), Color::white()),
+            (dvec3(-0.4986, -0.7856, -0.3663), Color::black()),
+            (dvec3(-0.0317, -0.9395, 0.3411), Color::black()),
+            (dvec3(0.4809, -0.7721, 0.4154), Color::black()),
+            (dvec3(0.0285, -0.9612, -0.2745), Color::black()),
+            (dvec3(-0.5734, -0.2162, -0.7903), Color::white()),
+            (dvec3(0.7688, -0.1470, 0.6223), Color::black()),
+            (dvec3(-0.7652, 0.2175, 0.6060), Color::black()),
+        ];
+
+        for data in datas {
+            assert_eq!(pattern.color_at(data.0), data.1);
+        }
+    }
+
+    #[test]
+    fn using_a_planar_mapping_on_a_3d_point() {
+        let datas = vec![
+            (dvec3(0.25, 0.0, 0.5), dvec2(0.25, 0.5)),
+            (dvec3(0.25, 0.0, -0.25), dvec2(0.25, 0.75)),
+            (dvec3(0.25, 0.5, -0.25), dvec2(0.25, 0.75)),
+            (dvec3(1.25, 0.0, 0.5), dvec2(0.25, 0.5)),
+            (dvec3(0.25, 0.0, -1.75), dvec2(0.25, 0.25)),
+            (dvec3(1.0, 0.0, -1.0), dvec2(0.0, 0.0)),
+            (dvec3(0.0, 0.0, 0.0), dvec2(0.0, 0.0)),
+        ];
+
+        for data in datas {
+            assert!(UvMapping::Planar.map(data.0).abs_diff_eq(data.1, EPSILON));
+        }
+    }
+
+    #[test]
+    fn using_a_cylindrical_mapping_on_a_3d_point() {
+        let datas = vec![
+            (dvec3(0.0, 0.0, -1.0), dvec2(0.0, 0.0)),
+            (dvec3(0.0, 0.5, -1.0), dvec2(0.0, 0.5)),
+            (dvec3(0.0, 1.0, -1.0), dvec2(0.0, 0.0)),
+            (dvec3(0.70711, 0.5, -0.70711), dvec2(0.125, 0.5)),
+            (dvec3(1.0, 0.5, 0.0), dvec2(0.25, 0.5)),
+            (dvec3(0.70711, 0.5, 0.70711), dvec2(0.375, 0.5)),
+            (dvec3(0.0, -0.25, 1.0), dvec2(0.5, 0.75)),
+            (dvec3(-0.70711, 0.5, 0.70711), dvec2(0.625, 0.5)),
+            (dvec3(-1.0, 1.25, 0.0), dvec2(0.75, 0.25)),
+            (dvec3(-0.70711, 0.5, -0.70711), dvec2(0.875, 0.5)),
+        ];
+
+        for data in datas {
+            assert!(UvMapping::Cylindrical.map(data.0).abs_diff_eq(data.1, EPSILON));
+        }
+    }
+
+    #[test]
+    fn layout_of_the_align_check_pattern() {
+        let main = Color::white();
+        let ul = Color::red();
+        let ur = Color::new(1.0, 1.0, 0.0);
+        let bl = Color::green();
+        let br = Color::new(0.0, 1.0, 1.0);
+        let pattern = align_check(main, ul, ur, bl, br);
+        let datas = vec![
+            (0.5, 0.5, main),
+            (0.1, 0.9, ul),
+            (0.9, 0.9, ur),
+            (0.1, 0.1, bl),
+            (0.9, 0.1, br),
+        ];
+
+        for data in datas {
+            assert_eq!(pattern.uv_color_at(dvec2(data.0, data.1)), data.2);
+        }
+    }
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        let datas = vec![
+            (dvec3(-1.0, 0.5, -0.25), CubeFace::Left),
+            (dvec3(1.1, -0.75, 0.8), CubeFace::Right),
+            (dvec3(0.1, 0.6, 0.9), CubeFace::Front),
+            (dvec3(-0.7, 0.0, -2.0), CubeFace::Back),
+            (dvec3(0.5, 1.0, 0.9), CubeFace::Up),
+            (dvec3(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+
+        for data in datas {
+            assert_eq!(CubeFace::from_point(data.0), data.1);
+        }
+    }
+
+    #[test]
+    fn uv_mapping_the_faces_of_a_cube() {
+        let datas = vec![
+            (CubeFace::Front, dvec3(-0.5, 0.5, 1.0), dvec2(0.25, 0.75)),
+            (CubeFace::Front, dvec3(0.5, -0.5, 1.0), dvec2(0.75, 0.25)),
+            (CubeFace::Back, dvec3(0.5, 0.5, -1.0), dvec2(0.25, 0.75)),
+            (CubeFace::Back, dvec3(-0.5, -0.5, -1.0), dvec2(0.75, 0.25)),
+            (CubeFace::Left, dvec3(-1.0, 0.5, -0.5), dvec2(0.25, 0.75)),
+            (CubeFace::Left, dvec3(-1.0, -0.5, 0.5), dvec2(0.75, 0.25)),
+            (CubeFace::Right, dvec3(1.0, 0.5, 0.5), dvec2(0.25, 0.75)),
+            (CubeFace::Right, dvec3(1.0, -0.5, -0.5), dvec2(0.75, 0.25)),
+            (CubeFace::Up, dvec3(-0.5, 1.0, -0.5), dvec2(0.25, 0.75)),
+            (CubeFace::Up, dvec3(0.5, 1.0, 0.5), dvec2(0.75, 0.25)),
+            (CubeFace::Down, dvec3(-0.5, -1.0, 0.5), dvec2(0.25, 0.75)),
+            (CubeFace::Down, dvec3(0.5, -1.0, -0.5), dvec2(0.75, 0.25)),
+        ];
+
+        for data in datas {
+            assert!(data.0.map(data.1).abs_diff_eq(data.2, EPSILON));
+        }
+    }
+
+    #[test]
+    fn finding_the_colors_on_a_mapped_cube() {
+        let red = Color::red();
+        let yellow = Color::new(1.0, 1.0, 0.0);
+        let brown = Color::new(1.0, 0.5, 0.0);
+        let green = Color::green();
+        let cyan = Color::new(0.0, 1.0, 1.0);
+        let blue = Color::blue();
+        let purple = Color::new(1.0, 0.0, 1.0);
+        let white = Color::white();
+        let pattern = CubeMapPattern::new(
+            align_check(yellow, cyan, red, blue, brown),
+            align_check(cyan, red, yellow, brown, green),
+            align_check(red, yellow, purple, green, white),
+            align_check(green, purple, cyan, white, blue),
+            align_check(brown, cyan, purple, red, yellow),
+            align_check(purple, brown, green, blue, white),
+        );
+        let datas = vec![
+            (dvec3(-1.0, 0.0, 0.0), yellow),
+            (dvec3(-1.0, 0.9, -0.9), cyan),
+            (dvec3(-1.0, 0.9, 0.9), red),
+            (dvec3(-1.0, -0.9, -0.9), blue),
+            (dvec3(-1.0, -0.9, 0.9), brown),
+            (dvec3(0.0, 0.0, 1.0), cyan),
+            (dvec3(-0.9, 0.9, 1.0), red),
+            (dvec3(0.9, 0.9, 1.0), yellow),
+            (dvec3(-0.9, -0.9, 1.0), brown),
+            (dvec3(0.9, -0.9, 1.0), green),
+            (dvec3(1.0, 0.0, 0.0), red),
+            (dvec3(1.0, 0.9, 0.9), yellow),
+            (dvec3(1.0, 0.9, -0.9), purple),
+            (dvec3(0.0, 0.0, -1.0), green),
+            (dvec3(0.9, 0.9, -1.0), purple),
+            (dvec3(0.0, 1.0, 0.0), brown),
+            (dvec3(-0.9, 1.0, -0.9), cyan),
+            (dvec3(0.9, 1.0, 0.9), yellow),
+            (dvec3(0.0, -1.0, 0.0), purple),
+            (dvec3(-0.9, -1.0, 0.9), brown),
+            (dvec3(0.9, -1.0, -0.9), white),
+        ];
+
+        for data in datas {
+            assert_eq!(pattern.color_at(data.0), data.1);
+        }
+    }
+
+    #[test]
+    fn a_cubic_mapping_projects_the_same_pattern_on_each_face() {
+        let pattern = TextureMapPattern::new(
+            align_check(Color::white(), Color::red(), Color::green(), Color::blue(), Color::black()),
+            UvMapping::Cubic
+        );
+        assert_eq!(pattern.color_at(dvec3(-0.9, 0.9, 1.0)), Color::red());
+        assert_eq!(pattern.color_at(dvec3(0.9, 0.9, -1.0)), Color::red());
+        assert_eq!(pattern.color_at(dvec3(0.9, -1.0, -0.9)), Color::black());
+    }
+
+    #[test]
+    fn an_image_pattern_looks_up_the_nearest_pixel() {
+        let mut canvas = Canvas::new(10, 10);
+        for y in 0..10 {
+            for x in 0..10 {
+                let value = (x + y) as f64 / 18.0;
+                canvas[y][x] = Color::new(value, value, value);
+            }
+        }
+        let pattern = UvImage::new(canvas).unwrap();
+        let datas = vec![
+            (0.05, 0.05, 0.5),
+            (0.35, 0.05, 0.66667),
+            (0.6, 0.3, 0.72222),
+            (0.95, 0.95, 0.5),
+            (0.0, 1.0, 0.0),
+            (0.95, 0.05, 1.0),
+            // the right edge is the left edge of the next image
+            (1.0, 0.55, 0.22222),
+        ];
+
+        for data in datas {
+            let value = data.2;
+            assert_eq!(pattern.uv_color_at(dvec2(data.0, data.1)), Color::new(value, value, value));
+        }
+    }
+
+    fn gradient_canvas() -> Canvas {
+        let mut canvas = Canvas::new(10, 10);
+        for y in 0..10 {
+            for x in 0..10 {
+                let value = (x + y) as f64 / 18.0;
+                canvas[y][x] = Color::new(value, value, value);
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn a_bilinear_image_pattern_blends_the_surrounding_pixels() {
+        let pattern = UvImage::new(gradient_canvas()).unwrap()
+            .with_filter(TextureFilter::Bilinear);
+        // x = 2.5, y = 6.5
+        let value = (2.5 + 6.5) / 18.0;
+        assert_eq!(pattern.uv_color_at(dvec2(0.3, 0.3)), Color::new(value, value, value));
+        assert_eq!(pattern.uv_color_at(dvec2(0.05, 0.95)), Color::black());
+        // the corner blends the pixels of the four corners of the repeated image
+        let value = (0.0 + 9.0 + 9.0 + 18.0) / 4.0 / 18.0;
+        assert_eq!(pattern.uv_color_at(dvec2(0.0, 1.0)), Color::new(value, value, value));
+    }
+
+    #[test]
+    fn wrapping_an_image_pattern_outside_of_the_unit_square() {
+        let repeat = UvImage::new(gradient_canvas()).unwrap();
+        let clamp = UvImage::new(gradient_canvas()).unwrap()
+            .with_wrap(TextureWrap::Clamp);
+        // x = -10, y = 0
+        let uv = dvec2(-1.0, 1.0);
+        assert_eq!(repeat.uv_color_at(uv), Color::black());
+        assert_eq!(clamp.uv_color_at(uv), Color::black());
+        // x = -3, y = 0
+        let uv = dvec2(-0.25, 0.95);
+        assert_eq!(repeat.uv_color_at(uv), Color::new(7.0 / 18.0, 7.0 / 18.0, 7.0 / 18.0));
+        assert_eq!(clamp.uv_color_at(uv), Color::black());
+    }
+
+    #[test]
+    fn an_image_pattern_needs_pixels() {
+        assert!(UvImage::new(Canvas::new(0, 10)).is_none());
+        assert!(UvImage::new(Canvas::new(10, 0)).is_none());
+    }
+
+    #[test]
+    fn loading_an_image_pattern_from_a_file() {
+        let path = std::env::temp_dir().join(format!("ray_tracer_uv_image_test_{}.png", std::process::id()));
+        let mut img = image::RgbImage::new(2, 1);
+        img.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        img.put_pixel(1, 0, image::Rgb([128, 128, 128]));
+        img.save(&path).unwrap();
+
+        let srgb = UvImage::load(&path, true).unwrap();
+        let raw = UvImage::load(&path, false).unwrap();
+        assert_eq!(srgb.canvas().width(), 2);
+        assert_eq!(srgb.canvas().height(), 1);
+        assert_eq!(srgb.uv_color_at(dvec2(0.0, 0.0)), Color::red());
+        assert_eq!(srgb.uv_color_at(dvec2(0.75, 0.0)), Color::new(0.21586, 0.21586, 0.21586));
+        assert_eq!(raw.uv_color_at(dvec2(0.75, 0.0)), Color::new(0.50196, 0.50196, 0.50196));
+
+        assert!(UvImage::load(std::env::temp_dir().join("ray_tracer_missing.png"), true).is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,107 @@
+/// rectangle of pixels of an image, rendered as a unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Tile {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// tiles of size x size pixels covering the image row after row, the
+    /// last tiles of a row or a column are cut at the border of the image
+    pub fn split(width: usize, height: usize, size: usize) -> Vec<Tile> {
+        Tile::new(0, 0, width, height).subdivide(size)
+    }
+
+    /// same as split, within the tile
+    pub fn subdivide(&self, size: usize) -> Vec<Tile> {
+        let size = size.max(1);
+        (self.y..self.y + self.height)
+            .step_by(size)
+            .flat_map(|y| {
+                (self.x..self.x + self.width)
+                    .step_by(size)
+                    .map(move |x| Tile::new(x, y, size.min(self.x + self.width - x), size.min(self.y + self.height - y)))
+            })
+            .collect()
+    }
+
+    /// the pixels in both tiles, none when they don't overlap
+    pub fn intersection(&self, other: &Tile) -> Option<Tile> {
+        let (x0, y0) = (self.x.max(other.x), self.y.max(other.y));
+        let x1 = (self.x + self.width).min(other.x + other.width);
+        let y1 = (self.y + self.height).min(other.y + other.height);
+        (x0 < x1 && y0 < y1).then(|| Tile::new(x0, y0, x1 - x0, y1 - y0))
+    }
+
+    /// the tile with a margin of pixels around it, kept within the bounds
+    pub fn grown(&self, margin: usize, bounds: &Tile) -> Tile {
+        let (x0, y0) = (self.x.saturating_sub(margin), self.y.saturating_sub(margin));
+        let grown = Tile::new(x0, y0, self.x + self.width + margin - x0, self.y + self.height + margin - y0);
+        grown.intersection(bounds).unwrap_or(*self)
+    }
+
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// coordinates of the pixels in the image, row after row
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize)> {
+        let Tile { x, y, width, height } = *self;
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splitting_an_image_into_tiles() {
+        let tiles = Tile::split(5, 3, 2);
+        assert_eq!(tiles, vec![
+            Tile::new(0, 0, 2, 2),
+            Tile::new(2, 0, 2, 2),
+            Tile::new(4, 0, 1, 2),
+            Tile::new(0, 2, 2, 1),
+            Tile::new(2, 2, 2, 1),
+            Tile::new(4, 2, 1, 1),
+        ]);
+        assert_eq!(tiles.iter().map(Tile::area).sum::<usize>(), 15);
+    }
+
+    #[test]
+    fn the_pixels_of_a_tile() {
+        let tile = Tile::new(4, 2, 2, 2);
+        assert_eq!(tile.pixels().collect::<Vec<(usize, usize)>>(), vec![(4, 2), (5, 2), (4, 3), (5, 3)]);
+        assert!(tile.contains(5, 3));
+        assert!(!tile.contains(6, 3));
+        assert!(!tile.contains(4, 1));
+    }
+
+    #[test]
+    fn subdividing_a_region_of_an_image() {
+        let tiles = Tile::new(3, 1, 3, 2).subdivide(2);
+        assert_eq!(tiles, vec![Tile::new(3, 1, 2, 2), Tile::new(5, 1, 1, 2)]);
+    }
+
+    #[test]
+    fn the_intersection_of_tiles() {
+        let tile = Tile::new(2, 2, 4, 4);
+        assert_eq!(tile.intersection(&Tile::new(4, 0, 10, 3)), Some(Tile::new(4, 2, 2, 1)));
+        assert_eq!(tile.intersection(&Tile::new(6, 2, 1, 1)), None);
+        assert_eq!(
+            tile.grown(3, &Tile::new(0, 0, 8, 20)),
+            Tile::new(0, 0, 8, 9)
+        );
+    }
+}
\ No newline at end of file
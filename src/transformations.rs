@@ -74,6 +74,115 @@ impl Transform {
     }
 }
 
+/// transformation changing over time, given by keyframes sorted by time.
+/// the transforms of the parents are kept apart from the keyframes, their
+/// product may be sheared and could not be interpolated
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Motion {
+    keyframes: Vec<(f64, Transform)>,
+    outer: Transform,
+}
+
+impl Motion {
+    /// moves from start at time 0 to end at time 1
+    pub fn new(start: Transform, end: Transform) -> Self {
+        Self::from_keyframes(vec![(0.0, start), (1.0, end)])
+    }
+
+    pub fn from_keyframes(mut keyframes: Vec<(f64, Transform)>) -> Self {
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { keyframes, outer: Transform::identity() }
+    }
+
+    pub fn with_keyframe(mut self, time: f64, transform: Transform) -> Self {
+        self.keyframes.push((time, transform));
+        self.keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self
+    }
+
+    /// the keyframes without the transform applied after them
+    pub fn keyframes(&self) -> &[(f64, Transform)] {
+        &self.keyframes
+    }
+
+    /// the transform applied after the keyframes
+    pub fn outer(&self) -> &Transform {
+        &self.outer
+    }
+
+    /// the interpolated keyframes followed by the outer transform
+    pub fn transform_at(&self, time: f64) -> Transform {
+        self.local_transform_at(time).apply(self.outer.clone())
+    }
+
+    /// scale and translation are interpolated linearly and rotation spherically
+    /// between the surrounding keyframes, the first and last keyframes hold
+    /// before and after them
+    pub(crate) fn local_transform_at(&self, time: f64) -> Transform {
+        let next = self.keyframes.iter().position(|(t, _)| *t > time);
+        match next {
+            None => self.keyframes.last().map(|k| k.1.clone()).unwrap_or_default(),
+            Some(0) => self.keyframes[0].1.clone(),
+            Some(index) => {
+                let (t0, start) = &self.keyframes[index - 1];
+                let (t1, end) = &self.keyframes[index];
+                let ratio = (time - t0) / (t1 - t0);
+                if ratio == 0.0 {
+                    return start.clone();
+                }
+                let (s0, r0, p0) = start.matrix.to_scale_rotation_translation();
+                let (s1, r1, p1) = end.matrix.to_scale_rotation_translation();
+                Transform::from_matrix(DMat4::from_scale_rotation_translation(
+                    s0.lerp(s1, ratio),
+                    r0.slerp(r1, ratio),
+                    p0.lerp(p1, ratio)
+                ))
+            }
+        }
+    }
+
+    /// applies the transform after every keyframe
+    pub fn apply(self, transform: &Transform) -> Self {
+        Self {
+            outer: self.outer.apply(transform.clone()),
+            ..self
+        }
+    }
+
+    /// combines this motion followed by the outer one, keeping the keyframes of both
+    pub fn then(&self, outer: &Motion) -> Self {
+        let mut times: Vec<f64> = self.keyframes
+            .iter()
+            .chain(outer.keyframes.iter())
+            .map(|(time, _)| *time)
+            .collect();
+        times.sort_by(f64::total_cmp);
+        times.dedup();
+
+        Self {
+            keyframes: times
+                .into_iter()
+                .map(|time| (time, self.transform_at(time).apply(outer.local_transform_at(time))))
+                .collect(),
+            outer: outer.outer.clone(),
+        }
+    }
+
+    /// the keyframe times with steps instants evenly spread between each pair of them
+    pub fn sample_times(&self, steps: usize) -> Vec<f64> {
+        let mut times = Vec::with_capacity(self.keyframes.len() * (steps + 1));
+        for (index, (time, _)) in self.keyframes.iter().enumerate() {
+            times.push(*time);
+            if let Some((next, _)) = self.keyframes.get(index + 1) {
+                for step in 1..=steps {
+                    times.push(time + (next - time) * step as f64 / (steps + 1) as f64);
+                }
+            }
+        }
+        times
+    }
+}
+
 pub struct TransformBuilder<T> {
     transform: Transform,
     object: T,
@@ -134,6 +243,8 @@ pub fn view_transform(from: DVec3, to: DVec3, up: DVec3)-> DMat4 {
 
 #[cfg(test)]
 mod tests {
+    use std::f64::consts::PI;
+
     use glam::{dvec3, dvec4};
 
     use crate::{Object, shapes::{Sphere, Shape}};
@@ -153,6 +264,97 @@ mod tests {
         assert_eq!(o.transform().translation(), dvec3(2.0, 2.0, 2.0));
     }
 
+    #[test]
+    fn a_motion_interpolates_between_its_keyframes() {
+        let m = Motion::new(
+            Transform::new().with_translation(0.0, 0.0, 0.0),
+            Transform::new().with_translation(2.0, 4.0, 0.0)
+        );
+        assert!(m.transform_at(0.5).translation().abs_diff_eq(dvec3(1.0, 2.0, 0.0), EPSILON));
+        assert!(m.transform_at(0.25).translation().abs_diff_eq(dvec3(0.5, 1.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn a_motion_holds_its_first_and_last_keyframes() {
+        let m = Motion::new(
+            Transform::new().with_scale(1.0, 1.0, 1.0),
+            Transform::new().with_scale(3.0, 3.0, 3.0)
+        );
+        assert!(m.transform_at(-1.0).scale().abs_diff_eq(dvec3(1.0, 1.0, 1.0), EPSILON));
+        assert!(m.transform_at(2.0).scale().abs_diff_eq(dvec3(3.0, 3.0, 3.0), EPSILON));
+    }
+
+    #[test]
+    fn a_motion_interpolates_rotations() {
+        let m = Motion::new(
+            Transform::new(),
+            Transform::new().with_rotation_z(PI / 2.0)
+        )
+        .with_keyframe(2.0, Transform::new().with_rotation_z(PI));
+        let t = m.transform_at(0.5);
+        let p = t.matrix.transform_point3(dvec3(1.0, 0.0, 0.0));
+        assert!(p.abs_diff_eq(dvec3(f64::sqrt(2.0) / 2.0, f64::sqrt(2.0) / 2.0, 0.0), EPSILON));
+        let t = m.transform_at(1.5);
+        let p = t.matrix.transform_point3(dvec3(1.0, 0.0, 0.0));
+        assert!(p.abs_diff_eq(dvec3(-f64::sqrt(2.0) / 2.0, f64::sqrt(2.0) / 2.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn combining_two_motions() {
+        let inner = Motion::new(
+            Transform::new(),
+            Transform::new().with_translation(2.0, 0.0, 0.0)
+        );
+        let outer = Motion::from_keyframes(vec![
+            (0.0, Transform::new()),
+            (0.5, Transform::new().with_translation(0.0, 1.0, 0.0)),
+            (1.0, Transform::new().with_translation(0.0, 2.0, 0.0)),
+        ]);
+        let m = inner.then(&outer);
+        assert_eq!(m.keyframes().len(), 3);
+        assert!(m.transform_at(0.5).translation().abs_diff_eq(dvec3(1.0, 1.0, 0.0), EPSILON));
+        assert!(m.transform_at(1.0).translation().abs_diff_eq(dvec3(2.0, 2.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn a_motion_keeps_the_transform_applied_after_it_apart() {
+        // the scaled rotations are sheared, they could not be interpolated
+        let parent = Transform::new().with_scale(2.0, 1.0, 1.0);
+        let m = Motion::new(
+            Transform::new(),
+            Transform::new().with_rotation_z(PI / 2.0)
+        )
+        .apply(&parent);
+        assert_eq!(m.keyframes()[1].1, Transform::new().with_rotation_z(PI / 2.0));
+        assert_eq!(m.outer(), &parent);
+
+        let p = m.transform_at(0.5).matrix.transform_point3(dvec3(1.0, 0.0, 0.0));
+        assert!(p.abs_diff_eq(dvec3(f64::sqrt(2.0), f64::sqrt(2.0) / 2.0, 0.0), EPSILON));
+        let p = m.transform_at(0.5).matrix.transform_point3(dvec3(0.0, 1.0, 0.0));
+        assert!(p.abs_diff_eq(dvec3(-f64::sqrt(2.0), f64::sqrt(2.0) / 2.0, 0.0), EPSILON));
+    }
+
+    #[test]
+    fn a_motion_is_exactly_its_keyframe_at_the_time_of_it() {
+        let sheared = Transform::from_matrix(DMat4::from_cols_array(&[
+            1.0, 0.0, 0.0, 0.0,
+            1.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ]));
+        let m = Motion::new(sheared.clone(), Transform::new())
+            .with_keyframe(2.0, sheared.clone());
+        assert_eq!(m.transform_at(0.0), sheared);
+        assert_eq!(m.transform_at(2.0), sheared);
+    }
+
+    #[test]
+    fn sampling_the_times_of_a_motion() {
+        let m = Motion::new(Transform::new(), Transform::new())
+            .with_keyframe(2.0, Transform::new());
+        assert_eq!(m.sample_times(1), vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+    }
+
     #[test]
     fn the_transformation_matrix_for_the_default_orientation() {
         let t = view_transform(
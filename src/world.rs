@@ -1,19 +1,15 @@
+use std::sync::OnceLock;
+
 use glam::DVec3;
 
-use crate::{object::Object, ray::Ray, Color, intersection::{Intersections, IntersectionInfos, ShadowHit, StandardHit}, lights::{light::LightSource, Light}};
+use crate::{object::Object, ray::Ray, Color, intersection::{Intersection, Intersections, IntersectionInfos}, lights::{light::LightSource, Light}, bvh::{BvhTree, SahBuilder}, bounds::BoundingBox};
 
+#[derive(Default)]
 pub struct World {
     objects: Vec<Object>,
     lights: Vec<Light>,
-}
-
-impl Default for World {
-    fn default() -> Self {
-        Self { 
-            objects: Vec::default(), 
-            lights: Vec::default() 
-        }
-    }
+    /// hierarchy over the objects, built by the first ray after a change
+    bvh: OnceLock<BvhTree>,
 }
 
 impl World {
@@ -23,6 +19,7 @@ impl World {
 
     pub fn with_objects(mut self, objects: Vec<Object>) -> Self {
         self.objects = objects;
+        self.bvh.take();
         self
     }
 
@@ -32,7 +29,8 @@ impl World {
     }
 
     pub fn push_object(&mut self, object: Object) {
-        self.objects.push(object)
+        self.objects.push(object);
+        self.bvh.take();
     }
 
     pub fn objects(&self) -> &Vec<Object> {
@@ -43,7 +41,9 @@ impl World {
         self.objects.get(index)
     }
 
+    /// the object may move, the hierarchy is rebuilt by the next ray
     pub fn object_mut(&mut self, index: usize) -> Option<&mut Object> {
+        self.bvh.take();
         self.objects.get_mut(index)
     }
 
@@ -52,47 +52,77 @@ impl World {
     }
 
     pub fn color_at(&self, ray: &Ray, remaining: u8) -> Option<Color> {
-        let intersections = self.intersects(ray);
-        match intersections.hit_index(StandardHit {}) {
-            Some(index) => {
-                let infos = IntersectionInfos::new(&intersections, index, &ray);
-                Some(self.shade_hit(&infos, remaining))
-            },
-            None => None
+        let hit = self.closest_hit(ray)?;
+        // n1 and n2 depend on the objects the ray went through before the hit
+        if hit.material().transparency() > 0.0 {
+            let intersections = self.intersects(ray);
+            let index = intersections.index_of(&hit);
+            debug_assert!(index.is_some(), "the closest hit is missing from the intersections of the world");
+            let infos = IntersectionInfos::new(&intersections, index?, ray);
+            return Some(self.shade_hit(&infos, remaining));
         }
-    }
-
-    fn intersects(&self, ray: &Ray) -> Intersections {
+        let infos = IntersectionInfos::from_hit(&hit, ray);
+        Some(self.shade_hit(&infos, remaining))
+    }
+
+    /// nearest hit within the range of the ray, nodes farther than the
+    /// closest hit found so far are skipped
+    pub fn closest_hit(&self, ray: &Ray) -> Option<Intersection<'_>> {
+        let mut range = *ray;
+        let mut closest = None;
+        self.bvh().traverse(ray, ray.t_max, |index, t_max| {
+            range.t_max = t_max;
+            if let Some(x) = self.objects[index].closest_hit(&range) {
+                range.t_max = x.t();
+                closest = Some(x);
+            }
+            range.t_max
+        });
+        closest
+    }
+
+    fn intersects(&self, ray: &Ray) -> Intersections<'_> {
         let mut intersections = Intersections::new();
-        for object in &self.objects {
+        self.bvh().traverse(ray, f64::INFINITY, |index, t_max| {
             intersections
                 .append(
-                    object.intersect(ray)
+                    self.objects[index].intersect(ray)
                 );
-        }
+            t_max
+        });
 
         intersections.sort()
     }
 
+    /// unbounded objects like planes are kept out of the nodes
+    fn bvh(&self) -> &BvhTree {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<BoundingBox> = self.objects.iter().map(|o| *o.bounding_box()).collect();
+            BvhTree::new(&bounds, &SahBuilder::new().into())
+        })
+    }
+
     fn shade_hit(&self, infos: &IntersectionInfos, remaining: u8) -> Color {
         let mut color = Color::black();
+        // patterns follow a moving or instanced object
+        let object = infos.shading_object();
         for light in &self.lights {
-            color += infos.object
+            color += object
                 .material()
                 .lighting(
-                    infos.object,
-                    &light, 
+                    &object,
+                    light, 
                     infos.over_point, 
                     infos.eyev, 
                     infos.normalv,
-                    light.intensity_at(infos.over_point, self)
+                    light.intensity_at(infos.over_point, infos.time, self)
                 );
         }
 
         let reflected = self.reflected_color(infos, remaining);
         let refracted = self.refracted_color(infos, remaining);
 
-        let material = infos.object.material();
+        let material = infos.material;
         if material.reflective() > 0.0 && material.transparency() > 0.0 {
             let reflectance = infos.schlick();
             color + reflected * reflectance + refracted * (1.0 - reflectance)
@@ -101,21 +131,23 @@ impl World {
         }
     }
 
-    pub fn is_shadowed(&self, world_point: DVec3, light_pos: DVec3) -> bool {
+    /// time is the instant of the shadow ray, moving objects cast their shadow
+    /// where they are at that time
+    pub fn is_shadowed(&self, world_point: DVec3, light_pos: DVec3, time: f64) -> bool {
         let ray_dir = light_pos - world_point;
         let distance = ray_dir.length();
-        let shadow_ray = Ray {
-            origin: world_point,
-            direction: ray_dir.normalize()
-        };
-        if let Some(hit) = self.intersects(&shadow_ray).hit(ShadowHit {}) {
-            return hit.t() < distance;
-        }
-        false
+        let shadow_ray = Ray::new(world_point, ray_dir.normalize())
+            .with_time(time);
+        self.occluded(&shadow_ray, distance)
+    }
+
+    /// stops at the first object casting a shadow between the ray origin and t_max
+    pub fn occluded(&self, ray: &Ray, t_max: f64) -> bool {
+        self.bvh().any_hit(ray, t_max, |index| self.objects[index].occluded(ray, t_max))
     }
 
     fn reflected_color(&self, infos: &IntersectionInfos, remaining: u8) -> Color {
-        let reflective = infos.object.material().reflective();
+        let reflective = infos.material.reflective();
         if remaining < 1 || reflective == 0.0 {
             return Color::black();
         }
@@ -124,13 +156,14 @@ impl World {
             &Ray::new(
                 infos.over_point, 
                 infos.reflectv
-            ),
+            )
+            .with_time(infos.time),
             remaining - 1
         ).unwrap_or_default() * reflective
     }
 
     fn refracted_color(&self, infos: &IntersectionInfos, remaining: u8) -> Color {
-        let transparency = infos.object.material().transparency();
+        let transparency = infos.material.transparency();
         if remaining < 1 || transparency == 0.0 {
             return Color::black();
         }
@@ -149,7 +182,8 @@ impl World {
         let refracted_ray = Ray::new(
             infos.under_point,
             infos.normalv * (ratio * cos_i - cos_t) - infos.eyev * ratio
-        );
+        )
+        .with_time(infos.time);
 
         self.color_at(&refracted_ray, remaining - 1).unwrap_or_default() * transparency
     }
@@ -157,9 +191,11 @@ impl World {
 
 #[cfg(test)]
 pub mod tests {
+    use std::sync::Arc;
+
     use glam::{DVec3, dvec3};
 
-    use crate::{shapes::{Sphere, Shape, Plane}, Material, intersection::{Intersection, IntersectionInfos}, Pattern, pattern::{PlainPattern, PatternObject, TestPattern}, lights::PointLight};
+    use crate::{shapes::{Sphere, Shape, Plane, Group, Instance}, Material, intersection::{Intersection, IntersectionInfos}, Pattern, pattern::{PlainPattern, PatternObject, TestPattern}, lights::PointLight, transformations::{Motion, Transform}};
 
     use super::*;
 
@@ -243,6 +279,22 @@ pub mod tests {
         assert_eq!(xs.get(3).unwrap().t(), 6.0);
     }
 
+    #[test]
+    fn the_closest_hit_of_a_world_is_within_the_range_of_the_ray() {
+        let w = default_world();
+        let r = Ray::new(
+            dvec3(0.0, 0.0, -5.0),
+            dvec3(0.0, 0.0, 1.0)
+        );
+        let hit = w.closest_hit(&r).unwrap();
+        assert_eq!(hit.t(), 4.0);
+        assert!(std::ptr::eq(hit.object(), &w.objects()[0]));
+        let hit = w.closest_hit(&r.with_t_min(4.2)).unwrap();
+        assert_eq!(hit.t(), 4.5);
+        assert!(std::ptr::eq(hit.object(), &w.objects()[1]));
+        assert!(w.closest_hit(&r.with_t_max(3.0)).is_none());
+    }
+
     #[test]
     fn shading_an_intersection() {
         let w = default_world();
@@ -250,7 +302,7 @@ pub mod tests {
             dvec3(0.0, 0.0, -5.0),
             dvec3(0.0, 0.0, 1.0)
         );
-        let s = w.objects.get(0).unwrap();
+        let s = w.objects.first().unwrap();
         let i = Intersection::new(4.0, s);
         let xs = Intersections::new().with_intersections(vec![i.clone()]);
         let comps = IntersectionInfos::new(&xs, 0, &r);
@@ -360,25 +412,53 @@ pub mod tests {
     #[test]
     fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
         let w = default_world();
-        assert_eq!(w.is_shadowed(dvec3(0.0, 10.0, 0.0), w.lights[0].positions()[0]), false);
+        assert!(!w.is_shadowed(dvec3(0.0, 10.0, 0.0), w.lights[0].positions()[0], 0.0));
     }
 
     #[test]
     fn the_shadow_when_an_object_is_between_the_point_and_the_light() {
         let w = default_world();
-        assert_eq!(w.is_shadowed(dvec3(10.0, -10.0, 10.0), w.lights[0].positions()[0]), true);
+        assert!(w.is_shadowed(dvec3(10.0, -10.0, 10.0), w.lights[0].positions()[0], 0.0));
     }
 
     #[test]
     fn there_is_no_shadow_when_an_object_is_behind_the_light() {
         let w = default_world();
-        assert_eq!(w.is_shadowed(dvec3(-20.0, 20.0, -20.0), w.lights[0].positions()[0]), false);
+        assert!(!w.is_shadowed(dvec3(-20.0, 20.0, -20.0), w.lights[0].positions()[0], 0.0));
     }
 
     #[test]
     fn there_is_no_shadow_when_an_object_is_behind_the_point() {
         let w = default_world();
-        assert_eq!(w.is_shadowed(dvec3(-2.0, 2.0, -2.0), w.lights[0].positions()[0]), false);
+        assert!(!w.is_shadowed(dvec3(-2.0, 2.0, -2.0), w.lights[0].positions()[0], 0.0));
+    }
+
+    #[test]
+    fn a_moving_object_casts_its_shadow_where_it_is_at_the_time_of_the_ray() {
+        let s = Object::new(Shape::Sphere(Sphere::default()))
+            .with_motion(Motion::new(
+                Transform::new().with_translation(0.0, 5.0, 0.0),
+                Transform::new().with_translation(0.0, -5.0, 0.0)
+            ));
+        let w = World::new()
+            .with_lights(vec![Light::PointLight(PointLight::new(dvec3(-10.0, 0.0, 0.0), Color::white()))])
+            .with_objects(vec![s]);
+        let point = dvec3(10.0, 0.0, 0.0);
+        assert!(!w.is_shadowed(point, w.lights[0].positions()[0], 0.0));
+        assert!(w.is_shadowed(point, w.lights[0].positions()[0], 0.5));
+        assert!(!w.is_shadowed(point, w.lights[0].positions()[0], 1.0));
+    }
+
+    #[test]
+    fn objects_that_cast_no_shadow_do_not_occlude_the_light() {
+        let w = default_world();
+        let r = Ray::new(dvec3(0.0, 0.0, -5.0), DVec3::Z);
+        assert!(w.occluded(&r, 10.0));
+        assert!(!w.occluded(&r, 3.5));
+
+        let objects = w.objects().iter().map(|o| o.clone().with_shadow(false)).collect();
+        let w = w.with_objects(objects);
+        assert!(!w.occluded(&r, 10.0));
     }
 
     #[test]
@@ -416,7 +496,7 @@ pub mod tests {
             dvec3(0.0, 0.0, -3.0),
             dvec3(0.0, -2.0_f64.sqrt()/2.0, 2.0_f64.sqrt()/2.0)
         );
-        let i = Intersection::new(2.0_f64.sqrt(), &o);
+        let i = Intersection::new(2.0_f64.sqrt(), o);
         let xs = Intersections::new().with_intersections(vec![i.clone()]);
         let comps = IntersectionInfos::new(&xs, 0, &r);
         assert_eq!(w.reflected_color(&comps, 5), Color::new(0.19032, 0.2379, 0.14274));
@@ -439,7 +519,7 @@ pub mod tests {
             dvec3(0.0, 0.0, -3.0),
             dvec3(0.0, -2.0_f64.sqrt()/2.0, 2.0_f64.sqrt()/2.0)
         );
-        let i = Intersection::new(2.0_f64.sqrt(), &o);
+        let i = Intersection::new(2.0_f64.sqrt(), o);
         let xs = Intersections::new().with_intersections(vec![i.clone()]);
         let comps = IntersectionInfos::new(&xs, 0, &r);
         assert_eq!(w.shade_hit(&comps, 5), Color::new(0.87677, 0.92436, 0.82918));
@@ -494,7 +574,7 @@ pub mod tests {
             dvec3(0.0, 0.0, -3.0),
             dvec3(0.0, -2.0_f64.sqrt()/2.0, 2.0_f64.sqrt()/2.0)
         );
-        let i = Intersection::new(2.0_f64.sqrt(), &o);
+        let i = Intersection::new(2.0_f64.sqrt(), o);
         let xs = Intersections::new().with_intersections(vec![i.clone()]);
         let comps = IntersectionInfos::new(&xs, 0, &r);
         assert_eq!(w.reflected_color(&comps, 0), Color::black());
@@ -510,8 +590,8 @@ pub mod tests {
         );
         let xs = Intersections::new().with_intersections(
             vec![
-                Intersection::new(4.0, &o),
-                Intersection::new(6.0, &o)
+                Intersection::new(4.0, o),
+                Intersection::new(6.0, o)
             ]
         );
         let comps = IntersectionInfos::new(&xs, 0, &r);
@@ -535,8 +615,8 @@ pub mod tests {
         );
         let xs = Intersections::new().with_intersections(
             vec![
-                Intersection::new(4.0, &o),
-                Intersection::new(6.0, &o)
+                Intersection::new(4.0, o),
+                Intersection::new(6.0, o)
             ]
         );
         let comps = IntersectionInfos::new(&xs, 0, &r);
@@ -560,8 +640,8 @@ pub mod tests {
         );
         let xs = Intersections::new().with_intersections(
             vec![
-                Intersection::new(-2.0_f64.sqrt()/2.0, &o),
-                Intersection::new(2.0_f64.sqrt()/2.0, &o)
+                Intersection::new(-2.0_f64.sqrt()/2.0, o),
+                Intersection::new(2.0_f64.sqrt()/2.0, o)
             ]
         );
         let comps = IntersectionInfos::new(&xs, 1, &r);
@@ -593,10 +673,10 @@ pub mod tests {
         );
         let xs = Intersections::new().with_intersections(
             vec![
-                Intersection::new(-0.9899, &a),
-                Intersection::new(-0.4899, &b),
-                Intersection::new(0.4899, &b),
-                Intersection::new(0.9899, &a)
+                Intersection::new(-0.9899, a),
+                Intersection::new(-0.4899, b),
+                Intersection::new(0.4899, b),
+                Intersection::new(0.9899, a)
             ]
         );
         let comps = IntersectionInfos::new(&xs, 2, &r);
@@ -679,4 +759,81 @@ pub mod tests {
         let comps = IntersectionInfos::new(&xs, 0, &r);
         assert_eq!(w.shade_hit(&comps, 5), Color::new(0.93391, 0.69643, 0.69243));
     }
+
+    #[test]
+    fn a_transparent_instance_in_a_hierarchy_is_shaded_like_the_object_it_shares() {
+        let glass = Material::default()
+            .with_transparency(1.0)
+            .with_refractive_index(1.5);
+        let spheres: Vec<Object> = (0..4)
+            .map(|i| Object::new(Shape::Sphere(Sphere::default()))
+                .with_material(glass.clone())
+                .with_translation(5.0, 0.0, 3.0 * i as f64)
+                .transform()
+            )
+            .collect();
+        let sphere = Arc::new(Object::new(Shape::Sphere(Sphere::default())).with_material(glass));
+        let instances = (0..4)
+            .map(|i| Object::new(Shape::Instance(Instance::new(sphere.clone())))
+                .with_translation(5.0, 0.0, 3.0 * i as f64)
+                .transform()
+            )
+            .collect();
+        let group = Object::new(Shape::Group(Group::new().with_objects(instances)))
+            .flatten(&SahBuilder::new());
+        let r = Ray::new(dvec3(5.5, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+
+        let mut w = default_world();
+        w.push_object(group);
+        let color = w.color_at(&r, 5);
+        assert!(color.is_some());
+
+        let mut w = default_world();
+        spheres.into_iter().for_each(|s| w.push_object(s));
+        assert_eq!(color, w.color_at(&r, 5));
+    }
+
+    #[test]
+    fn the_world_builds_a_hierarchy_over_its_objects() {
+        let spheres = (0..20)
+            .map(|i| Object::new(Shape::Sphere(Sphere::default()))
+                .with_translation(3.0 * i as f64, 0.0, 0.0)
+                .transform()
+            )
+            .collect();
+        let mut w = World::new().with_objects(spheres);
+        w.push_object(
+            Object::new(Shape::Plane(Plane::default()))
+                .with_translation(0.0, -1.0, 0.0)
+                .transform()
+        );
+
+        let r = Ray::new(dvec3(30.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        let xs = w.intersects(&r);
+        assert_eq!(xs.count(), 2);
+        assert_eq!(xs[0].t(), 4.0);
+        assert!(w.bvh().node_count() > 1);
+
+        let r = Ray::new(dvec3(100.0, 5.0, 0.0), dvec3(0.0, -1.0, 0.0));
+        assert_eq!(w.intersects(&r).count(), 1);
+    }
+
+    #[test]
+    fn the_world_hierarchy_is_rebuilt_after_a_change() {
+        let mut w = default_world();
+        let r = Ray::new(dvec3(10.0, 0.0, -5.0), dvec3(0.0, 0.0, 1.0));
+        assert_eq!(w.intersects(&r).count(), 0);
+
+        w.push_object(
+            Object::new(Shape::Sphere(Sphere::default()))
+                .with_translation(10.0, 0.0, 0.0)
+                .transform()
+        );
+        assert_eq!(w.intersects(&r).count(), 2);
+
+        *w.object_mut(2).unwrap() = Object::new(Shape::Sphere(Sphere::default()))
+            .with_translation(-10.0, 0.0, 0.0)
+            .transform();
+        assert_eq!(w.intersects(&r).count(), 0);
+    }
 }
\ No newline at end of file
@@ -1,12 +1,65 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, path::Path};
 
 use glam::DVec3;
-use yaml_rust::{Yaml, yaml::Hash};
+use yaml_rust::{Yaml, yaml::Hash, ScanError};
 
-use crate::{Object, Camera, transformations::{self, Transform}, Color, shapes::{Sphere, Plane, Cube, Group, Shape}, Material, pattern::{PatternObject, PlainPattern, StrippedPattern, RingPattern, CheckerPattern, GradientPattern}, Pattern, lights::{Light, PointLight, AreaLight}};
+use crate::{Object, Camera, camera::{ApertureShape, AdaptiveSampling, Projection, FisheyeMapping}, sampler::Sampler, filter::Filter, tile::Tile, transformations::{self, Transform, Transformable, Motion}, Color, shapes::{Sphere, Plane, Cube, Cylinder, Cone, Triangle, SmoothTriangle, Group, Csg, CsgOperation, Shape}, ObjLoader, ObjError, Material, pattern::{PatternObject, PlainPattern, StrippedPattern, RingPattern, CheckerPattern, GradientPattern}, Pattern, texture::{UvPattern, UvMapping, UvCheckers, UvAlignCheck, UvImage, TextureFilter, TextureWrap, TextureMapPattern, CubeMapPattern}, lights::{Light, PointLight, AreaLight}};
 
 extern crate yaml_rust;
 
+/// error raised while loading a scene, the path locates the offending
+/// element in the document, like `[2].material.pattern.type`
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Parse(ScanError),
+    UnknownType { path: String, name: String },
+    MissingKey { path: String, key: String },
+    InvalidValue { path: String, expected: &'static str },
+    UnknownDefine { path: String, name: String },
+    Image { path: String, error: image::ImageError },
+    Obj { path: String, error: ObjError },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "unable to read the scene: {}", error),
+            SceneError::Parse(error) => write!(f, "invalid yaml: {}", error),
+            SceneError::UnknownType { path, name } => write!(f, "{}: unknown type '{}'", path, name),
+            SceneError::MissingKey { path, key } => write!(f, "{}: missing key '{}'", path, key),
+            SceneError::InvalidValue { path, expected } => write!(f, "{}: expected {}", path, expected),
+            SceneError::UnknownDefine { path, name } => write!(f, "{}: unknown define '{}'", path, name),
+            SceneError::Image { path, error } => write!(f, "{}: unable to load the image: {}", path, error),
+            SceneError::Obj { path, error } => write!(f, "{}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneError::Io(error) => Some(error),
+            SceneError::Parse(error) => Some(error),
+            SceneError::Image { error, .. } => Some(error),
+            SceneError::Obj { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(error: std::io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<ScanError> for SceneError {
+    fn from(error: ScanError) -> Self {
+        SceneError::Parse(error)
+    }
+}
+
 pub struct YamlLoader {
     objects: Vec<Object>,
     lights: Vec<Light>,
@@ -16,9 +69,19 @@ pub struct YamlLoader {
 type Defines<'a> = HashMap<&'a str, &'a Hash>;
 
 impl YamlLoader {
-    pub fn load_from_str(source: &str) -> Self {
-        let docs = yaml_rust::yaml::YamlLoader::load_from_str(source).unwrap();
-        let doc = &docs[0];
+    /// the files the scene refers to are looked for next to it
+    pub fn load_from_path<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        let source = std::fs::read_to_string(&path)?;
+        Self::load(&source, path.as_ref().parent().unwrap_or(Path::new("")))
+    }
+
+    /// the files the scene refers to are looked for from the working directory
+    pub fn load_from_str(source: &str) -> Result<Self, SceneError> {
+        Self::load(source, Path::new(""))
+    }
+
+    fn load(source: &str, directory: &Path) -> Result<Self, SceneError> {
+        let docs = yaml_rust::yaml::YamlLoader::load_from_str(source)?;
 
         let mut camera = None;
         let mut objects = Vec::default();
@@ -26,41 +89,39 @@ impl YamlLoader {
 
         let mut defines: Defines = HashMap::default();
 
-        let elems = doc.as_vec().expect("The yaml should be an array of elements to add to the scene");
-        for elem in elems {
-            let hash = elem.as_hash().unwrap();
+        // an empty document is an empty scene
+        let elems = match docs.first() {
+            Some(doc) => Self::unwrap_vec(doc, "")?.as_slice(),
+            None => &[],
+        };
+        for (index, elem) in elems.iter().enumerate() {
+            let path = format!("[{}]", index);
+            let hash = Self::unwrap_hash(elem, &path)?;
             
-            let _ = match Self::load_str_from_hash(hash, "define") {
-                Some(define_name) => defines.insert(define_name, hash),
-                None => None,
-            };
+            if let Some(define_name) = Self::load_str_from_hash(hash, "define", &path)? {
+                defines.insert(define_name, hash);
+            }
 
-            match Self::load_str_from_hash(hash, "add") {
-                Some(add_value) => {
-                    match add_value {
-                        "camera" => {
-                            camera = Some(Self::load_camera(&hash));
-                        }
-                        "point-light" | "area-light" => {
-                            lights.push(Self::load_light(&hash));
-                        }
-                        "sphere" | "plane" | "cube" | "triangle" | "group" => {
-                            objects.push(Self::load_object(&hash, &defines).expect("Unable to load object"));
-                        }
-                        &_ => {
-                            panic!("Unsupported entity to add to the scene")
-                        }
+            if let Some(add_value) = Self::load_str_from_hash(hash, "add", &path)? {
+                match add_value {
+                    "camera" => {
+                        camera = Some(Self::load_camera(hash, &path)?);
                     }
-                },
-                None => (),
+                    "point-light" | "area-light" => {
+                        lights.push(Self::load_light(hash, &path)?);
+                    }
+                    _ => {
+                        objects.push(Self::load_object(hash, &defines, directory, None, &path)?);
+                    }
+                }
             }
         }
         
-        YamlLoader {
+        Ok(YamlLoader {
             objects,
             lights,
             camera
-        }
+        })
     }
 
     pub fn objects(&self) -> &Vec<Object> {
@@ -75,455 +136,695 @@ impl YamlLoader {
         self.camera.as_ref()
     }
 
-    fn load_camera(hash: &Hash) -> Camera {
+    fn load_camera(hash: &Hash, path: &str) -> Result<Camera, SceneError> {
         let default = Camera::new(100, 100, 1.0);
 
-        let from = Self::load_dvec3_from_hash(hash, "from");
-        let to = Self::load_dvec3_from_hash(hash, "to");
-        let up = Self::load_dvec3_from_hash(hash, "up");
+        let from = Self::load_dvec3_from_hash(hash, "from", path)?;
+        let to = Self::load_dvec3_from_hash(hash, "to", path)?;
+        let up = Self::load_dvec3_from_hash(hash, "up", path)?;
         
-        let transform = match from.is_none() || to.is_none() || up.is_none() {
-            true => *default.transform(),
-            false => transformations::view_transform(from.unwrap(), to.unwrap(), up.unwrap()),
+        let transform = match (from, to, up) {
+            (Some(from), Some(to), Some(up)) => transformations::view_transform(from, to, up),
+            _ => *default.transform(),
         };
 
-        Camera::new(
-            Self::load_i64_from_hash(hash, "width").expect("Camera is missing the width parameter") as usize,
-            Self::load_i64_from_hash(hash, "height").expect("Camera is missing the height parameter") as usize,
-            Self::load_f64_from_hash(hash, "field-of-view").expect("Camera is missing the field-of-view parameter"),
-        )
+        let width = Self::require(Self::load_usize_from_hash(hash, "width", path)?, path, "width")?;
+        let height = Self::require(Self::load_usize_from_hash(hash, "height", path)?, path, "height")?;
+        // the view of an orthographic camera replaces the field of view, its
+        // height keeps the pixels square by default
+        let projection = match Self::load_str_from_hash(hash, "projection", path)? {
+            Some("perspective") | None => Projection::Perspective,
+            Some("orthographic") => {
+                let view_width = Self::require(Self::load_f64_from_hash(hash, "view-width", path)?, path, "view-width")?;
+                Projection::Orthographic {
+                    view_width,
+                    view_height: Self::load_f64_from_hash(hash, "view-height", path)?
+                        .unwrap_or(view_width * height as f64 / width as f64),
+                }
+            }
+            Some("equirectangular") => Projection::Equirectangular,
+            Some("fisheye") => Projection::Fisheye(
+                match Self::load_str_from_hash(hash, "fisheye-mapping", path)? {
+                    Some("equidistant") | None => FisheyeMapping::Equidistant,
+                    Some("equisolid") => FisheyeMapping::Equisolid,
+                    Some(name) => return Err(Self::unknown_type(path, "fisheye-mapping", name)),
+                }
+            ),
+            Some(name) => return Err(Self::unknown_type(path, "projection", name)),
+        };
+        let fov = match projection {
+            Projection::Perspective | Projection::Fisheye(_) => {
+                Self::require(Self::load_f64_from_hash(hash, "field-of-view", path)?, path, "field-of-view")?
+            }
+            _ => Self::load_f64_from_hash(hash, "field-of-view", path)?.unwrap_or(default.field_of_view()),
+        };
+
+        let camera = Camera::new(width, height, fov)
+        .with_projection(projection)
         .with_transform(transform)
-        .with_antialiasing(
-            Self::load_i64_from_hash(hash, "antialiasing").unwrap_or(default.antialiasing() as i64) as usize,
+        // antialiasing is the side of a grid of samples
+        .with_samples_per_pixel(
+            match Self::load_usize_from_hash(hash, "samples-per-pixel", path)? {
+                Some(samples) => samples,
+                None => Self::load_usize_from_hash(hash, "antialiasing", path)?
+                    .map_or(default.samples_per_pixel(), |level| level * level),
+            }
         )
+        .with_sampler(
+            match Self::load_str_from_hash(hash, "sampler", path)? {
+                Some("regular") => Sampler::Regular,
+                Some("stratified") => Sampler::Stratified,
+                Some("random") => Sampler::Random,
+                Some("halton") => Sampler::Halton,
+                Some("sobol") => Sampler::Sobol,
+                Some(name) => return Err(Self::unknown_type(path, "sampler", name)),
+                None => default.sampler(),
+            }
+        )
+        .with_filter(
+            match Self::load_str_from_hash(hash, "filter", path)? {
+                Some("box") => Filter::Box,
+                Some("tent") => Filter::Tent,
+                Some("gaussian") => Filter::Gaussian,
+                Some("mitchell") => Filter::Mitchell,
+                Some(name) => return Err(Self::unknown_type(path, "filter", name)),
+                None => default.filter(),
+            }
+        )
+        .with_tile_size(
+            Self::load_usize_from_hash(hash, "tile-size", path)?.unwrap_or(default.tile_size())
+        )
+        .with_aperture(
+            Self::load_f64_from_hash(hash, "aperture", path)?.unwrap_or(default.aperture())
+        )
+        .with_focal_distance(
+            Self::load_f64_from_hash(hash, "focal-distance", path)?.unwrap_or(default.focal_distance())
+        )
+        // focal-samples is the name of the setting before motion blur shared it
+        .with_blur_samples(
+            match Self::load_usize_from_hash(hash, "blur-samples", path)? {
+                Some(samples) => samples,
+                None => Self::load_usize_from_hash(hash, "focal-samples", path)?.unwrap_or(default.blur_samples()),
+            }
+        )
+        .with_shutter(
+            Self::load_f64_from_hash(hash, "shutter-open", path)?.unwrap_or(default.shutter().0),
+            Self::load_f64_from_hash(hash, "shutter-close", path)?.unwrap_or(default.shutter().1),
+        )
+        .with_aperture_shape(
+            match Self::load_usize_from_hash(hash, "aperture-blades", path)? {
+                Some(blades) if blades >= 3 => ApertureShape::Polygon(blades),
+                Some(_) => return Err(SceneError::InvalidValue {
+                    path: Self::key_path(path, "aperture-blades"),
+                    expected: "at least 3 blades",
+                }),
+                None => default.aperture_shape(),
+            }
+        );
+
+        // a crop window in pixels, or in fractions of the image
+        let camera = match Self::load_from_hash(hash, "crop", path, Self::unwrap_quad)? {
+            Some((x, y, width, height)) => camera.with_crop(Tile::new(x as usize, y as usize, width as usize, height as usize)),
+            None => match Self::load_from_hash(hash, "crop-window", path, Self::unwrap_quad)? {
+                Some((x0, y0, x1, y1)) => camera.with_crop_window(x0, y0, x1, y1),
+                None => camera,
+            },
+        };
+
+        // more samples are spent on the noisy pixels up to the maximum
+        Ok(match Self::load_usize_from_hash(hash, "max-samples-per-pixel", path)? {
+            Some(max_samples) => camera.with_adaptive_sampling(
+                max_samples,
+                Self::load_f64_from_hash(hash, "adaptive-threshold", path)?.unwrap_or(AdaptiveSampling::DEFAULT_THRESHOLD),
+            ),
+            None => camera,
+        })
     }
 
-    fn load_light(hash: &Hash) -> Light {
-        match Self::load_str_from_hash(hash, "add").expect("The light type should be a string") {
+    fn load_light(hash: &Hash, path: &str) -> Result<Light, SceneError> {
+        let dvec3 = |key| Self::require(Self::load_dvec3_from_hash(hash, key, path)?, path, key);
+        let usize = |key| Self::require(Self::load_usize_from_hash(hash, key, path)?, path, key);
+        let intensity = Self::require(Self::load_color_from_hash(hash, "intensity", path)?, path, "intensity")?;
+
+        match Self::require(Self::load_str_from_hash(hash, "add", path)?, path, "add")? {
             "point-light" => {
-                Light::PointLight(PointLight::new(
-                    Self::load_dvec3_from_hash(hash, "position").expect("The light is missing the position parameter"), 
-                    Self::load_color_from_hash(hash, "intensity").expect("The light is missing the intensity parameter")
-                ))
+                Ok(Light::PointLight(PointLight::new(
+                    dvec3("position")?, 
+                    intensity
+                )))
             }
             "area-light" => {
-                Light::AreaLight(AreaLight::new(
-                    Self::load_dvec3_from_hash(hash, "corner").expect("The light is missing the corner parameter"), 
-                    Self::load_dvec3_from_hash(hash, "uvec").expect("The light is missing the uvec parameter"), 
-                    Self::load_i64_from_hash(hash, "usteps").expect("The light is missing the usteps parameter") as usize, 
-                    Self::load_dvec3_from_hash(hash, "vvec").expect("The light is missing the vvec parameter"),
-                    Self::load_i64_from_hash(hash, "vsteps").expect("The light is missing the vsteps parameter") as usize, 
-                    Self::load_color_from_hash(hash, "intensity").expect("The light is missing the intensity parameter"),
-                ))
-            }
-            &_ => {
-                panic!("Unsupported light type")
+                Ok(Light::AreaLight(AreaLight::new(
+                    dvec3("corner")?, 
+                    dvec3("uvec")?, 
+                    usize("usteps")?, 
+                    dvec3("vvec")?,
+                    usize("vsteps")?, 
+                    intensity,
+                )))
             }
+            name => Err(Self::unknown_type(path, "add", name))
         }
-        
     }
 
-    fn load_object(hash: &Hash, defines: &Defines) -> Option<Object> {
-        let mut object = None;
-        match Self::load_str_from_hash(hash, "add").expect("The shape should be a string") {
+    /// inherited is the material of a parent group or csg propagating it
+    fn load_object(hash: &Hash, defines: &Defines, directory: &Path, inherited: Option<&Material>, path: &str) -> Result<Object, SceneError> {
+        let dvec3 = |key| Self::require(Self::load_dvec3_from_hash(hash, key, path)?, path, key);
+        let own_material = hash.contains_key(&Yaml::from_str("material"));
+        let material = match (own_material, inherited) {
+            (false, Some(inherited)) => inherited.clone(),
+            _ => Self::load_material(hash, defines, directory, path)?,
+        };
+        // children without a material of their own inherit this one, a group
+        // without a material keeps handing down the one it inherited
+        let propagated = match Self::load_bool_from_hash(hash, "propagate-material", path)? {
+            Some(true) => Some(&material),
+            Some(false) => None,
+            None => inherited.filter(|_| !own_material),
+        };
+
+        let object = match Self::require(Self::load_str_from_hash(hash, "add", path)?, path, "add")? {
             "sphere" => {
-                object = Some(Object::new(Shape::Sphere(Sphere::default())));
+                Object::new(Shape::Sphere(Sphere::default()))
             }
             "plane" => {
-                object = Some(Object::new(Shape::Plane(Plane::default())));
+                Object::new(Shape::Plane(Plane::default()))
             }
             "cube" => {
-                object = Some(Object::new(Shape::Cube(Cube::default())));
+                Object::new(Shape::Cube(Cube::default()))
+            }
+            "cylinder" => {
+                let default = Cylinder::default();
+                Object::new(Shape::Cylinder(
+                    Cylinder::new()
+                    .with_min(Self::load_f64_from_hash(hash, "min", path)?.unwrap_or(default.min()))
+                    .with_max(Self::load_f64_from_hash(hash, "max", path)?.unwrap_or(default.max()))
+                    .with_closed(Self::load_bool_from_hash(hash, "closed", path)?.unwrap_or(default.closed()))
+                ))
+            }
+            "cone" => {
+                let default = Cone::default();
+                Object::new(Shape::Cone(
+                    Cone::new()
+                    .with_min(Self::load_f64_from_hash(hash, "min", path)?.unwrap_or(default.min()))
+                    .with_max(Self::load_f64_from_hash(hash, "max", path)?.unwrap_or(default.max()))
+                    .with_closed(Self::load_bool_from_hash(hash, "closed", path)?.unwrap_or(default.closed()))
+                ))
             }
             "triangle" => {
-                //object = Object::new(Shape::Triangle(Triangle::default()));
+                Object::new(Shape::Triangle(
+                    Triangle::new(dvec3("p1")?, dvec3("p2")?, dvec3("p3")?)
+                ))
+            }
+            "smooth-triangle" => {
+                Object::new(Shape::SmoothTriangle(
+                    SmoothTriangle::new(
+                        dvec3("p1")?, dvec3("p2")?, dvec3("p3")?,
+                        dvec3("n1")?, dvec3("n2")?, dvec3("n3")?
+                    )
+                ))
+            }
+            "obj" => {
+                let file = Self::require(Self::load_str_from_hash(hash, "file", path)?, path, "file")?;
+                let mut meshes = ObjLoader::load_from_path(directory.join(file))
+                    .map_err(|error| SceneError::Obj {
+                        path: Self::key_path(path, "file"),
+                        error,
+                    })?
+                    .objects();
+                // a single model is kept as a mesh, several ones are grouped
+                let object = match meshes.len() {
+                    1 => meshes.remove(0),
+                    _ => Object::new(Shape::Group(
+                        Group::new()
+                        .with_objects(
+                            meshes
+                            .into_iter()
+                            .map(|mesh| mesh.with_material(material.clone()))
+                            .collect()
+                        )
+                    )),
+                };
+                match Self::load_usize_from_hash(hash, "divide", path)? {
+                    Some(threshold) => object.divide(threshold),
+                    None => object,
+                }
             }
             "group" => {
-                object = Some(Object::new(Shape::Group(Group::default())));
+                // children:
+                //   - add: sphere
+                //   - add: group
+                //     children: [...]
+                let children_path = Self::key_path(path, "children");
+                let mut children = Vec::default();
+                for (index, child) in Self::load_vec_from_hash(hash, "children", path)?.into_iter().flatten().enumerate() {
+                    let path = Self::index_path(&children_path, index);
+                    children.push(Self::load_object(Self::unwrap_hash(child, &path)?, defines, directory, propagated, &path)?);
+                }
+                Object::new(Shape::Group(Group::new().with_objects(children)))
             }
-            &_ => {
-                panic!("Unsupported shape")
+            "csg" => {
+                let operation = match Self::require(Self::load_str_from_hash(hash, "operation", path)?, path, "operation")? {
+                    "union" => CsgOperation::Union,
+                    "intersection" => CsgOperation::Intersection,
+                    "difference" => CsgOperation::Difference,
+                    name => return Err(Self::unknown_type(path, "operation", name)),
+                };
+                let left = Self::require(Self::load_hash_from_hash(hash, "left", path)?, path, "left")?;
+                let right = Self::require(Self::load_hash_from_hash(hash, "right", path)?, path, "right")?;
+                Object::new(Shape::Csg(
+                    Csg::new(
+                        operation,
+                        Self::load_object(left, defines, directory, propagated, &Self::key_path(path, "left"))?,
+                        Self::load_object(right, defines, directory, propagated, &Self::key_path(path, "right"))?,
+                    )
+                ))
             }
-        }
-
-        let default = Object::new(Shape::Sphere(Sphere::default()));
+            name => return Err(Self::unknown_type(path, "add", name)),
+        };
 
-        object
-        .map(|o| {
-            o
-            .with_material(
-                Self::load_material(hash, defines)
-            )
-            .with_transform(
-                Self::load_transform(hash, defines)
-            )
-            .with_shadow(
-                Self::load_bool_from_hash(hash, "shadow").unwrap_or(default.shadow())
-            )
+        // the children of groups and csgs are the ones tested by shadow rays,
+        // so an object casting no shadow hands the flag down to them
+        let mut object = match Self::load_bool_from_hash(hash, "shadow", path)? {
+            Some(false) => object.with_material(material).with_propagated_shadow(false),
+            _ => object.with_material(material),
+        };
+        // groups and csgs hand the transform down to their children
+        object.apply_transform(
+            Self::load_transform(hash, defines, path)?
+        );
+        // motion:
+        //   - time: 1.0
+        //     transform: [...]
+        Ok(match Self::load_vec_from_hash(hash, "motion", path)? {
+            Some(keyframes) => object.with_motion(Self::load_motion(keyframes, defines, &Self::key_path(path, "motion"))?),
+            None => object,
         })
     }
 
-    fn load_material(hash: &Hash, defines: &Defines) -> Material {
+    fn load_motion(keyframes: &[Yaml], defines: &Defines, path: &str) -> Result<Motion, SceneError> {
+        let mut motion = Motion::default();
+        for (index, keyframe) in keyframes.iter().enumerate() {
+            let path = Self::index_path(path, index);
+            let keyframe = Self::unwrap_hash(keyframe, &path)?;
+            motion = motion.with_keyframe(
+                Self::require(Self::load_f64_from_hash(keyframe, "time", &path)?, &path, "time")?,
+                Self::load_transform(keyframe, defines, &path)?
+            );
+        }
+        Ok(motion)
+    }
+
+    fn load_material(hash: &Hash, defines: &Defines, directory: &Path, path: &str) -> Result<Material, SceneError> {
         /// Extends material hash with define's values
-        fn extend_with_defines(defines: &Defines, name: &str, hash: &mut Hash) {
-            defines
-            .get(name)
-            .map(|define_hash| {
-                match define_hash.get(&Yaml::from_str("extend")) {
-                    Some(extend) => match extend.as_str() {
-                        Some(define_name) => {
-                            extend_with_defines(defines, define_name, hash);
-                        }
-                        None => panic!("The extend should have a name"),
-                    },
-                    None => (),
-                }
-            
-                hash.extend(define_hash.get(&Yaml::from_str("value")).unwrap().as_hash().unwrap().clone());
-            });
+        fn extend_with_defines(defines: &Defines, name: &str, hash: &mut Hash, path: &str) -> Result<(), SceneError> {
+            let define_hash = defines.get(name).ok_or_else(|| SceneError::UnknownDefine {
+                path: path.to_string(),
+                name: name.to_string(),
+            })?;
+            if let Some(extend) = YamlLoader::load_str_from_hash(define_hash, "extend", path)? {
+                extend_with_defines(defines, extend, hash, path)?;
+            }
+        
+            let value = YamlLoader::require(YamlLoader::load_hash_from_hash(define_hash, "value", path)?, path, "value")?;
+            hash.extend(value.clone());
+            Ok(())
         }
 
         let default = Material::default();
 
-        match hash.get(&Yaml::from_str("material")) {
-            Some(material_yaml) => {
-                let mut material_hash = Hash::new();
-                match material_yaml.as_str() {
-                    Some(define_name) => {
-                        extend_with_defines(defines, define_name, &mut material_hash);
-                    },
-                    None => material_hash = material_yaml.as_hash().unwrap().clone(),
-                }
+        let material_yaml = match hash.get(&Yaml::from_str("material")) {
+            Some(material_yaml) => material_yaml,
+            None => return Ok(default),
+        };
 
-                Material::default()
-                    .with_ambient(
-                        Self::load_f64_from_hash(&material_hash, "ambient")
-                        .unwrap_or(default.ambient())
-                    )
-                    .with_diffuse(
-                        Self::load_f64_from_hash(&material_hash, "diffuse")
-                        .unwrap_or(default.diffuse())
-                    )
-                    .with_specular(
-                        Self::load_f64_from_hash(&material_hash, "specular")
-                        .unwrap_or(default.specular())
-                    )
-                    .with_shininess(
-                        Self::load_f64_from_hash(&material_hash, "shininess")
-                        .unwrap_or(default.shininess()))
-                    .with_reflective(
-                        Self::load_f64_from_hash(&material_hash, "reflective")
-                        .unwrap_or(default.reflective()))
-                    .with_transparency(
-                            Self::load_f64_from_hash(&material_hash, "transparency")
-                            .unwrap_or(default.transparency()))
-                    .with_refractive_index(
-                        Self::load_f64_from_hash(&material_hash, "refractive-index")
-                        .unwrap_or(default.refractive_index()))
-                    .with_pattern(
-                        Self::load_pattern(&material_hash, defines)
-                        .unwrap_or(default.pattern().clone()))
+        let path = Self::key_path(path, "material");
+        let material_hash = match material_yaml.as_str() {
+            Some(define_name) => {
+                let mut material_hash = Hash::new();
+                extend_with_defines(defines, define_name, &mut material_hash, &path)?;
+                material_hash
             },
-            None => default,
-        }
+            None => Self::unwrap_hash(material_yaml, &path)?.clone(),
+        };
+
+        let f64 = |key, default| -> Result<f64, SceneError> {
+            Ok(Self::load_f64_from_hash(&material_hash, key, &path)?.unwrap_or(default))
+        };
+
+        Ok(
+            Material::default()
+                .with_ambient(f64("ambient", default.ambient())?)
+                .with_diffuse(f64("diffuse", default.diffuse())?)
+                .with_specular(f64("specular", default.specular())?)
+                .with_shininess(f64("shininess", default.shininess())?)
+                .with_reflective(f64("reflective", default.reflective())?)
+                .with_transparency(f64("transparency", default.transparency())?)
+                .with_refractive_index(f64("refractive-index", default.refractive_index())?)
+                .with_pattern(
+                    Self::load_pattern(&material_hash, defines, directory, &path)?
+                    .unwrap_or(default.pattern().clone()))
+        )
     }
 
-    fn load_pattern(hash: &Hash, defines: &Defines) -> Option<PatternObject> {
+    fn load_pattern(hash: &Hash, defines: &Defines, directory: &Path, path: &str) -> Result<Option<PatternObject>, SceneError> {
         // if there is a color value, its considered like a plane pattern with this color
-        if let Some(color) = Self::load_color_from_hash(hash, "color") {
-            return Some(PatternObject::new(
+        if let Some(color) = Self::load_color_from_hash(hash, "color", path)? {
+            return Ok(Some(PatternObject::new(
                 crate::Pattern::Plain(PlainPattern::new(color))
-            ));
+            )));
         }
 
-        let mut pattern_object = None;
-
-        match Self::load_hash_from_hash(hash, "pattern") {
-            Some(pattern_hash) => {
-                match Self::load_str_from_hash(pattern_hash, "type").expect("The pattern type should be a string") {
-                    "stripes" => {
-                        let colors = Self::load_vec_from_hash(pattern_hash, "colors").expect("The pattern colors should be a vec");
-                        pattern_object = Some(
-                            PatternObject::new(
-                                Pattern::Stripped(
-                                    StrippedPattern::new(
-                                        Self::load_color_from_vec(colors[0].as_vec().expect("A color should be a vec")), 
-                                        Self::load_color_from_vec(colors[1].as_vec().expect("A color should be a vec"))
-                                    )
-                                )
-                            )
-                        )
-                    },
-                    "rings" => {
-                        let colors = pattern_hash.get(&Yaml::from_str("colors")).unwrap().as_vec().unwrap();
-                        pattern_object = Some(
-                            PatternObject::new(
-                                Pattern::Ring(
-                                    RingPattern::new(
-                                        Self::load_color_from_vec(colors[0].as_vec().expect("A color should be a vec")), 
-                                        Self::load_color_from_vec(colors[1].as_vec().expect("A color should be a vec"))
-                                    )
-                                )
-                            )
-                        )
-                    },
-                    "checkers" => {
-                        let colors = pattern_hash.get(&Yaml::from_str("colors")).unwrap().as_vec().unwrap();
-                        pattern_object = Some(
-                            PatternObject::new(
-                                Pattern::Checker(
-                                    CheckerPattern::new(
-                                        Self::load_color_from_vec(colors[0].as_vec().expect("A color should be a vec")), 
-                                        Self::load_color_from_vec(colors[1].as_vec().expect("A color should be a vec"))
-                                    )
-                                )
-                            )
+        let pattern_hash = match Self::load_hash_from_hash(hash, "pattern", path)? {
+            Some(pattern_hash) => pattern_hash,
+            None => return Ok(None),
+        };
+
+        let path = &Self::key_path(path, "pattern");
+        let colors = || -> Result<(Color, Color), SceneError> {
+            let colors = Self::require(Self::load_vec_from_hash(pattern_hash, "colors", path)?, path, "colors")?;
+            Self::load_color_pair(colors, &Self::key_path(path, "colors"))
+        };
+
+        let pattern = match Self::require(Self::load_str_from_hash(pattern_hash, "type", path)?, path, "type")? {
+            "stripes" => {
+                let (c0, c1) = colors()?;
+                Pattern::Stripped(StrippedPattern::new(c0, c1))
+            },
+            "rings" => {
+                let (c0, c1) = colors()?;
+                Pattern::Ring(RingPattern::new(c0, c1))
+            },
+            "checkers" => {
+                let (c0, c1) = colors()?;
+                Pattern::Checker(CheckerPattern::new(c0, c1))
+            },
+            "gradient" => {
+                let (c0, c1) = colors()?;
+                Pattern::Gradient(GradientPattern::new(c0, c1))
+            },
+            "map" => {
+                let mapping = Self::require(Self::load_str_from_hash(pattern_hash, "mapping", path)?, path, "mapping")?;
+                match (mapping, Self::load_hash_from_hash(pattern_hash, "uv_pattern", path)?) {
+                    // one uv pattern for each face
+                    ("cube", None) => {
+                        let face = |name| Self::load_uv_pattern(
+                            Self::require(Self::load_hash_from_hash(pattern_hash, name, path)?, path, name)?,
+                            directory,
+                            &Self::key_path(path, name)
+                        );
+                        Pattern::CubeMap(
+                            CubeMapPattern::new(face("left")?, face("front")?, face("right")?, face("back")?, face("up")?, face("down")?)
                         )
-                    },
-                    "gradient" => {
-                        let colors = pattern_hash.get(&Yaml::from_str("colors")).unwrap().as_vec().unwrap();
-                        pattern_object = Some(
-                            PatternObject::new(
-                                Pattern::Gradient(
-                                    GradientPattern::new(
-                                        Self::load_color_from_vec(colors[0].as_vec().expect("A color should be a vec")), 
-                                        Self::load_color_from_vec(colors[1].as_vec().expect("A color should be a vec"))
-                                    )
-                                )
+                    }
+                    (mapping, Some(uv_pattern)) => {
+                        Pattern::TextureMap(
+                            TextureMapPattern::new(
+                                Self::load_uv_pattern(uv_pattern, directory, &Self::key_path(path, "uv_pattern"))?,
+                                Self::load_uv_mapping(mapping, path)?
                             )
                         )
-                    },
-                    &_ => {
-                        panic!("Unsupported pattern")
                     }
+                    (_, None) => return Err(Self::missing_key(path, "uv_pattern")),
                 }
-    
-                pattern_object
-                .map(|o| {
-                    o
-                    .with_transform(
-                        Self::load_transform(pattern_hash, defines)
+            },
+            // shortcut for an image uv pattern, planar by default
+            "image" => {
+                let mapping = Self::load_str_from_hash(pattern_hash, "mapping", path)?.unwrap_or("planar");
+                Pattern::TextureMap(
+                    TextureMapPattern::new(Self::load_uv_pattern(pattern_hash, directory, path)?, Self::load_uv_mapping(mapping, path)?)
+                )
+            },
+            name => return Err(Self::unknown_type(path, "type", name)),
+        };
+
+        Ok(Some(
+            PatternObject::new(pattern)
+            .with_transform(
+                Self::load_transform(pattern_hash, defines, path)?
+            )
+        ))
+    }
+
+    fn load_uv_pattern(hash: &Hash, directory: &Path, path: &str) -> Result<UvPattern, SceneError> {
+        let pattern = match Self::require(Self::load_str_from_hash(hash, "type", path)?, path, "type")? {
+            "checkers" => {
+                let colors = Self::require(Self::load_vec_from_hash(hash, "colors", path)?, path, "colors")?;
+                let (c0, c1) = Self::load_color_pair(colors, &Self::key_path(path, "colors"))?;
+                UvPattern::Checkers(
+                    UvCheckers::new(
+                        Self::require(Self::load_f64_from_hash(hash, "width", path)?, path, "width")?,
+                        Self::require(Self::load_f64_from_hash(hash, "height", path)?, path, "height")?,
+                        c0,
+                        c1
                     )
-                })
+                )
             },
-            None => pattern_object,
+            "align_check" => {
+                let colors = Self::require(Self::load_hash_from_hash(hash, "colors", path)?, path, "colors")?;
+                let colors_path = Self::key_path(path, "colors");
+                let color = |name| Self::require(Self::load_color_from_hash(colors, name, &colors_path)?, &colors_path, name);
+                UvPattern::AlignCheck(
+                    UvAlignCheck::new(color("main")?, color("ul")?, color("ur")?, color("bl")?, color("br")?)
+                )
+            },
+            "image" => {
+                let file = Self::require(Self::load_str_from_hash(hash, "file", path)?, path, "file")?;
+                let srgb = Self::load_bool_from_hash(hash, "srgb", path)?.unwrap_or(true);
+                let filter = match Self::load_str_from_hash(hash, "filter", path)?.unwrap_or("nearest") {
+                    "nearest" => TextureFilter::Nearest,
+                    "bilinear" => TextureFilter::Bilinear,
+                    name => return Err(Self::unknown_type(path, "filter", name)),
+                };
+                let wrap = match Self::load_str_from_hash(hash, "wrap", path)?.unwrap_or("repeat") {
+                    "repeat" => TextureWrap::Repeat,
+                    "clamp" => TextureWrap::Clamp,
+                    name => return Err(Self::unknown_type(path, "wrap", name)),
+                };
+                let image = UvImage::load(directory.join(file), srgb).map_err(|error| SceneError::Image {
+                    path: Self::key_path(path, "file"),
+                    error,
+                })?;
+                UvPattern::Image(
+                    image
+                    .with_filter(filter)
+                    .with_wrap(wrap)
+                )
+            },
+            name => return Err(Self::unknown_type(path, "type", name)),
+        };
+        Ok(pattern)
+    }
+
+    fn load_uv_mapping(name: &str, path: &str) -> Result<UvMapping, SceneError> {
+        match name {
+            "spherical" => Ok(UvMapping::Spherical),
+            "planar" => Ok(UvMapping::Planar),
+            "cylindrical" => Ok(UvMapping::Cylindrical),
+            "cube" => Ok(UvMapping::Cubic),
+            name => Err(Self::unknown_type(path, "mapping", name)),
         }
     }
 
-    fn load_transform(hash: &Hash, defines: &Defines) -> Transform {
+    fn load_transform(hash: &Hash, defines: &Defines, path: &str) -> Result<Transform, SceneError> {
         /// Extends transform array with define's values
-        fn extend_with_defines(defines: &Defines, name: &str, vec: &mut Vec<Yaml>) {
-            defines
-            .get(name)
-            .map(|define_hash| {
-                match define_hash.get(&Yaml::from_str("extend")) {
-                    Some(extend) => match extend.as_str() {
-                        Some(define_name) => {
-                            extend_with_defines(defines, define_name, vec);
-                        }
-                        None => panic!("The extend should have a name"),
-                    },
-                    None => (),
-                }
-            
-                match define_hash.get(&Yaml::from_str("value")).unwrap().as_vec() {
-                    Some(values) => {
-                        for value in values {
-                            match value.as_str() {
-                                Some(define_name) => {
-                                    extend_with_defines(defines, define_name, vec);
-                                },
-                                None => (),
-                            }
-                        }
+        fn extend_with_defines(defines: &Defines, name: &str, vec: &mut Vec<Yaml>, path: &str) -> Result<(), SceneError> {
+            let define_hash = defines.get(name).ok_or_else(|| SceneError::UnknownDefine {
+                path: path.to_string(),
+                name: name.to_string(),
+            })?;
+            if let Some(extend) = YamlLoader::load_str_from_hash(define_hash, "extend", path)? {
+                extend_with_defines(defines, extend, vec, path)?;
+            }
+
+            let values = YamlLoader::require(YamlLoader::load_vec_from_hash(define_hash, "value", path)?, path, "value")?;
+            extend_with_values(defines, values, vec, path)
+        }
+
+        /// Keeps the transformations and replaces the define names by their values
+        fn extend_with_values(defines: &Defines, values: &[Yaml], vec: &mut Vec<Yaml>, path: &str) -> Result<(), SceneError> {
+            for (index, value) in values.iter().enumerate() {
+                match value.as_str() {
+                    Some(define_name) => {
+                        extend_with_defines(defines, define_name, vec, &YamlLoader::index_path(path, index))?;
                     },
-                    None => (),
+                    None => vec.push(value.clone()),
                 }
-
-                vec.extend(define_hash.get(&Yaml::from_str("value")).unwrap().as_vec().unwrap().clone());
-            });
+            }
+            Ok(())
         }
 
         let mut transform = Transform::default();
 
-        match hash.get(&Yaml::from_str("transform")) {
-            Some(transform_yaml) => {
-                let mut transform_vec = Vec::new();
-
-                // transform:
-                //   - other-transform
-                match transform_yaml.as_vec() {
-                    Some(values) => {
-                        for value in values {
-                            match value.as_str() {
-                                Some(define_name) => {
-                                    extend_with_defines(defines, define_name, &mut transform_vec);
-                                },
-                                None => transform_vec.push(value.clone()),
-                            }
-                        }
-                    },
-                    None => (),
-                }
+        let transform_yaml = match hash.get(&Yaml::from_str("transform")) {
+            Some(transform_yaml) => transform_yaml,
+            None => return Ok(transform),
+        };
 
-                // transform: other-transform 
-                match transform_yaml.as_str() {
-                    Some(define_name) => {
-                        extend_with_defines(defines, define_name, &mut transform_vec);
-                    },
-                    None => (),
-                }
+        let path = Self::key_path(path, "transform");
+        let mut transform_vec = Vec::new();
 
-                for transformation in transform_vec.into_iter() {
-                    if let Some(values) = transformation.as_vec() {
-                        let operation = &values[0];
-                        match operation.as_str().unwrap() {
-                            "translate" => {
-                                transform = transform.with_translation(
-                                    Self::unwrap_f64(&values[1]),
-                                    Self::unwrap_f64(&values[2]),
-                                    Self::unwrap_f64(&values[3])
-                                );
-                            }
-                            "scale" => {
-                                transform = transform.with_scale(
-                                    Self::unwrap_f64(&values[1]),
-                                    Self::unwrap_f64(&values[2]),
-                                    Self::unwrap_f64(&values[3])
-                                );
-                            }
-                            "rotate-x" => {
-                                transform = transform.with_rotation_x(
-                                    Self::unwrap_f64(&values[1])
-                                );
-                            }
-                            "rotate-y" => {
-                                transform = transform.with_rotation_y(
-                                    Self::unwrap_f64(&values[1])
-                                );
-                            }
-                            "rotate-z" => {
-                                transform = transform.with_rotation_z(
-                                    Self::unwrap_f64(&values[1])
-                                );
-                            }
-                            &_ => {
-                                panic!("Unsupported transform operation")
-                            }
-                        };
-                    }
-                }
-            },
-            None => (),
+        match transform_yaml.as_str() {
+            // transform: other-transform 
+            Some(define_name) => extend_with_defines(defines, define_name, &mut transform_vec, &path)?,
+            // transform:
+            //   - other-transform
+            //   - [ translate, 1, 2, 3 ]
+            None => extend_with_values(defines, Self::unwrap_vec(transform_yaml, &path)?, &mut transform_vec, &path)?,
         }
-        transform
+
+        for transformation in transform_vec.iter() {
+            let values = Self::unwrap_vec(transformation, &path)?;
+            let value = |index: usize| match values.get(index) {
+                Some(value) => Self::unwrap_f64(value, &path),
+                None => Err(SceneError::InvalidValue { path: path.clone(), expected: "a transformation and its parameters" }),
+            };
+            transform = match values.first().map(|operation| Self::unwrap_str(operation, &path)).transpose()? {
+                Some("translate") => transform.with_translation(value(1)?, value(2)?, value(3)?),
+                Some("scale") => transform.with_scale(value(1)?, value(2)?, value(3)?),
+                Some("rotate-x") => transform.with_rotation_x(value(1)?),
+                Some("rotate-y") => transform.with_rotation_y(value(1)?),
+                Some("rotate-z") => transform.with_rotation_z(value(1)?),
+                Some(name) => return Err(Self::unknown_type(&path, "operation", name)),
+                None => return Err(SceneError::InvalidValue { path: path.clone(), expected: "a transformation and its parameters" }),
+            };
+        }
+        Ok(transform)
     }
 
-    fn load_str_from_hash<'a>(hash: &'a Hash, key: &str) -> Option<&'a str> {
-        hash
-        .get(&Yaml::from_str(key))
-        .map(|yaml| Self::unwrap_str(yaml))
+    fn key_path(path: &str, key: &str) -> String {
+        format!("{}.{}", path, key)
     }
 
-    fn load_i64_from_hash(hash: &Hash, key: &str) -> Option<i64> {
-        hash
-        .get(&Yaml::from_str(key))
-        .map(|yaml| Self::unwrap_i64(yaml))
+    fn index_path(path: &str, index: usize) -> String {
+        format!("{}[{}]", path, index)
     }
 
-    fn load_f64_from_hash(hash: &Hash, key: &str) -> Option<f64> {
-        hash
-        .get(&Yaml::from_str(key))
-        .map(|yaml| Self::unwrap_f64(yaml))
+    fn missing_key(path: &str, key: &str) -> SceneError {
+        SceneError::MissingKey { path: path.to_string(), key: key.to_string() }
+    }
+
+    fn unknown_type(path: &str, key: &str, name: &str) -> SceneError {
+        SceneError::UnknownType { path: Self::key_path(path, key), name: name.to_string() }
+    }
+
+    fn require<T>(value: Option<T>, path: &str, key: &str) -> Result<T, SceneError> {
+        value.ok_or_else(|| Self::missing_key(path, key))
     }
 
-    fn load_bool_from_hash(hash: &Hash, key: &str) -> Option<bool> {
+    fn load_from_hash<'a, T>(hash: &'a Hash, key: &str, path: &str, unwrap: fn(&'a Yaml, &str) -> Result<T, SceneError>) -> Result<Option<T>, SceneError> {
         hash
         .get(&Yaml::from_str(key))
-        .map(|yaml| Self::unwrap_bool(yaml))
+        .map(|yaml| unwrap(yaml, &Self::key_path(path, key)))
+        .transpose()
     }
 
-    fn load_dvec3_from_hash(hash: &Hash, key: &str) -> Option<DVec3> {
-        hash.get(&Yaml::from_str(key)).map(|yaml| {
-            let vec = yaml.as_vec().unwrap();
-            DVec3::new(
-                Self::unwrap_f64(&vec[0]),
-                Self::unwrap_f64(&vec[1]),
-                Self::unwrap_f64(&vec[2])
-            )
-        })
+    fn load_str_from_hash<'a>(hash: &'a Hash, key: &str, path: &str) -> Result<Option<&'a str>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_str)
     }
 
-    fn load_color_from_hash(hash: &Hash, key: &str) -> Option<Color> {
-        hash.get(&Yaml::from_str(key)).map(|yaml| {
-            Self::load_color_from_vec(yaml.as_vec().unwrap())
-        })
+    fn load_usize_from_hash(hash: &Hash, key: &str, path: &str) -> Result<Option<usize>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_usize)
     }
 
-    fn load_hash_from_hash<'a>(hash: &'a Hash, key: &'a str) -> Option<&'a Hash> {
-        hash
-        .get(&Yaml::from_str(key))
-        .map(|yaml| Self::unwrap_hash(yaml))
+    fn load_f64_from_hash(hash: &Hash, key: &str, path: &str) -> Result<Option<f64>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_f64)
     }
 
-    fn load_vec_from_hash<'a>(hash: &'a Hash, key: &'a str) -> Option<&'a Vec<Yaml>> {
-        hash
-        .get(&Yaml::from_str(key))
-        .map(|yaml| Self::unwrap_vec(yaml))
+    fn load_bool_from_hash(hash: &Hash, key: &str, path: &str) -> Result<Option<bool>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_bool)
     }
 
-    fn load_color_from_vec(vec: &Vec<Yaml>) -> Color {
-        Color::new(
-            Self::unwrap_f64(&vec[0]),
-            Self::unwrap_f64(&vec[1]),
-            Self::unwrap_f64(&vec[2])
-        )
+    fn load_dvec3_from_hash(hash: &Hash, key: &str, path: &str) -> Result<Option<DVec3>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_dvec3)
     }
 
-    fn unwrap_str(yaml: &Yaml) -> &str {
-        match yaml.as_str() {
-            Some(value) => value,
-            None => panic!("Unwrapping str failed, the value is not a str"),
-        }
+    fn load_color_from_hash(hash: &Hash, key: &str, path: &str) -> Result<Option<Color>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_color)
+    }
+
+    fn load_hash_from_hash<'a>(hash: &'a Hash, key: &str, path: &str) -> Result<Option<&'a Hash>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_hash)
+    }
+
+    fn load_vec_from_hash<'a>(hash: &'a Hash, key: &str, path: &str) -> Result<Option<&'a Vec<Yaml>>, SceneError> {
+        Self::load_from_hash(hash, key, path, Self::unwrap_vec)
     }
 
-    fn unwrap_i64(yaml: &Yaml) -> i64 {
-        match yaml.as_i64() {
-            Some(value) => value,
-            None => panic!("Unwrapping i64 failed, the value is not a i64"),
+    fn load_color_pair(colors: &[Yaml], path: &str) -> Result<(Color, Color), SceneError> {
+        match colors {
+            [c0, c1, ..] => Ok((
+                Self::unwrap_color(c0, &Self::index_path(path, 0))?,
+                Self::unwrap_color(c1, &Self::index_path(path, 1))?
+            )),
+            _ => Err(SceneError::InvalidValue { path: path.to_string(), expected: "two colors" }),
         }
     }
 
-    fn unwrap_f64(yaml: &Yaml) -> f64 {
+    fn unwrap_str<'a>(yaml: &'a Yaml, path: &str) -> Result<&'a str, SceneError> {
+        yaml.as_str().ok_or_else(|| SceneError::InvalidValue { path: path.to_string(), expected: "a string" })
+    }
+
+    fn unwrap_usize(yaml: &Yaml, path: &str) -> Result<usize, SceneError> {
+        yaml.as_i64()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| SceneError::InvalidValue { path: path.to_string(), expected: "a positive integer" })
+    }
+
+    fn unwrap_f64(yaml: &Yaml, path: &str) -> Result<f64, SceneError> {
         match yaml.as_f64() {
-            Some(value) => value,
+            Some(value) => Ok(value),
             None => match yaml.as_i64() {
-                Some(value) => value as f64,
-                None => panic!("Unwrapping f64 failed, the value is not a f64 or i64"),
+                Some(value) => Ok(value as f64),
+                None => Err(SceneError::InvalidValue { path: path.to_string(), expected: "a number" }),
             }
         }
     }
 
-    fn unwrap_bool(yaml: &Yaml) -> bool {
-        match yaml.as_bool() {
-            Some(value) => value,
-            None => panic!("Unwrapping bool failed, the value is not a bool"),
-        }
+    fn unwrap_bool(yaml: &Yaml, path: &str) -> Result<bool, SceneError> {
+        yaml.as_bool().ok_or_else(|| SceneError::InvalidValue { path: path.to_string(), expected: "a boolean" })
     }
 
-    fn unwrap_vec(yaml: &Yaml) -> &Vec<Yaml> {
-        match yaml.as_vec() {
-            Some(value) => value,
-            None => panic!("Unwrapping vec failed, the value is not a vec"),
+    fn unwrap_triple(yaml: &Yaml, path: &str) -> Result<(f64, f64, f64), SceneError> {
+        match yaml.as_vec().map(|vec| vec.as_slice()) {
+            Some([x, y, z]) => Ok((
+                Self::unwrap_f64(x, &Self::index_path(path, 0))?,
+                Self::unwrap_f64(y, &Self::index_path(path, 1))?,
+                Self::unwrap_f64(z, &Self::index_path(path, 2))?
+            )),
+            _ => Err(SceneError::InvalidValue { path: path.to_string(), expected: "an array of 3 numbers" }),
         }
     }
 
-    fn unwrap_hash(yaml: &Yaml) -> &Hash {
-        match yaml.as_hash() {
-            Some(value) => value,
-            None => panic!("Unwrapping hash failed, the value is not an hash"),
+    fn unwrap_quad(yaml: &Yaml, path: &str) -> Result<(f64, f64, f64, f64), SceneError> {
+        match yaml.as_vec().map(|vec| vec.as_slice()) {
+            Some([a, b, c, d]) => Ok((
+                Self::unwrap_f64(a, &Self::index_path(path, 0))?,
+                Self::unwrap_f64(b, &Self::index_path(path, 1))?,
+                Self::unwrap_f64(c, &Self::index_path(path, 2))?,
+                Self::unwrap_f64(d, &Self::index_path(path, 3))?
+            )),
+            _ => Err(SceneError::InvalidValue { path: path.to_string(), expected: "an array of 4 numbers" }),
         }
     }
+
+    fn unwrap_dvec3(yaml: &Yaml, path: &str) -> Result<DVec3, SceneError> {
+        let (x, y, z) = Self::unwrap_triple(yaml, path)?;
+        Ok(DVec3::new(x, y, z))
+    }
+
+    fn unwrap_color(yaml: &Yaml, path: &str) -> Result<Color, SceneError> {
+        let (r, g, b) = Self::unwrap_triple(yaml, path)?;
+        Ok(Color::new(r, g, b))
+    }
+
+    fn unwrap_vec<'a>(yaml: &'a Yaml, path: &str) -> Result<&'a Vec<Yaml>, SceneError> {
+        yaml.as_vec().ok_or_else(|| SceneError::InvalidValue { path: path.to_string(), expected: "an array" })
+    }
+
+    fn unwrap_hash<'a>(yaml: &'a Yaml, path: &str) -> Result<&'a Hash, SceneError> {
+        yaml.as_hash().ok_or_else(|| SceneError::InvalidValue { path: path.to_string(), expected: "a hash" })
+    }
 }
 
 
@@ -535,6 +836,13 @@ pub mod tests {
 
     use super::*;
 
+    /// a directory of its own for the files of a test
+    fn scratch_dir(test: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ray_tracer_{}_{}", test, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
     #[test]
     fn importing_a_camera_from_a_yaml_scene() {
         let source = "
@@ -548,7 +856,7 @@ pub mod tests {
               antialiasing: 4
         ";
 
-        let loader = YamlLoader::load_from_str(source);
+        let loader = YamlLoader::load_from_str(source).unwrap();
         let camera = loader.camera();
 
         assert!(camera.is_some());
@@ -556,8 +864,222 @@ pub mod tests {
         assert_eq!(camera.unwrap().height(), 1080);
         assert_eq!(camera.unwrap().field_of_view(), 0.7854);
         assert_eq!(camera.unwrap().antialiasing(), 4);
+        assert_eq!(camera.unwrap().aperture(), 0.0);
+    }
+
+    #[test]
+    fn importing_a_camera_with_focal_blur_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              aperture: 0.2
+              focal-distance: 4.5
+              focal-samples: 16
+              aperture-blades: 6
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let camera = loader.camera().unwrap();
+
+        assert_eq!(camera.aperture(), 0.2);
+        assert_eq!(camera.focal_distance(), 4.5);
+        assert_eq!(camera.focal_samples(), 16);
+        assert_eq!(camera.aperture_shape(), ApertureShape::Polygon(6));
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              aperture-blades: 2
+        ";
+        match YamlLoader::load_from_str(source) {
+            Err(SceneError::InvalidValue { path, .. }) => assert_eq!(path, "[0].aperture-blades"),
+            _ => panic!("two blades should be invalid"),
+        }
+    }
+
+    #[test]
+    fn the_blur_samples_of_a_camera_replace_its_focal_samples() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              shutter-open: 0
+              shutter-close: 0.5
+              blur-samples: 8
+              focal-samples: 16
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let camera = loader.camera().unwrap();
+
+        assert_eq!(camera.blur_samples(), 8);
+        assert_eq!(camera.focal_samples(), 8);
+    }
+
+    #[test]
+    fn importing_a_camera_sampling_its_pixels_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              samples-per-pixel: 12
+              sampler: sobol
+              filter: mitchell
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let camera = loader.camera().unwrap();
+
+        assert_eq!(camera.samples_per_pixel(), 12);
+        assert_eq!(camera.sampler(), Sampler::Sobol);
+        assert_eq!(camera.filter(), Filter::Mitchell);
+        assert_eq!(camera.adaptive_sampling(), None);
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              filter: lanczos
+        ";
+        match YamlLoader::load_from_str(source) {
+            Err(SceneError::UnknownType { path, name }) => {
+                assert_eq!(path, "[0].filter");
+                assert_eq!(name, "lanczos");
+            }
+            _ => panic!("the filter should be unknown"),
+        }
+    }
+
+    #[test]
+    fn importing_a_camera_with_adaptive_sampling_and_tiles_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              samples-per-pixel: 4
+              max-samples-per-pixel: 64
+              tile-size: 32
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let camera = loader.camera().unwrap();
+
+        assert_eq!(camera.samples_per_pixel(), 4);
+        assert_eq!(
+            camera.adaptive_sampling(),
+            Some(AdaptiveSampling { max_samples: 64, threshold: AdaptiveSampling::DEFAULT_THRESHOLD })
+        );
+        assert_eq!(camera.tile_size(), 32);
     }
     
+    #[test]
+    fn importing_a_camera_with_a_crop_window_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              crop: [10, 20, 30, 40]
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        assert_eq!(loader.camera().unwrap().crop(), Some(Tile::new(10, 20, 30, 30)));
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              crop-window: [0.5, 0, 1, 0.5]
+        ";
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        assert_eq!(loader.camera().unwrap().crop(), Some(Tile::new(50, 0, 50, 25)));
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              crop: [10, 20]
+        ";
+        match YamlLoader::load_from_str(source) {
+            Err(SceneError::InvalidValue { path, .. }) => assert_eq!(path, "[0].crop"),
+            _ => panic!("the crop should be invalid"),
+        }
+    }
+
+    #[test]
+    fn importing_an_orthographic_camera_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              projection: orthographic
+              view-width: 8
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        assert_eq!(
+            loader.camera().unwrap().projection(),
+            Projection::Orthographic { view_width: 8.0, view_height: 4.0 }
+        );
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              projection: orthographic
+        ";
+        match YamlLoader::load_from_str(source) {
+            Err(SceneError::MissingKey { key, .. }) => assert_eq!(key, "view-width"),
+            _ => panic!("the view width should be missing"),
+        }
+    }
+
+    #[test]
+    fn importing_panoramic_cameras_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 200
+              height: 100
+              projection: equirectangular
+        ";
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        assert_eq!(loader.camera().unwrap().projection(), Projection::Equirectangular);
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 100
+              field-of-view: 3.5
+              projection: fisheye
+              fisheye-mapping: equisolid
+        ";
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let camera = loader.camera().unwrap();
+        assert_eq!(camera.projection(), Projection::Fisheye(FisheyeMapping::Equisolid));
+        assert_eq!(camera.field_of_view(), 3.5);
+
+        let source = "
+            - add: camera
+              width: 100
+              height: 100
+              projection: fisheye
+        ";
+        match YamlLoader::load_from_str(source) {
+            Err(SceneError::MissingKey { key, .. }) => assert_eq!(key, "field-of-view"),
+            _ => panic!("the field of view should be missing"),
+        }
+    }
+
     #[test]
     fn importing_a_point_light_from_a_yaml_scene() {
         let source = "
@@ -566,7 +1088,7 @@ pub mod tests {
               intensity: [1.5, 1.5, 1.5]
         ";
 
-        let loader = YamlLoader::load_from_str(source);
+        let loader = YamlLoader::load_from_str(source).unwrap();
         let lights = loader.lights();
 
         assert_eq!(lights.len(), 1);
@@ -593,7 +1115,7 @@ pub mod tests {
               shadow: false
         ";
 
-        let loader = YamlLoader::load_from_str(source);
+        let loader = YamlLoader::load_from_str(source).unwrap();
         let objects = loader.objects();
 
         assert_eq!(objects.len(), 1);
@@ -604,7 +1126,7 @@ pub mod tests {
         assert_eq!(objects[0].material().reflective(), 0.3);
         assert_eq!(objects[0].material().transparency(), 0.5);
         assert_eq!(objects[0].material().refractive_index(), 1.5);
-        assert_eq!(objects[0].shadow(), false);
+        assert!(!objects[0].shadow());
 
         let translation = objects[0].transform().translation();
         let scale = objects[0].transform().scale();
@@ -613,6 +1135,420 @@ pub mod tests {
     }
 
 
+    #[test]
+    fn importing_a_cone_from_a_yaml_scene() {
+        let source = "
+            - add: cone
+              min: -1
+              max: 0.5
+              closed: false
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(objects.len(), 1);
+        assert_eq!(
+            *objects[0].shape(), 
+            Shape::Cone(
+                Cone::new()
+                .with_min(-1.0)
+                .with_max(0.5)
+                .with_closed(false)
+            )
+        );
+    }
+
+    #[test]
+    fn importing_a_cylinder_from_a_yaml_scene() {
+        let source = "
+            - add: cylinder
+              min: 0
+              max: 2
+              closed: false
+            - add: cylinder
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            *objects[0].shape(),
+            Shape::Cylinder(
+                Cylinder::new()
+                .with_min(0.0)
+                .with_max(2.0)
+                .with_closed(false)
+            )
+        );
+        assert_eq!(*objects[1].shape(), Shape::Cylinder(Cylinder::default()));
+    }
+
+    #[test]
+    fn importing_triangles_from_a_yaml_scene() {
+        let source = "
+            - add: triangle
+              p1: [0, 1, 0]
+              p2: [-1, 0, 0]
+              p3: [1, 0, 0]
+            - add: smooth-triangle
+              p1: [0, 1, 0]
+              p2: [-1, 0, 0]
+              p3: [1, 0, 0]
+              n1: [0, 1, 0]
+              n2: [-1, 0, 0]
+              n3: [1, 0, 0]
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(objects.len(), 2);
+        assert_eq!(
+            *objects[0].shape(),
+            Shape::Triangle(Triangle::new(dvec3(0.0, 1.0, 0.0), dvec3(-1.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0)))
+        );
+        assert_eq!(
+            *objects[1].shape(),
+            Shape::SmoothTriangle(SmoothTriangle::new(
+                dvec3(0.0, 1.0, 0.0), dvec3(-1.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0),
+                dvec3(0.0, 1.0, 0.0), dvec3(-1.0, 0.0, 0.0), dvec3(1.0, 0.0, 0.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn importing_an_obj_file_from_a_yaml_scene() {
+        let dir = scratch_dir("yaml_obj");
+        std::fs::write(dir.join("strip.obj"), "
+v -3 1 0
+v -3 0 0
+v -2 0 0
+v 2 1 0
+v 2 0 0
+v 3 0 0
+f 1 2 3
+f 4 5 6
+").unwrap();
+        std::fs::write(dir.join("scene.yml"), "
+            - add: obj
+              file: strip.obj
+              material:
+                color: [1, 0, 0]
+            - add: obj
+              file: strip.obj
+              divide: 1
+        ").unwrap();
+
+        let loader = YamlLoader::load_from_path(dir.join("scene.yml")).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(objects.len(), 2);
+        let mesh = match objects[0].shape() {
+            Shape::Mesh(mesh) => mesh,
+            _ => unreachable!(),
+        };
+        assert_eq!(mesh.faces().len(), 2);
+        assert_eq!(objects[0].material().pattern().color_at_object(&objects[0], DVec3::ZERO), Color::red());
+        assert_eq!(objects[0].bvh_stats().leaves, 1);
+        assert_eq!(objects[1].bvh_stats().leaves, 2);
+    }
+
+    #[test]
+    fn loading_a_missing_obj_file_fails() {
+        let source = "
+            - add: obj
+              file: examples/obj/missing.obj
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        match error {
+            SceneError::Obj { path, error } => {
+                assert_eq!(path, "[0].file");
+                assert_eq!(error.error, tobj::LoadError::OpenFileFailed);
+            }
+            _ => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn importing_nested_groups_from_a_yaml_scene() {
+        let source = "
+            - add: group
+              material:
+                color: [1, 0, 0]
+              propagate-material: true
+              transform:
+                - [ translate, 0, 1, 0 ]
+              children:
+                - add: sphere
+                  transform:
+                    - [ scale, 2, 2, 2 ]
+                - add: group
+                  transform:
+                    - [ translate, 3, 0, 0 ]
+                  children:
+                    - add: cube
+                    - add: cube
+                      material:
+                        color: [0, 0, 1]
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(objects.len(), 1);
+        let children = objects[0].shape().as_group().unwrap().objects();
+        assert_eq!(children.len(), 2);
+        let sphere = &children[0];
+        assert_eq!(sphere.transform().scale(), dvec3(2.0, 2.0, 2.0));
+        assert_eq!(sphere.transform().translation(), dvec3(0.0, 1.0, 0.0));
+        assert_eq!(sphere.material().pattern().color_at_object(sphere, DVec3::ZERO), Color::red());
+
+        let cubes = children[1].shape().as_group().unwrap().objects();
+        assert_eq!(cubes.len(), 2);
+        assert_eq!(cubes[0].transform().translation(), dvec3(3.0, 1.0, 0.0));
+        assert_eq!(cubes[0].material().pattern().color_at_object(&cubes[0], DVec3::ZERO), Color::red());
+        assert_eq!(cubes[1].material().pattern().color_at_object(&cubes[1], DVec3::ZERO), Color::blue());
+        assert!(objects[0].bounding_box().max().abs_diff_eq(dvec3(4.0, 3.0, 2.0), 0.0001));
+    }
+
+    #[test]
+    fn the_children_of_a_group_keep_their_material_unless_it_is_propagated() {
+        let source = "
+            - add: group
+              material:
+                color: [1, 0, 0]
+              children:
+                - add: sphere
+                - add: group
+                  material:
+                    color: [0, 1, 0]
+                  propagate-material: true
+                  children:
+                    - add: cube
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let group = &loader.objects()[0];
+        assert_eq!(group.material().pattern().color_at_object(group, DVec3::ZERO), Color::red());
+
+        let children = group.shape().as_group().unwrap().objects();
+        assert_eq!(*children[0].material(), Material::default());
+        let cube = &children[1].shape().as_group().unwrap().objects()[0];
+        assert_eq!(cube.material().pattern().color_at_object(cube, DVec3::ZERO), Color::green());
+    }
+
+    #[test]
+    fn a_group_casting_no_shadow_hands_the_flag_down_to_its_children() {
+        let source = "
+            - add: group
+              shadow: false
+              children:
+                - add: sphere
+                - add: group
+                  children:
+                    - add: cube
+            - add: group
+              children:
+                - add: sphere
+                  shadow: false
+                - add: cube
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        let children = objects[0].shape().as_group().unwrap().objects();
+        assert!(!objects[0].shadow());
+        assert!(!children[0].shadow());
+        assert!(!children[1].shape().as_group().unwrap().objects()[0].shadow());
+
+        let children = objects[1].shape().as_group().unwrap().objects();
+        assert!(objects[1].shadow());
+        assert!(!children[0].shadow());
+        assert!(children[1].shadow());
+    }
+
+    #[test]
+    fn loading_an_invalid_group_child_fails() {
+        let source = "
+            - add: group
+              children:
+                - add: group
+                  children:
+                    - add: torus
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(error, SceneError::UnknownType { path, .. } if path == "[0].children[0].children[0].add"));
+    }
+
+    #[test]
+    fn importing_a_csg_from_a_yaml_scene() {
+        let source = "
+            - define: red
+              value:
+                color: [1, 0, 0]
+
+            - add: csg
+              operation: difference
+              left:
+                add: cube
+                material: red
+              right:
+                add: sphere
+                transform:
+                - [ scale, 1.5, 1.5, 1.5 ]
+              transform:
+              - [ translate, 0, 1, 0 ]
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(objects.len(), 1);
+        let csg = objects[0].shape().as_csg().unwrap();
+        assert_eq!(csg.operation(), CsgOperation::Difference);
+        assert_eq!(*csg.left().shape(), Shape::Cube(Cube::default()));
+        assert_eq!(csg.left().material().pattern().color_at_object(csg.left(), DVec3::ZERO), Color::red());
+        assert_eq!(*csg.right().shape(), Shape::Sphere(Sphere::default()));
+        assert_eq!(csg.right().transform().scale(), dvec3(1.5, 1.5, 1.5));
+        assert_eq!(csg.right().transform().translation(), dvec3(0.0, 1.0, 0.0));
+        assert_eq!(csg.left().transform().translation(), dvec3(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn importing_a_moving_object_from_a_yaml_scene() {
+        let source = "
+            - add: camera
+              width: 100
+              height: 50
+              field-of-view: 0.7854
+              shutter-open: 0.0
+              shutter-close: 1.0
+
+            - add: sphere
+              transform:
+              - [ scale, 0.5, 0.5, 0.5 ]
+              motion:
+              - time: 0.0
+              - time: 1.0
+                transform:
+                - [ translate, 2, 0, 0 ]
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        assert_eq!(loader.camera().unwrap().shutter(), (0.0, 1.0));
+
+        let sphere = &loader.objects()[0];
+        let motion = sphere.motion().unwrap();
+        assert_eq!(motion.keyframes().len(), 2);
+        assert_eq!(sphere.transform().scale(), dvec3(0.5, 0.5, 0.5));
+        assert_eq!(sphere.transform_at(0.5).translation(), dvec3(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn importing_texture_maps_from_a_yaml_scene() {
+        let source = "
+            - add: sphere
+              material:
+                pattern:
+                  type: map
+                  mapping: spherical
+                  uv_pattern:
+                    type: checkers
+                    width: 16
+                    height: 8
+                    colors:
+                      - [0, 0.5, 0]
+                      - [1, 1, 1]
+
+            - add: cube
+              material:
+                pattern:
+                  type: map
+                  mapping: cube
+                  left:
+                    type: align_check
+                    colors:
+                      main: [1, 1, 0]
+                      ul: [0, 1, 1]
+                      ur: [1, 0, 0]
+                      bl: [0, 0, 1]
+                      br: [1, 0.5, 0]
+                  front: { type: checkers, width: 2, height: 2, colors: [[0, 0, 0], [1, 1, 1]] }
+                  right: { type: checkers, width: 2, height: 2, colors: [[0, 0, 0], [1, 1, 1]] }
+                  back: { type: checkers, width: 2, height: 2, colors: [[0, 0, 0], [1, 1, 1]] }
+                  up: { type: checkers, width: 2, height: 2, colors: [[0, 0, 0], [1, 1, 1]] }
+                  down: { type: checkers, width: 2, height: 2, colors: [[0, 0, 0], [1, 1, 1]] }
+        ";
+
+        let loader = YamlLoader::load_from_str(source).unwrap();
+        let objects = loader.objects();
+
+        assert_eq!(
+            objects[0].material().pattern().color_at_object(&objects[0], dvec3(0.4315, 0.4670, 0.7719)),
+            Color::white()
+        );
+        assert_eq!(
+            objects[0].material().pattern().color_at_object(&objects[0], dvec3(-0.9654, 0.2552, -0.0534)),
+            Color::new(0.0, 0.5, 0.0)
+        );
+        assert_eq!(
+            objects[1].material().pattern().color_at_object(&objects[1], dvec3(-1.0, 0.9, -0.9)),
+            Color::new(0.0, 1.0, 1.0)
+        );
+        assert_eq!(
+            objects[1].material().pattern().color_at_object(&objects[1], dvec3(-1.0, 0.0, 0.0)),
+            Color::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn importing_an_image_texture_from_a_yaml_scene() {
+        let dir = scratch_dir("yaml_image");
+        let path = dir.join("texture.png");
+        let mut img = image::RgbImage::new(2, 2);
+        img.put_pixel(0, 0, image::Rgb([255, 0, 0]));
+        img.put_pixel(1, 0, image::Rgb([0, 255, 0]));
+        img.put_pixel(0, 1, image::Rgb([0, 0, 255]));
+        img.put_pixel(1, 1, image::Rgb([255, 255, 255]));
+        img.save(&path).unwrap();
+
+        let scene = format!("
+            - add: plane
+              material:
+                pattern:
+                  type: image
+                  file: texture.png
+                  filter: bilinear
+                  wrap: clamp
+
+            - add: sphere
+              material:
+                pattern:
+                  type: map
+                  mapping: spherical
+                  uv_pattern:
+                    type: image
+                    file: {}
+        ", path.display());
+        std::fs::write(dir.join("scene.yml"), scene).unwrap();
+
+        let loader = YamlLoader::load_from_path(dir.join("scene.yml")).unwrap();
+        let objects = loader.objects();
+
+        let plane = &objects[0];
+        let pattern = plane.material().pattern();
+        assert_eq!(pattern.color_at_object(plane, dvec3(0.0, 0.0, 0.9999)), Color::red());
+        assert_eq!(pattern.color_at_object(plane, dvec3(0.5, 0.0, 0.5)), Color::new(0.5, 0.5, 0.5));
+        assert_eq!(pattern.color_at_object(plane, dvec3(0.9999, 0.0, 0.0)), Color::white());
+
+        let sphere = &objects[1];
+        let pattern = sphere.material().pattern();
+        assert_eq!(pattern.color_at_object(sphere, dvec3(-0.5, -0.8, 0.0)), Color::white());
+    }
+
     #[test]
     fn importing_a_yaml_scene_with_material_definitions() {
         let source = "
@@ -632,7 +1568,7 @@ pub mod tests {
               material: b
         ";
 
-        let loader = YamlLoader::load_from_str(source);
+        let loader = YamlLoader::load_from_str(source).unwrap();
         let objects = loader.objects();
 
         assert_eq!(objects[0].material().ambient(), 0.6);
@@ -660,7 +1596,7 @@ pub mod tests {
               transform: b
         ";
 
-        let loader = YamlLoader::load_from_str(source);
+        let loader = YamlLoader::load_from_str(source).unwrap();
         let objects = loader.objects();
 
         assert_eq!(objects[0].transform().scale(), dvec3(2.0, 2.0, 2.0));
@@ -688,10 +1624,125 @@ pub mod tests {
               - b
         ";
 
-        let loader = YamlLoader::load_from_str(source);
+        let loader = YamlLoader::load_from_str(source).unwrap();
         let objects = loader.objects();
 
         assert_eq!(objects[0].transform().scale(), dvec3(2.0, 2.0, 2.0));
         assert_eq!(objects[1].transform().scale(), dvec3(4.0, 4.0, 4.0));
     }
+
+    #[test]
+    fn loading_the_example_scenes() {
+        for scene in ["cover", "reflect-refract", "shadow-glamour-shot", "hexagon"] {
+            let loader = YamlLoader::load_from_path(format!("examples/yaml_scenes/{}.yml", scene)).unwrap();
+            assert!(loader.camera().is_some());
+            assert!(!loader.objects().is_empty());
+        }
+    }
+
+    #[test]
+    fn loading_a_missing_file_fails() {
+        let error = YamlLoader::load_from_path("examples/yaml_scenes/missing.yml").err().unwrap();
+        assert!(matches!(error, SceneError::Io(_)));
+    }
+
+    #[test]
+    fn loading_an_invalid_yaml_fails() {
+        let error = YamlLoader::load_from_str("- add: [sphere").err().unwrap();
+        assert!(matches!(error, SceneError::Parse(_)));
+    }
+
+    #[test]
+    fn loading_an_unknown_type_fails() {
+        let source = "
+            - add: sphere
+            - add: torus
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::UnknownType { ref path, ref name } if path == "[1].add" && name == "torus"
+        ));
+        assert_eq!(error.to_string(), "[1].add: unknown type 'torus'");
+
+        let source = "
+            - add: cube
+              material:
+                pattern:
+                  type: waves
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::UnknownType { ref path, ref name } if path == "[0].material.pattern.type" && name == "waves"
+        ));
+    }
+
+    #[test]
+    fn loading_an_element_with_a_missing_key_fails() {
+        let source = "
+            - add: camera
+              width: 100
+              field-of-view: 0.7854
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::MissingKey { ref path, ref key } if path == "[0]" && key == "height"
+        ));
+    }
+
+    #[test]
+    fn loading_an_ill_typed_value_fails() {
+        let source = "
+            - add: point-light
+              position: [0, 10, -10]
+              intensity: [1, 1]
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::InvalidValue { ref path, .. } if path == "[0].intensity"
+        ));
+
+        let source = "
+            - add: sphere
+              transform:
+                - [ translate, 1, up, 3 ]
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::InvalidValue { ref path, expected: "a number" } if path == "[0].transform"
+        ));
+    }
+
+    #[test]
+    fn loading_an_unknown_define_fails() {
+        let source = "
+            - define: red
+              value:
+                color: [1, 0, 0]
+
+            - add: sphere
+              material: blue
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::UnknownDefine { ref path, ref name } if path == "[1].material" && name == "blue"
+        ));
+
+        let source = "
+            - add: sphere
+              transform:
+                - [ scale, 2, 2, 2 ]
+                - moved
+        ";
+        let error = YamlLoader::load_from_str(source).err().unwrap();
+        assert!(matches!(
+            error,
+            SceneError::UnknownDefine { ref path, ref name } if path == "[0].transform[1]" && name == "moved"
+        ));
+    }
 }
\ No newline at end of file